@@ -14,12 +14,15 @@
 
 //! Structs for building the native representations of circuits, proposals, services and nodes
 
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use crate::admin::messages::{is_valid_circuit_id, is_valid_service_id};
 
 use super::error::BuilderError;
 use super::{
-    AuthorizationType, Circuit, CircuitNode, CircuitProposal, DurabilityType, PersistenceType,
-    ProposalType, ProposedCircuit, ProposedNode, ProposedService, RouteType, Service, VoteRecord,
+    AuthorizationType, Circuit, CircuitNode, CircuitProposal, CircuitStatus, DurabilityType,
+    PersistenceType, ProposalType, ProposedCircuit, ProposedNode, ProposedService, RouteType,
+    Service, VoteRecord,
 };
 
 /// Builder to be used to build a `Circuit`
@@ -33,6 +36,10 @@ pub struct CircuitBuilder {
     durability: Option<DurabilityType>,
     routes: Option<RouteType>,
     circuit_management_type: Option<String>,
+    created_at: Option<SystemTime>,
+    updated_at: Option<SystemTime>,
+    circuit_status: Option<CircuitStatus>,
+    display_name: Option<String>,
 }
 
 impl CircuitBuilder {
@@ -81,6 +88,26 @@ impl CircuitBuilder {
         self.circuit_management_type.clone()
     }
 
+    /// Returns the creation time in the builder
+    pub fn created_at(&self) -> Option<SystemTime> {
+        self.created_at
+    }
+
+    /// Returns the update time in the builder
+    pub fn updated_at(&self) -> Option<SystemTime> {
+        self.updated_at
+    }
+
+    /// Returns the circuit status in the builder
+    pub fn circuit_status(&self) -> Option<CircuitStatus> {
+        self.circuit_status.clone()
+    }
+
+    /// Returns the display name in the builder
+    pub fn display_name(&self) -> Option<String> {
+        self.display_name.clone()
+    }
+
     /// Sets the circuit ID
     ///
     /// # Arguments
@@ -161,10 +188,52 @@ impl CircuitBuilder {
         self
     }
 
+    /// Sets the time the circuit was created
+    ///
+    /// # Arguments
+    ///
+    ///  * `created_at` - The creation time of the circuit
+    pub fn with_created_at(mut self, created_at: SystemTime) -> CircuitBuilder {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Sets the time the circuit was last updated
+    ///
+    /// # Arguments
+    ///
+    ///  * `updated_at` - The update time of the circuit
+    pub fn with_updated_at(mut self, updated_at: SystemTime) -> CircuitBuilder {
+        self.updated_at = Some(updated_at);
+        self
+    }
+
+    /// Sets the circuit status
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_status` - The status of the circuit
+    pub fn with_circuit_status(mut self, circuit_status: &CircuitStatus) -> CircuitBuilder {
+        self.circuit_status = Some(circuit_status.clone());
+        self
+    }
+
+    /// Sets the human-readable name of the circuit
+    ///
+    /// # Arguments
+    ///
+    ///  * `display_name` - The display name of the circuit
+    pub fn with_display_name(mut self, display_name: &str) -> CircuitBuilder {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
     /// Builds a `Circuit`
     ///
     /// Returns an error if the circuit ID, roster, members or circuit management
-    /// type are not set.
+    /// type are not set. If the creation time is not set, it defaults to `UNIX_EPOCH`, and if the
+    /// update time is not set, it defaults to the creation time. If the status is not set, the
+    /// circuit is active.
     pub fn build(self) -> Result<Circuit, BuilderError> {
         let circuit_id = match self.circuit_id {
             Some(circuit_id) if is_valid_circuit_id(&circuit_id) => circuit_id,
@@ -200,6 +269,12 @@ impl CircuitBuilder {
             .circuit_management_type
             .ok_or_else(|| BuilderError::MissingField("circuit_management_type".to_string()))?;
 
+        let created_at = self.created_at.unwrap_or(UNIX_EPOCH);
+
+        let updated_at = self.updated_at.unwrap_or(created_at);
+
+        let circuit_status = self.circuit_status.unwrap_or_default();
+
         let create_circuit_message = Circuit {
             id: circuit_id,
             roster,
@@ -209,6 +284,10 @@ impl CircuitBuilder {
             durability,
             routes,
             circuit_management_type,
+            created_at,
+            updated_at,
+            circuit_status,
+            display_name: self.display_name,
         };
 
         Ok(create_circuit_message)
@@ -469,6 +548,10 @@ impl From<ProposedCircuit> for Circuit {
             durability: circuit.durability,
             routes: circuit.routes,
             circuit_management_type: circuit.circuit_management_type,
+            created_at: UNIX_EPOCH,
+            updated_at: UNIX_EPOCH,
+            circuit_status: CircuitStatus::Active,
+            display_name: None,
         }
     }
 }
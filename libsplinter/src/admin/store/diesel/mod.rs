@@ -94,6 +94,12 @@ impl AdminServiceStore for DieselAdminServiceStore<diesel::pg::PgConnection> {
     }
 
     fn remove_proposal(&self, proposal_id: &str) -> Result<(), AdminServiceStoreError> {
+        AdminServiceStoreOperations::new(&*self.connection_pool.get()?)
+            .remove_proposal(proposal_id)
+            .map(|_| ())
+    }
+
+    fn take_proposal(&self, proposal_id: &str) -> Result<CircuitProposal, AdminServiceStoreError> {
         AdminServiceStoreOperations::new(&*self.connection_pool.get()?).remove_proposal(proposal_id)
     }
 
@@ -179,6 +185,12 @@ impl AdminServiceStore for DieselAdminServiceStore<diesel::sqlite::SqliteConnect
     }
 
     fn remove_proposal(&self, proposal_id: &str) -> Result<(), AdminServiceStoreError> {
+        AdminServiceStoreOperations::new(&*self.connection_pool.get()?)
+            .remove_proposal(proposal_id)
+            .map(|_| ())
+    }
+
+    fn take_proposal(&self, proposal_id: &str) -> Result<CircuitProposal, AdminServiceStoreError> {
         AdminServiceStoreOperations::new(&*self.connection_pool.get()?).remove_proposal(proposal_id)
     }
 
@@ -17,6 +17,7 @@
 //! the requirements for storing data with a diesel backend.
 
 use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::admin::store::diesel::schema::{
     circuit, circuit_member, circuit_proposal, node_endpoint, proposed_circuit, proposed_node,
@@ -25,7 +26,8 @@ use crate::admin::store::diesel::schema::{
 };
 use crate::admin::store::error::AdminServiceStoreError;
 use crate::admin::store::{
-    AuthorizationType, DurabilityType, PersistenceType, ProposalType, RouteType, Vote, VoteRecord,
+    AuthorizationType, CircuitStatus, DurabilityType, PersistenceType, ProposalType, RouteType,
+    Vote, VoteRecord,
 };
 use crate::admin::store::{Circuit, CircuitProposal, ProposedCircuit};
 
@@ -362,6 +364,10 @@ pub struct CircuitModel {
     pub durability: String,
     pub routes: String,
     pub circuit_management_type: String,
+    pub circuit_status: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub display_name: Option<String>,
 }
 
 impl From<&Circuit> for CircuitModel {
@@ -373,10 +379,26 @@ impl From<&Circuit> for CircuitModel {
             durability: String::from(&circuit.durability),
             routes: String::from(&circuit.routes),
             circuit_management_type: circuit.circuit_management_type.clone(),
+            circuit_status: String::from(&circuit.circuit_status),
+            created_at: to_epoch_seconds(circuit.created_at),
+            updated_at: to_epoch_seconds(circuit.updated_at),
+            display_name: circuit.display_name.clone(),
         }
     }
 }
 
+/// Converts a time to the whole seconds since the Unix epoch that are stored in the database
+pub fn to_epoch_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Converts seconds since the Unix epoch stored in the database to a time
+pub fn from_epoch_seconds(seconds: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64)
+}
+
 /// Database model representation of the `members` of a `Circuit`
 #[derive(Debug, PartialEq, Associations, Identifiable, Insertable, Queryable, QueryableByName)]
 #[table_name = "circuit_member"]
@@ -470,6 +492,7 @@ impl TryFrom<String> for AuthorizationType {
     fn try_from(variant: String) -> Result<Self, Self::Error> {
         match variant.as_ref() {
             "Trust" => Ok(AuthorizationType::Trust),
+            "Challenge" => Ok(AuthorizationType::Challenge),
             _ => Err(AdminServiceStoreError::StorageError {
                 context: "Unable to convert string to AuthorizationType".into(),
                 source: None,
@@ -482,6 +505,7 @@ impl From<&AuthorizationType> for String {
     fn from(variant: &AuthorizationType) -> Self {
         match variant {
             AuthorizationType::Trust => String::from("Trust"),
+            AuthorizationType::Challenge => String::from("Challenge"),
         }
     }
 }
@@ -548,3 +572,26 @@ impl From<&RouteType> for String {
         }
     }
 }
+
+impl TryFrom<String> for CircuitStatus {
+    type Error = AdminServiceStoreError;
+    fn try_from(variant: String) -> Result<Self, Self::Error> {
+        match variant.as_ref() {
+            "Active" => Ok(CircuitStatus::Active),
+            "Disbanded" => Ok(CircuitStatus::Disbanded),
+            _ => Err(AdminServiceStoreError::StorageError {
+                context: "Unable to convert string to CircuitStatus".into(),
+                source: None,
+            }),
+        }
+    }
+}
+
+impl From<&CircuitStatus> for String {
+    fn from(variant: &CircuitStatus) -> Self {
+        match variant {
+            CircuitStatus::Active => String::from("Active"),
+            CircuitStatus::Disbanded => String::from("Disbanded"),
+        }
+    }
+}
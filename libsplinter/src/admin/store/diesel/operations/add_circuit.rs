@@ -15,6 +15,7 @@
 //! Provides the "add circuit" operation for the `DieselAdminServiceStore`.
 
 use std::collections::HashMap;
+use std::time::SystemTime;
 
 use diesel::{dsl::insert_into, prelude::*};
 
@@ -47,9 +48,13 @@ impl<'a> AdminServiceStoreAddCircuitOperation
 {
     fn add_circuit(
         &self,
-        circuit: Circuit,
+        mut circuit: Circuit,
         nodes: Vec<CircuitNode>,
     ) -> Result<(), AdminServiceStoreError> {
+        // The circuit's creation time is the time it is first stored
+        circuit.created_at = SystemTime::now();
+        circuit.updated_at = circuit.created_at;
+
         self.conn.transaction::<(), _, _>(|| {
             // Check if the circuit already exists in the `AdminServiceStore`, in which case
             // an error is returned.
@@ -171,9 +176,13 @@ impl<'a> AdminServiceStoreAddCircuitOperation
 {
     fn add_circuit(
         &self,
-        circuit: Circuit,
+        mut circuit: Circuit,
         nodes: Vec<CircuitNode>,
     ) -> Result<(), AdminServiceStoreError> {
+        // The circuit's creation time is the time it is first stored
+        circuit.created_at = SystemTime::now();
+        circuit.updated_at = circuit.created_at;
+
         self.conn.transaction::<(), _, _>(|| {
             // Check if the circuit already exists in the `AdminServiceStore`, in which case
             // an error is returned.
@@ -20,12 +20,12 @@ use std::convert::TryFrom;
 use super::{list_services::AdminServiceStoreListServicesOperation, AdminServiceStoreOperations};
 use crate::admin::store::{
     diesel::{
-        models::{CircuitMemberModel, CircuitModel},
+        models::{from_epoch_seconds, CircuitMemberModel, CircuitModel},
         schema::{circuit, circuit_member},
     },
     error::AdminServiceStoreError,
-    AuthorizationType, Circuit, CircuitBuilder, DurabilityType, PersistenceType, RouteType,
-    Service,
+    AuthorizationType, Circuit, CircuitBuilder, CircuitStatus, DurabilityType, PersistenceType,
+    RouteType, Service,
 };
 
 pub(in crate::admin::store::diesel) trait AdminServiceStoreFetchCircuitOperation {
@@ -73,21 +73,28 @@ where
                 .map(|member| member.node_id.to_string())
                 .collect();
 
-            Ok(Some(
-                CircuitBuilder::new()
-                    .with_circuit_id(&circuit.circuit_id)
-                    .with_roster(&services)
-                    .with_members(&circuit_member)
-                    .with_auth(&AuthorizationType::try_from(circuit.auth)?)
-                    .with_persistence(&PersistenceType::try_from(circuit.persistence)?)
-                    .with_durability(&DurabilityType::try_from(circuit.durability)?)
-                    .with_routes(&RouteType::try_from(circuit.routes)?)
-                    .build()
-                    .map_err(|err| AdminServiceStoreError::StorageError {
-                        context: String::from("Failed to build Circuit"),
-                        source: Some(Box::new(err)),
-                    })?,
-            ))
+            let mut circuit_builder = CircuitBuilder::new()
+                .with_circuit_id(&circuit.circuit_id)
+                .with_roster(&services)
+                .with_members(&circuit_member)
+                .with_auth(&AuthorizationType::try_from(circuit.auth)?)
+                .with_persistence(&PersistenceType::try_from(circuit.persistence)?)
+                .with_durability(&DurabilityType::try_from(circuit.durability)?)
+                .with_routes(&RouteType::try_from(circuit.routes)?)
+                .with_circuit_management_type(&circuit.circuit_management_type)
+                .with_circuit_status(&CircuitStatus::try_from(circuit.circuit_status)?)
+                .with_created_at(from_epoch_seconds(circuit.created_at))
+                .with_updated_at(from_epoch_seconds(circuit.updated_at));
+            if let Some(display_name) = &circuit.display_name {
+                circuit_builder = circuit_builder.with_display_name(display_name);
+            }
+
+            Ok(Some(circuit_builder.build().map_err(|err| {
+                AdminServiceStoreError::StorageError {
+                    context: String::from("Failed to build Circuit"),
+                    source: Some(Box::new(err)),
+                }
+            })?))
         })
     }
 }
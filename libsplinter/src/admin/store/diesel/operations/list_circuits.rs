@@ -24,12 +24,15 @@ use diesel::{
 
 use crate::admin::store::{
     diesel::{
-        models::{CircuitMemberModel, CircuitModel, ServiceArgumentModel, ServiceModel},
+        models::{
+            from_epoch_seconds, CircuitMemberModel, CircuitModel, ServiceArgumentModel,
+            ServiceModel,
+        },
         schema::{circuit, circuit_member, service, service_allowed_node, service_argument},
     },
     error::AdminServiceStoreError,
-    AuthorizationType, Circuit, CircuitBuilder, CircuitPredicate, DurabilityType, PersistenceType,
-    RouteType, Service, ServiceBuilder,
+    AuthorizationType, Circuit, CircuitBuilder, CircuitPredicate, CircuitStatus, DurabilityType,
+    PersistenceType, RouteType, Service, ServiceBuilder,
 };
 
 use super::AdminServiceStoreOperations;
@@ -243,8 +246,15 @@ where
                         .with_auth(&AuthorizationType::try_from(model.auth)?)
                         .with_persistence(&PersistenceType::try_from(model.persistence)?)
                         .with_durability(&DurabilityType::try_from(model.durability)?)
-                        .with_routes(&RouteType::try_from(model.routes)?);
+                        .with_routes(&RouteType::try_from(model.routes)?)
+                        .with_circuit_management_type(&model.circuit_management_type)
+                        .with_circuit_status(&CircuitStatus::try_from(model.circuit_status)?)
+                        .with_created_at(from_epoch_seconds(model.created_at))
+                        .with_updated_at(from_epoch_seconds(model.updated_at));
 
+                    if let Some(display_name) = &model.display_name {
+                        circuit_builder = circuit_builder.with_display_name(display_name);
+                    }
                     if let Some(members) = circuit_members.get(&id) {
                         circuit_builder = circuit_builder.with_members(&members);
                     }
@@ -252,12 +262,21 @@ where
                         circuit_builder = circuit_builder.with_roster(&services);
                     }
 
-                    ret_circuits.push(circuit_builder.build().map_err(|err| {
+                    let circuit = circuit_builder.build().map_err(|err| {
                         AdminServiceStoreError::OperationError {
                             context: String::from("Unable to build Circuit"),
                             source: Some(Box::new(err)),
                         }
-                    })?);
+                    })?;
+
+                    // Status predicates are checked against the built circuit, since a
+                    // circuit must match every status predicate given.
+                    if predicates.iter().all(|predicate| match predicate {
+                        CircuitPredicate::Status(_) => predicate.apply_to_circuit(&circuit),
+                        _ => true,
+                    }) {
+                        ret_circuits.push(circuit);
+                    }
                 }
 
                 Ok(Box::new(ret_circuits.into_iter()))
@@ -26,12 +26,14 @@ use crate::admin::store::{
         schema::circuit_proposal,
     },
     error::AdminServiceStoreError,
+    CircuitProposal,
 };
 
 use super::{fetch_proposal::AdminServiceStoreFetchProposalOperation, AdminServiceStoreOperations};
 
 pub(in crate::admin::store::diesel) trait AdminServiceStoreRemoveProposalOperation {
-    fn remove_proposal(&self, proposal_id: &str) -> Result<(), AdminServiceStoreError>;
+    fn remove_proposal(&self, proposal_id: &str)
+        -> Result<CircuitProposal, AdminServiceStoreError>;
 }
 
 impl<'a, C> AdminServiceStoreRemoveProposalOperation for AdminServiceStoreOperations<'a, C>
@@ -44,23 +46,31 @@ where
         diesel::Queryable<(Text, Text, Text, Text, Text, Text, Binary, Text), C::Backend>,
     VoteRecordModel: diesel::Queryable<(Text, Binary, Text, Text), C::Backend>,
 {
-    fn remove_proposal(&self, proposal_id: &str) -> Result<(), AdminServiceStoreError> {
-        self.conn.transaction::<(), _, _>(|| {
-            // Verify the `proposal` being removed exists
-            self.fetch_proposal(&proposal_id).and_then(|_| {
-                // Remove the `proposal` entry with the matching `proposal_id`, which is represented
-                // in the `circuit_proposal` by the `circuit_id`.
-                // The `circuit_id` foreign key has cascade delete, meaning all related tables
-                // associated to the `circuit` table via the `circuit_id` will be deleted, if the
-                // corresponding `circuit` entry with the matching `circuit_id` is deleted.
-                delete(circuit_proposal::table.find(&proposal_id))
-                    .execute(self.conn)
-                    .map_err(|err| AdminServiceStoreError::QueryError {
-                        context: String::from("Failed to delete CircuitProposal"),
-                        source: Box::new(err),
-                    })?;
-                Ok(())
-            })
+    fn remove_proposal(
+        &self,
+        proposal_id: &str,
+    ) -> Result<CircuitProposal, AdminServiceStoreError> {
+        self.conn.transaction::<CircuitProposal, _, _>(|| {
+            // Fetch the `proposal` being removed, so it can be returned
+            let proposal = self.fetch_proposal(&proposal_id)?.ok_or_else(|| {
+                AdminServiceStoreError::NotFoundError(format!(
+                    "Cannot find circuit proposal with id: {}",
+                    proposal_id
+                ))
+            })?;
+
+            // Remove the `proposal` entry with the matching `proposal_id`, which is represented
+            // in the `circuit_proposal` by the `circuit_id`.
+            // The `circuit_id` foreign key has cascade delete, meaning all related tables
+            // associated to the `circuit` table via the `circuit_id` will be deleted, if the
+            // corresponding `circuit` entry with the matching `circuit_id` is deleted.
+            delete(circuit_proposal::table.find(&proposal_id))
+                .execute(self.conn)
+                .map_err(|err| AdminServiceStoreError::QueryError {
+                    context: String::from("Failed to delete CircuitProposal"),
+                    source: Box::new(err),
+                })?;
+            Ok(proposal)
         })
     }
 }
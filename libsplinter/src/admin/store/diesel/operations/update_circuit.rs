@@ -14,6 +14,8 @@
 
 //! Provides the "update circuit" operation for the `DieselAdminServiceStore`.
 
+use std::time::SystemTime;
+
 use diesel::{
     dsl::{delete, insert_into, update},
     prelude::*,
@@ -23,8 +25,8 @@ use super::AdminServiceStoreOperations;
 use crate::admin::store::{
     diesel::{
         models::{
-            CircuitMemberModel, CircuitModel, ServiceAllowedNodeModel, ServiceArgumentModel,
-            ServiceModel,
+            to_epoch_seconds, CircuitMemberModel, CircuitModel, ServiceAllowedNodeModel,
+            ServiceArgumentModel, ServiceModel,
         },
         schema::{circuit, circuit_member, service, service_allowed_node, service_argument},
     },
@@ -57,7 +59,7 @@ impl<'a> AdminServiceStoreUpdateCircuitOperation
                     ))
                 })?;
 
-            // Update existing `Circuit`
+            // Update existing `Circuit`, keeping the time it was created
             let circuit_model = CircuitModel::from(&circuit);
             update(circuit::table.find(&circuit.id))
                 .set((
@@ -66,6 +68,9 @@ impl<'a> AdminServiceStoreUpdateCircuitOperation
                     circuit::durability.eq(circuit_model.durability),
                     circuit::routes.eq(circuit_model.routes),
                     circuit::circuit_management_type.eq(circuit_model.circuit_management_type),
+                    circuit::circuit_status.eq(circuit_model.circuit_status),
+                    circuit::display_name.eq(circuit_model.display_name),
+                    circuit::updated_at.eq(to_epoch_seconds(SystemTime::now())),
                 ))
                 .execute(self.conn)
                 .map_err(|err| AdminServiceStoreError::QueryError {
@@ -94,6 +99,12 @@ impl<'a> AdminServiceStoreUpdateCircuitOperation
                     context: String::from("Failed to remove old Service arguments"),
                     source: Box::new(err),
                 })?;
+            delete(circuit_member::table.filter(circuit_member::circuit_id.eq(&circuit.id)))
+                .execute(self.conn)
+                .map_err(|err| AdminServiceStoreError::QueryError {
+                    context: String::from("Failed to remove old Circuit members"),
+                    source: Box::new(err),
+                })?;
             // Insert new data associate with the `Circuit`
             let services: Vec<ServiceModel> = Vec::from(&circuit);
             insert_into(service::table)
@@ -153,7 +164,7 @@ impl<'a> AdminServiceStoreUpdateCircuitOperation
                     ))
                 })?;
 
-            // Update existing `Circuit`
+            // Update existing `Circuit`, keeping the time it was created
             let circuit_model = CircuitModel::from(&circuit);
             update(circuit::table.find(&circuit.id))
                 .set((
@@ -162,6 +173,9 @@ impl<'a> AdminServiceStoreUpdateCircuitOperation
                     circuit::durability.eq(circuit_model.durability),
                     circuit::routes.eq(circuit_model.routes),
                     circuit::circuit_management_type.eq(circuit_model.circuit_management_type),
+                    circuit::circuit_status.eq(circuit_model.circuit_status),
+                    circuit::display_name.eq(circuit_model.display_name),
+                    circuit::updated_at.eq(to_epoch_seconds(SystemTime::now())),
                 ))
                 .execute(self.conn)
                 .map_err(|err| AdminServiceStoreError::QueryError {
@@ -190,6 +204,12 @@ impl<'a> AdminServiceStoreUpdateCircuitOperation
                     context: String::from("Failed to remove old Service arguments"),
                     source: Box::new(err),
                 })?;
+            delete(circuit_member::table.filter(circuit_member::circuit_id.eq(&circuit.id)))
+                .execute(self.conn)
+                .map_err(|err| AdminServiceStoreError::QueryError {
+                    context: String::from("Failed to remove old Circuit members"),
+                    source: Box::new(err),
+                })?;
             // Insert new `Circuit` data
             let services: Vec<ServiceModel> = Vec::from(&circuit);
             insert_into(service::table)
@@ -116,6 +116,10 @@ table! {
         durability -> Text,
         routes -> Text,
         circuit_management_type -> Text,
+        circuit_status -> Text,
+        created_at -> BigInt,
+        updated_at -> BigInt,
+        display_name -> Nullable<Text>,
     }
 }
 
@@ -18,14 +18,15 @@
 //!
 //! The public interface includes the trait [`AdminServiceStore`] and structs for
 //! [`Circuit`], [`ProposedCircuit`], [`CircuitNode`], [`ProposedNode`], [`Service`],
-//! [`ProposedService`], and [`CircuitProposal`]. A YAML backed [`YamlAdminServiceStore`] is
-//! also available.
+//! [`ProposedService`], [`CircuitProposal`], and [`AdminStateView`]. A YAML backed
+//! [`YamlAdminServiceStore`] is also available.
 //!
 //! Builders are also provided. The structs are [`CircuitBuilder`], [`ProposedCircuitBuilder`],
 //! [`CircuitNodeBuilder`], [`ProposedNodeBuilder`], [`ServiceBuilder`],
 //! [`ProposedServiceBuilder`], and [`CircuitProposalBuilder`].
 //!
 //! [`AdminServiceStore`]: trait.AdminServiceStore.html
+//! [`AdminStateView`]: struct.AdminStateView.html
 //! [`Circuit`]: struct.Circuit.html
 //! [`ProposedCircuit`]: struct.ProposedCircuit.html
 //! [`CircuitNode`]: struct.CircuitNode.html
@@ -50,9 +51,11 @@ pub mod error;
 pub mod yaml;
 
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::hex::{as_hex, deserialize_hex};
+use crate::hex::{as_hex, deserialize_hex, to_hex};
 
 pub use self::builders::{
     CircuitBuilder, CircuitNodeBuilder, CircuitProposalBuilder, ProposedCircuitBuilder,
@@ -71,6 +74,42 @@ pub struct Circuit {
     durability: DurabilityType,
     routes: RouteType,
     circuit_management_type: String,
+    #[serde(serialize_with = "as_rfc3339")]
+    #[serde(deserialize_with = "deserialize_rfc3339")]
+    #[serde(default = "unix_epoch")]
+    created_at: SystemTime,
+    #[serde(serialize_with = "as_rfc3339")]
+    #[serde(deserialize_with = "deserialize_rfc3339")]
+    #[serde(default = "unix_epoch")]
+    updated_at: SystemTime,
+    #[serde(default)]
+    circuit_status: CircuitStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+}
+
+impl Circuit {
+    /// Returns the time the circuit was first added to the store. Circuits whose creation time is
+    /// not known return `UNIX_EPOCH`.
+    pub fn created_at(&self) -> SystemTime {
+        self.created_at
+    }
+
+    /// Returns the time the circuit was last added or updated in the store. Circuits whose update
+    /// time is not known return their creation time.
+    pub fn updated_at(&self) -> SystemTime {
+        self.updated_at
+    }
+
+    /// Returns the status of the circuit
+    pub fn circuit_status(&self) -> &CircuitStatus {
+        &self.circuit_status
+    }
+
+    /// Returns the human-readable name of the circuit, if it has one
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
 }
 
 /// Native representation of a circuit that is being proposed in a proposal
@@ -98,6 +137,7 @@ pub struct CircuitProposal {
     pub circuit_id: String,
     pub circuit_hash: String,
     pub circuit: ProposedCircuit,
+    #[serde(deserialize_with = "deserialize_votes")]
     pub votes: Vec<VoteRecord>,
     #[serde(serialize_with = "as_hex")]
     #[serde(deserialize_with = "deserialize_hex")]
@@ -110,6 +150,11 @@ impl CircuitProposal {
     pub fn add_vote(&mut self, vote: VoteRecord) {
         self.votes.push(vote);
     }
+
+    /// Returns the public key of the requester as lowercase hex, as it is written to state files
+    pub fn requester_hex(&self) -> String {
+        to_hex(&self.requester)
+    }
 }
 
 /// Native representation of a vote record for a proposal
@@ -120,6 +165,45 @@ pub struct VoteRecord {
     pub voter_node_id: String,
 }
 
+impl VoteRecord {
+    /// Returns the public key of the voter as lowercase hex
+    pub fn public_key_hex(&self) -> String {
+        to_hex(&self.public_key)
+    }
+}
+
+/// Deserializes the votes on a proposal from either a list of vote records or a map of voter node
+/// IDs to their public key and vote. Votes in a map are returned in voter node ID order.
+fn deserialize_votes<'de, D>(deserializer: D) -> Result<Vec<VoteRecord>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct MapVote {
+        public_key: Vec<u8>,
+        vote: Vote,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Votes {
+        List(Vec<VoteRecord>),
+        Map(BTreeMap<String, MapVote>),
+    }
+
+    match serde::Deserialize::deserialize(deserializer)? {
+        Votes::List(votes) => Ok(votes),
+        Votes::Map(votes) => Ok(votes
+            .into_iter()
+            .map(|(voter_node_id, vote)| VoteRecord {
+                public_key: vote.public_key,
+                vote: vote.vote,
+                voter_node_id,
+            })
+            .collect()),
+    }
+}
+
 /// Represents a vote, either accept or reject, for a circuit proposal
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum Vote {
@@ -137,46 +221,51 @@ pub enum ProposalType {
     Destroy,
 }
 
+/// Whether a circuit is in use or has been disbanded
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+pub enum CircuitStatus {
+    #[default]
+    Active,
+    Disbanded,
+}
+
 /// What type of authorization the circuit requires
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum AuthorizationType {
+    #[serde(alias = "trust")]
     Trust,
+    #[serde(alias = "challenge")]
+    Challenge,
 }
 
 /// A circuits message persistence strategy
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
 pub enum PersistenceType {
+    #[serde(alias = "any")]
+    #[default]
     Any,
 }
 
-impl Default for PersistenceType {
-    fn default() -> Self {
-        PersistenceType::Any
-    }
-}
-
 /// A circuits durability requirement
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum DurabilityType {
+    #[serde(alias = "nodurability", alias = "no-durability")]
     NoDurability,
 }
 
 /// How messages are expected to be routed across a circuit
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
 pub enum RouteType {
+    #[serde(alias = "any")]
+    #[default]
     Any,
 }
 
-impl Default for RouteType {
-    fn default() -> Self {
-        RouteType::Any
-    }
-}
-
 /// Native representation of a node included in circuit
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct CircuitNode {
     id: String,
+    #[serde(deserialize_with = "deserialize_endpoints")]
     endpoints: Vec<String>,
 }
 
@@ -193,9 +282,51 @@ impl From<&ProposedNode> for CircuitNode {
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct ProposedNode {
     node_id: String,
+    #[serde(deserialize_with = "deserialize_endpoints")]
     endpoints: Vec<String>,
 }
 
+/// Deserializes node endpoints from either a list of endpoints or a single endpoint. Older state
+/// files may contain a single endpoint string, which is converted to a one-element list.
+fn deserialize_endpoints<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Endpoints {
+        Single(String),
+        List(Vec<String>),
+    }
+
+    match serde::Deserialize::deserialize(deserializer)? {
+        Endpoints::Single(endpoint) => Ok(vec![endpoint]),
+        Endpoints::List(endpoints) => Ok(endpoints),
+    }
+}
+
+/// Serializes a time as an RFC 3339 timestamp
+fn as_rfc3339<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&humantime::format_rfc3339(*time).to_string())
+}
+
+/// Deserializes a time from an RFC 3339 timestamp
+fn deserialize_rfc3339<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let timestamp: String = serde::Deserialize::deserialize(deserializer)?;
+    humantime::parse_rfc3339_weak(&timestamp).map_err(serde::de::Error::custom)
+}
+
+/// Returns the time used when a circuit's creation time is not known
+fn unix_epoch() -> SystemTime {
+    UNIX_EPOCH
+}
+
 /// Native representation of a service that is a part of circuit
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct Service {
@@ -205,6 +336,31 @@ pub struct Service {
     arguments: Vec<(String, String)>,
 }
 
+impl Service {
+    /// Returns the value of the service argument with the given key, if it is set. If a key is
+    /// repeated, the last value is used.
+    ///
+    /// # Arguments
+    ///
+    ///  * `key` - The key of the argument to be returned
+    pub fn argument(&self, key: &str) -> Option<&str> {
+        self.arguments
+            .iter()
+            .rev()
+            .find(|(arg_key, _)| arg_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the service arguments as a map of key to value. If a key is repeated, the last
+    /// value is used.
+    pub fn arguments_map(&self) -> BTreeMap<&str, &str> {
+        self.arguments
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
 impl From<&ProposedService> for Service {
     fn from(proposed_service: &ProposedService) -> Self {
         Service {
@@ -289,9 +445,11 @@ impl PartialOrd for ServiceId {
 }
 
 /// Predicate for filtering the lists of circuits and circuit proposals
+#[derive(Clone, Debug)]
 pub enum CircuitPredicate {
     ManagmentTypeEq(String),
     MembersInclude(Vec<String>),
+    Status(CircuitStatus),
 }
 
 impl CircuitPredicate {
@@ -309,6 +467,7 @@ impl CircuitPredicate {
                 }
                 true
             }
+            CircuitPredicate::Status(status) => &circuit.circuit_status == status,
         }
     }
 
@@ -332,10 +491,20 @@ impl CircuitPredicate {
                 }
                 true
             }
+            // Proposed circuits have not been created yet, so they have no status
+            CircuitPredicate::Status(_) => true,
         }
     }
 }
 
+/// The circuits, proposals and nodes of an admin service store, read at the same time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdminStateView {
+    pub circuits: Vec<Circuit>,
+    pub proposals: Vec<CircuitProposal>,
+    pub nodes: Vec<CircuitNode>,
+}
+
 /// Defines methods for CRUD operations and fetching and listing circuits, proposals, nodes and
 /// services without defining a storage strategy
 pub trait AdminServiceStore: Send + Sync {
@@ -363,9 +532,32 @@ pub trait AdminServiceStore: Send + Sync {
     ///
     ///  * `proposal_id` - The unique ID of the circuit proposal to be removed
     ///
-    ///  Returns an error if a `CircuitProposal` with specified ID does not exist
+    ///  Returns a `NotFoundError` if a `CircuitProposal` with specified ID does not exist
     fn remove_proposal(&self, proposal_id: &str) -> Result<(), AdminServiceStoreError>;
 
+    /// Removes a circuit proposal from the underlying storage, returning the removed proposal
+    ///
+    /// By default the proposal is fetched and then removed with `remove_proposal`, which is not
+    /// atomic. Stores that can remove and return a proposal in a single operation override this.
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_id` - The unique ID of the circuit proposal to be removed
+    ///
+    ///  Returns the removed `CircuitProposal`, or a `NotFoundError` if a `CircuitProposal` with
+    ///  specified ID does not exist
+    fn take_proposal(&self, proposal_id: &str) -> Result<CircuitProposal, AdminServiceStoreError> {
+        let proposal = self.fetch_proposal(proposal_id)?.ok_or_else(|| {
+            AdminServiceStoreError::NotFoundError(format!(
+                "Cannot find circuit proposal with id: {}",
+                proposal_id
+            ))
+        })?;
+        self.remove_proposal(proposal_id)?;
+
+        Ok(proposal)
+    }
+
     /// Fetches a circuit proposal from the underlying storage
     ///
     /// # Arguments
@@ -428,7 +620,7 @@ pub trait AdminServiceStore: Send + Sync {
     /// List all circuits from the underlying storage
     ///
     /// The proposals returned can be filtered by provided `CircuitPredicate`. This enables
-    /// filtering by management type and members.
+    /// filtering by management type, members and status.
     fn list_circuits(
         &self,
         predicates: &[CircuitPredicate],
@@ -474,4 +666,274 @@ pub trait AdminServiceStore: Send + Sync {
         &self,
         circuit_id: &str,
     ) -> Result<Box<dyn ExactSizeIterator<Item = Service>>, AdminServiceStoreError>;
+
+    /// List all circuits, proposals and nodes from the underlying storage
+    ///
+    /// The default implementation calls the individual list operations, so the lists may not
+    /// reflect the same state if the storage changes between calls. Stores that can read the
+    /// lists together should override this method.
+    fn list_all(&self) -> Result<AdminStateView, AdminServiceStoreError> {
+        Ok(AdminStateView {
+            circuits: self.list_circuits(&[])?.collect(),
+            proposals: self.list_proposals(&[])?.collect(),
+            nodes: self.list_nodes()?.collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Validate that service arguments can be looked up by key
+    //
+    // 1. Build a service with `admin_keys` and `peer_services` arguments
+    // 2. Check that `argument` returns the value of `peer_services` and `None` for a missing key
+    // 3. Check that `arguments_map` contains both arguments
+    #[test]
+    fn test_service_arguments() {
+        let service = ServiceBuilder::new()
+            .with_service_id("a000")
+            .with_service_type("scabbard")
+            .with_allowed_nodes(&["acme-node-000".to_string()])
+            .with_arguments(&[
+                ("admin_keys".to_string(), "[\"0357\"]".to_string()),
+                ("peer_services".to_string(), "[\"a001\"]".to_string()),
+            ])
+            .build()
+            .expect("Unable to build service");
+
+        assert_eq!(service.argument("peer_services"), Some("[\"a001\"]"));
+        assert_eq!(service.argument("missing"), None);
+
+        let arguments = service.arguments_map();
+        assert_eq!(arguments.len(), 2);
+        assert_eq!(arguments.get("admin_keys"), Some(&"[\"0357\"]"));
+        assert_eq!(arguments.get("peer_services"), Some(&"[\"a001\"]"));
+    }
+
+    // Validate that the YAML and diesel stores report a missing proposal the same way
+    //
+    // 1. Create an in-memory YAML store and a diesel store backed by an in-memory SQLite database
+    // 2. Check that removing and taking a missing proposal returns a `NotFoundError` from both
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_remove_missing_proposal_error() {
+        use ::diesel::{
+            r2d2::{ConnectionManager, Pool},
+            sqlite::SqliteConnection,
+        };
+
+        use self::diesel::{migrations::run_sqlite_migrations, DieselAdminServiceStore};
+        use self::yaml::YamlAdminServiceStore;
+
+        let yaml_store =
+            YamlAdminServiceStore::from_bytes(b"nodes: {}\ncircuits: {}\n", b"proposals: {}\n")
+                .expect("Unable to create yaml admin store");
+
+        // Each connection is backed by a different in-memory SQLite database, so the pool is
+        // limited to a single connection
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+            .expect("Failed to build connection pool");
+        run_sqlite_migrations(&pool.get().expect("Failed to get connection for migrations"))
+            .expect("Failed to run migrations");
+        let diesel_store = DieselAdminServiceStore::new(pool);
+
+        let stores: [&dyn AdminServiceStore; 2] = [&yaml_store, &diesel_store];
+        for store in stores.iter() {
+            match store.remove_proposal("missing") {
+                Err(AdminServiceStoreError::NotFoundError(_)) => (),
+                res => panic!("Expected a not found error, got {:?}", res),
+            }
+            match store.take_proposal("missing") {
+                Err(AdminServiceStoreError::NotFoundError(_)) => (),
+                res => panic!("Expected a not found error, got {:?}", res),
+            }
+        }
+    }
+
+    // Validate that the diesel store persists a circuit's status
+    //
+    // 1. Add an active circuit to a diesel store backed by an in-memory SQLite database
+    // 2. Update the circuit with a `Disbanded` status
+    // 3. Check that the fetched circuit is disbanded and only a `Disbanded` status predicate
+    //    lists it
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_diesel_circuit_status() {
+        let store = create_sqlite_store();
+
+        let circuit = create_circuit();
+        store
+            .add_circuit(circuit.clone(), create_nodes())
+            .expect("Unable to add circuit");
+
+        let mut disbanded = circuit;
+        disbanded.circuit_status = CircuitStatus::Disbanded;
+        store
+            .update_circuit(disbanded)
+            .expect("Unable to update circuit");
+
+        let fetched = store
+            .fetch_circuit("WBKLF-BBBBB")
+            .expect("Unable to fetch circuit")
+            .expect("Circuit not found");
+        assert_eq!(fetched.circuit_status(), &CircuitStatus::Disbanded);
+
+        let predicates = [
+            CircuitPredicate::ManagmentTypeEq("gameroom".to_string()),
+            CircuitPredicate::MembersInclude(vec!["acme-node-000".to_string()]),
+        ];
+        let active = store
+            .list_circuits(
+                &[
+                    &predicates[..],
+                    &[CircuitPredicate::Status(CircuitStatus::Active)],
+                ]
+                .concat(),
+            )
+            .expect("Unable to list circuits");
+        assert_eq!(active.len(), 0);
+        let disbanded = store
+            .list_circuits(
+                &[
+                    &predicates[..],
+                    &[CircuitPredicate::Status(CircuitStatus::Disbanded)],
+                ]
+                .concat(),
+            )
+            .expect("Unable to list circuits")
+            .collect::<Vec<Circuit>>();
+        assert_eq!(disbanded, vec![fetched]);
+    }
+
+    // Validate that the diesel store records when a circuit is created and updated
+    //
+    // 1. Add a circuit without timestamps to a diesel store backed by an in-memory SQLite
+    //    database
+    // 2. Check that the fetched circuit has a creation time and was last updated at that time
+    // 3. Update the circuit and check that the creation time is kept and the update time is not
+    //    earlier than it
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_diesel_circuit_timestamps() {
+        let store = create_sqlite_store();
+
+        let circuit = create_circuit();
+        store
+            .add_circuit(circuit.clone(), create_nodes())
+            .expect("Unable to add circuit");
+
+        let added = store
+            .fetch_circuit("WBKLF-BBBBB")
+            .expect("Unable to fetch circuit")
+            .expect("Circuit not found");
+        assert_ne!(added.created_at(), UNIX_EPOCH);
+        assert_eq!(added.updated_at(), added.created_at());
+
+        store
+            .update_circuit(circuit)
+            .expect("Unable to update circuit");
+
+        let updated = store
+            .fetch_circuit("WBKLF-BBBBB")
+            .expect("Unable to fetch circuit")
+            .expect("Circuit not found");
+        assert_eq!(updated.created_at(), added.created_at());
+        assert!(updated.updated_at() >= added.created_at());
+    }
+
+    // Validate that the diesel store persists a circuit's display name
+    //
+    // 1. Add a circuit with a display name to a diesel store backed by an in-memory SQLite
+    //    database
+    // 2. Check that the fetched and listed circuits have the display name
+    // 3. Update the circuit without a display name and check that the fetched circuit has none
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_diesel_circuit_display_name() {
+        let store = create_sqlite_store();
+
+        let mut circuit = create_circuit();
+        circuit.display_name = Some("Acme Gameroom".to_string());
+        store
+            .add_circuit(circuit.clone(), create_nodes())
+            .expect("Unable to add circuit");
+
+        let fetched = store
+            .fetch_circuit("WBKLF-BBBBB")
+            .expect("Unable to fetch circuit")
+            .expect("Circuit not found");
+        assert_eq!(fetched.display_name(), Some("Acme Gameroom"));
+
+        let listed = store
+            .list_circuits(&[
+                CircuitPredicate::ManagmentTypeEq("gameroom".to_string()),
+                CircuitPredicate::MembersInclude(vec!["acme-node-000".to_string()]),
+            ])
+            .expect("Unable to list circuits")
+            .collect::<Vec<Circuit>>();
+        assert_eq!(listed, vec![fetched]);
+
+        circuit.display_name = None;
+        store
+            .update_circuit(circuit)
+            .expect("Unable to update circuit");
+
+        let updated = store
+            .fetch_circuit("WBKLF-BBBBB")
+            .expect("Unable to fetch circuit")
+            .expect("Circuit not found");
+        assert_eq!(updated.display_name(), None);
+    }
+
+    /// Creates a diesel store backed by an in-memory SQLite database
+    #[cfg(feature = "sqlite")]
+    fn create_sqlite_store(
+    ) -> self::diesel::DieselAdminServiceStore<::diesel::sqlite::SqliteConnection> {
+        use ::diesel::{
+            r2d2::{ConnectionManager, Pool},
+            sqlite::SqliteConnection,
+        };
+
+        use self::diesel::{migrations::run_sqlite_migrations, DieselAdminServiceStore};
+
+        // Each connection is backed by a different in-memory SQLite database, so the pool is
+        // limited to a single connection
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+            .expect("Failed to build connection pool");
+        run_sqlite_migrations(&pool.get().expect("Failed to get connection for migrations"))
+            .expect("Failed to run migrations");
+        DieselAdminServiceStore::new(pool)
+    }
+
+    fn create_circuit() -> Circuit {
+        let service = ServiceBuilder::new()
+            .with_service_id("a000")
+            .with_service_type("scabbard")
+            .with_allowed_nodes(&["acme-node-000".to_string()])
+            .with_arguments(&[("peer_services".to_string(), "[\"a001\"]".to_string())])
+            .build()
+            .expect("Unable to build service");
+
+        CircuitBuilder::new()
+            .with_circuit_id("WBKLF-BBBBB")
+            .with_roster(&[service])
+            .with_members(&["acme-node-000".to_string()])
+            .with_circuit_management_type("gameroom")
+            .build()
+            .expect("Unable to build circuit")
+    }
+
+    fn create_nodes() -> Vec<CircuitNode> {
+        vec![CircuitNodeBuilder::new()
+            .with_node_id("acme-node-000")
+            .with_endpoints(&["tcps://splinterd-node-acme:8044".to_string()])
+            .build()
+            .expect("Unable to build node")]
+    }
 }
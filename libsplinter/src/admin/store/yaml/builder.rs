@@ -0,0 +1,743 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Builder for the `YamlAdminServiceStore`
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+
+use super::error::YamlAdminStoreError;
+use super::indent::MIN_INDENT;
+use super::journal::Journal;
+use super::wal::WriteAheadLog;
+use super::{
+    CascadeProposals, CircuitValidator, Clock, EnumCase, FileFormat, FileStore, InitOutcome,
+    MembershipPolicy, NodeConflictPolicy, OsFileStore, ProposalConflictPolicy, StatePaths,
+    StoreMetrics, SystemClock, TimestampFormat, WriteRetry, YamlAdminServiceStore, YamlState,
+};
+
+/// The number of write-ahead log records after which the state files are rewritten, if the
+/// interval is not set
+const DEFAULT_CHECKPOINT_INTERVAL: usize = 100;
+
+/// Builder to be used to build a `YamlAdminServiceStore`
+#[derive(Default, Clone)]
+pub struct YamlAdminServiceStoreBuilder {
+    circuit_file_path: Option<String>,
+    proposal_file_path: Option<String>,
+    archive_proposals: bool,
+    metrics: Option<Arc<dyn StoreMetrics>>,
+    expand_paths: bool,
+    cascade_proposals: CascadeProposals,
+    prune_nodes_on_remove: bool,
+    lenient_proposals: bool,
+    strict_schema: Option<bool>,
+    #[cfg(feature = "admin-store-mmap")]
+    mmap_circuit_file: bool,
+    rebuild_on_reload: bool,
+    file_format: FileFormat,
+    enum_case: EnumCase,
+    timestamp_format: TimestampFormat,
+    indent: Option<usize>,
+    proposal_conflict_policy: ProposalConflictPolicy,
+    node_conflict_policy: NodeConflictPolicy,
+    membership_policy: MembershipPolicy,
+    require_known_members: bool,
+    circuit_validators: Vec<Arc<CircuitValidator>>,
+    write_ahead_log_path: Option<String>,
+    journal_directory: Option<String>,
+    checkpoint_interval: Option<usize>,
+    write_retry: WriteRetry,
+    verify_proposal_hashes: bool,
+    clock: Option<Arc<dyn Clock>>,
+    file_store: Option<Arc<dyn FileStore>>,
+    circuit_cache: bool,
+}
+
+impl YamlAdminServiceStoreBuilder {
+    /// Creates a new YAML admin service store builder
+    pub fn new() -> Self {
+        YamlAdminServiceStoreBuilder::default()
+    }
+
+    /// Sets the path to the file that contains circuit state
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_file_path` - The path to the circuit state file
+    pub fn with_circuit_file_path(mut self, circuit_file_path: &str) -> Self {
+        self.circuit_file_path = Some(circuit_file_path.into());
+        self
+    }
+
+    /// Sets the path to the file that contains circuit proposal state
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_file_path` - The path to the circuit proposal state file
+    pub fn with_proposal_file_path(mut self, proposal_file_path: &str) -> Self {
+        self.proposal_file_path = Some(proposal_file_path.into());
+        self
+    }
+
+    /// Sets whether proposals are archived when they are upgraded to a circuit. Archived
+    /// proposals are stored next to the proposal state file, in a file named after it with an
+    /// `archived_` prefix, such as `archived_circuit_proposals.yaml`.
+    ///
+    /// # Arguments
+    ///
+    ///  * `archive_proposals` - Whether upgraded proposals should be archived
+    pub fn with_archived_proposals(mut self, archive_proposals: bool) -> Self {
+        self.archive_proposals = archive_proposals;
+        self
+    }
+
+    /// Sets the metrics hook that receives metrics recorded by the store. By default no metrics
+    /// are recorded.
+    ///
+    /// # Arguments
+    ///
+    ///  * `metrics` - The metrics hook
+    pub fn with_metrics(mut self, metrics: Arc<dyn StoreMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Sets the clock the store reads the current time from when it records the creation and
+    /// update times of circuits. By default the system time is used.
+    ///
+    /// # Arguments
+    ///
+    ///  * `clock` - The clock to read the current time from
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Sets whether a binary cache of the circuit state file is kept next to it, with the same
+    /// name and the extension `.cache`. When the store is created the cache is loaded instead of
+    /// the circuit state file if it was made from the file's current contents; otherwise the file
+    /// is parsed and the cache is replaced. The circuit state file remains the source of truth, so
+    /// it can still be edited by hand. By default no cache is kept.
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_cache` - Whether the circuit cache is kept
+    pub fn with_circuit_cache(mut self, circuit_cache: bool) -> Self {
+        self.circuit_cache = circuit_cache;
+        self
+    }
+
+    /// Sets the file store the circuit, proposal and archived proposal state files are read from
+    /// and written to. By default the files are on the operating system's filesystem. A file store
+    /// cannot be combined with a journal directory, which replaces the files on the operating
+    /// system's filesystem.
+    ///
+    /// # Arguments
+    ///
+    ///  * `file_store` - The file store holding the state files
+    pub fn with_file_store(mut self, file_store: Arc<dyn FileStore>) -> Self {
+        self.file_store = Some(file_store);
+        self
+    }
+
+    /// Sets whether the circuit and proposal file paths are expanded before they are used. When
+    /// enabled, a leading `~` is replaced with the user's home directory and `$VAR` or `${VAR}`
+    /// is replaced with the value of the environment variable `VAR`. By default paths are used
+    /// as provided.
+    ///
+    /// # Arguments
+    ///
+    ///  * `expand_paths` - Whether the file paths should be expanded
+    pub fn with_path_expansion(mut self, expand_paths: bool) -> Self {
+        self.expand_paths = expand_paths;
+        self
+    }
+
+    /// Sets how a proposal with the same ID as a removed circuit is handled. By default, the
+    /// proposal is left in place and a warning is logged.
+    ///
+    /// # Arguments
+    ///
+    ///  * `cascade_proposals` - The policy applied when removing a circuit
+    pub fn with_cascade_proposals(mut self, cascade_proposals: CascadeProposals) -> Self {
+        self.cascade_proposals = cascade_proposals;
+        self
+    }
+
+    /// Sets whether removing a circuit also removes the nodes that are no longer a member of any
+    /// circuit, as with `YamlAdminServiceStore::prune_orphan_nodes`. By default, nodes are kept
+    /// when their circuits are removed.
+    ///
+    /// # Arguments
+    ///
+    ///  * `prune_nodes_on_remove` - Whether orphaned nodes are removed with a circuit
+    pub fn with_prune_nodes_on_remove(mut self, prune_nodes_on_remove: bool) -> Self {
+        self.prune_nodes_on_remove = prune_nodes_on_remove;
+        self
+    }
+
+    /// Sets whether proposals that cannot be parsed are skipped when the proposal state file is
+    /// loaded. When enabled, each skipped proposal is logged and its ID is available from
+    /// `YamlAdminServiceStore::skipped_proposals`. Skipped proposals are not kept in the store,
+    /// so they are removed from the file the next time proposal state is written. By default, an
+    /// unparseable proposal fails the load.
+    ///
+    /// # Arguments
+    ///
+    ///  * `lenient_proposals` - Whether unparseable proposals should be skipped
+    pub fn with_lenient_proposals(mut self, lenient_proposals: bool) -> Self {
+        self.lenient_proposals = lenient_proposals;
+        self
+    }
+
+    /// Sets whether a state file that is missing a required top-level key, such as a circuit state
+    /// file without a `circuits` key, fails the load. When disabled, a warning naming the missing
+    /// keys is logged and the file is read as if each missing key had no entries. By default, a
+    /// missing key fails the load.
+    ///
+    /// # Arguments
+    ///
+    ///  * `strict_schema` - Whether a missing top-level key fails the load
+    pub fn with_strict_schema(mut self, strict_schema: bool) -> Self {
+        self.strict_schema = Some(strict_schema);
+        self
+    }
+
+    /// Sets whether the circuit state file is memory-mapped, rather than read into a buffer, when
+    /// it is loaded. Mapping avoids copying a large file before it is parsed. The file store must
+    /// support mapping, as `OsFileStore` does; other file stores read the file instead. By
+    /// default, the file is read.
+    ///
+    /// # Arguments
+    ///
+    ///  * `mmap_circuit_file` - Whether the circuit state file is memory-mapped
+    #[cfg(feature = "admin-store-mmap")]
+    pub fn with_mmap_circuit_file(mut self, mmap_circuit_file: bool) -> Self {
+        self.mmap_circuit_file = mmap_circuit_file;
+        self
+    }
+
+    /// Sets whether the service directory is rebuilt from the circuit rosters each time the store
+    /// is reloaded with `YamlAdminServiceStore::reload`. By default the directory is updated
+    /// incrementally from the reloaded circuits.
+    ///
+    /// # Arguments
+    ///
+    ///  * `rebuild_on_reload` - Whether the service directory should be rebuilt on reload
+    pub fn with_rebuild_on_reload(mut self, rebuild_on_reload: bool) -> Self {
+        self.rebuild_on_reload = rebuild_on_reload;
+        self
+    }
+
+    /// Sets the format the state files are read and written in. By default the files are YAML.
+    ///
+    /// # Arguments
+    ///
+    ///  * `file_format` - The format of the state files
+    pub fn with_file_format(mut self, file_format: FileFormat) -> Self {
+        self.file_format = file_format;
+        self
+    }
+
+    /// Sets how the authorization, persistence, durability and route types of circuits are named
+    /// when the state files are written. By default they are written as in Splinter v0.4, such as
+    /// `Trust`. The state files are read regardless of the case the values are in.
+    ///
+    /// # Arguments
+    ///
+    ///  * `enum_case` - The case the values are written in
+    pub fn with_enum_case(mut self, enum_case: EnumCase) -> Self {
+        self.enum_case = enum_case;
+        self
+    }
+
+    /// Sets how the creation and update times of circuits are written to the circuit state file.
+    /// By default they are written as RFC 3339 timestamps. The file is read regardless of the
+    /// format the times are in.
+    ///
+    /// # Arguments
+    ///
+    ///  * `timestamp_format` - The format the times are written in
+    pub fn with_timestamp_format(mut self, timestamp_format: TimestampFormat) -> Self {
+        self.timestamp_format = timestamp_format;
+        self
+    }
+
+    /// Sets the number of spaces each level of the YAML state files is indented by, so the files
+    /// are written the same way regardless of the serde_yaml version. By default the indentation
+    /// is left to serde_yaml. Has no effect on JSON state files.
+    ///
+    /// # Arguments
+    ///
+    ///  * `indent` - The indentation width, which must be at least 2
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+
+    /// Sets how adding a proposal with the same ID as an existing proposal is handled. By default,
+    /// an error is returned.
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_conflict_policy` - The policy applied when adding a proposal
+    pub fn with_proposal_conflict_policy(
+        mut self,
+        proposal_conflict_policy: ProposalConflictPolicy,
+    ) -> Self {
+        self.proposal_conflict_policy = proposal_conflict_policy;
+        self
+    }
+
+    /// Sets how adding a circuit with a node that already exists with different endpoints is
+    /// handled. By default, a warning is logged and the existing endpoints are kept.
+    ///
+    /// # Arguments
+    ///
+    ///  * `node_conflict_policy` - The policy applied when adding a circuit
+    pub fn with_node_conflict_policy(mut self, node_conflict_policy: NodeConflictPolicy) -> Self {
+        self.node_conflict_policy = node_conflict_policy;
+        self
+    }
+
+    /// Sets how adding or updating a circuit with a service that is allowed on a node that is not
+    /// a member of the circuit is handled. By default, a warning is logged and the circuit is
+    /// written.
+    ///
+    /// # Arguments
+    ///
+    ///  * `membership_policy` - The policy applied when adding or updating a circuit
+    pub fn with_membership_policy(mut self, membership_policy: MembershipPolicy) -> Self {
+        self.membership_policy = membership_policy;
+        self
+    }
+
+    /// Sets whether `add_circuit` rejects a circuit with a member that has no node definition,
+    /// either in the nodes added with the circuit or already in the store. When enabled, an
+    /// `OperationError` naming the unknown member is returned and the circuit is not written. By
+    /// default, members are not checked.
+    ///
+    /// # Arguments
+    ///
+    ///  * `require_known_members` - Whether every member of an added circuit must be a known node
+    pub fn with_require_known_members(mut self, require_known_members: bool) -> Self {
+        self.require_known_members = require_known_members;
+        self
+    }
+
+    /// Adds a validator that each circuit must pass before `add_circuit`, `update_circuit` or a
+    /// change to its roster writes it. A circuit rejected by any validator is not written, and an
+    /// `OperationError` with the validator's reason is returned. Validators are applied in the
+    /// order they are added. By default there are no validators.
+    ///
+    /// # Arguments
+    ///
+    ///  * `validator` - The validator to add
+    pub fn with_circuit_validator(mut self, validator: Box<CircuitValidator>) -> Self {
+        self.circuit_validators.push(Arc::from(validator));
+        self
+    }
+
+    /// Sets the path to a write-ahead log. When set, each change is appended to the log and synced
+    /// to disk before the operation returns, and the state files are only rewritten at
+    /// checkpoints. Any changes in the log are applied when the store is built. Changes that
+    /// replace the whole state, such as a reset or an import, are written to the state files
+    /// instead. By default there is no write-ahead log and the state files are rewritten on every
+    /// change.
+    ///
+    /// # Arguments
+    ///
+    ///  * `write_ahead_log_path` - The path to the write-ahead log file
+    pub fn with_write_ahead_log(mut self, write_ahead_log_path: &str) -> Self {
+        self.write_ahead_log_path = Some(write_ahead_log_path.into());
+        self
+    }
+
+    /// Sets the path to a journal directory. When set, the circuit, proposal and archived proposal
+    /// state files are replaced together as a single commit: their new contents are written to
+    /// the journal and synced before any file is replaced, so a crash never leaves one file
+    /// updated without the others. A commit left in the journal by a crash is completed when the
+    /// store is built. The directory must be on the same filesystem as the state files.
+    ///
+    /// # Arguments
+    ///
+    ///  * `journal_directory` - The path to the journal directory
+    pub fn with_journal_directory(mut self, journal_directory: &str) -> Self {
+        self.journal_directory = Some(journal_directory.into());
+        self
+    }
+
+    /// Sets the number of write-ahead log records after which the state files are rewritten and
+    /// the log is truncated. Defaults to 100. Has no effect without a write-ahead log.
+    ///
+    /// # Arguments
+    ///
+    ///  * `checkpoint_interval` - The number of records between checkpoints
+    pub fn with_checkpoint_interval(mut self, checkpoint_interval: usize) -> Self {
+        self.checkpoint_interval = Some(checkpoint_interval);
+        self
+    }
+
+    /// Sets how many times a state file write that fails with a transient error, such as an
+    /// interrupted or timed out write, is retried. The wait before each retry starts at
+    /// `initial_backoff` and doubles after every attempt. Other errors fail the write
+    /// immediately. By default failed writes are not retried. Writes committed through a
+    /// journal directory are not retried.
+    ///
+    /// # Arguments
+    ///
+    ///  * `max_retries` - The number of times a failed write is retried
+    ///  * `initial_backoff` - The time waited before the first retry
+    pub fn with_write_retries(mut self, max_retries: u32, initial_backoff: Duration) -> Self {
+        self.write_retry = WriteRetry {
+            max_retries,
+            initial_backoff,
+        };
+        self
+    }
+
+    /// Sets whether the circuit hash of each proposal is checked against its proposed circuit when
+    /// the proposal state file is loaded. A warning is logged for each proposal whose hash does
+    /// not match; the proposal is still loaded. By default hashes are not checked.
+    ///
+    /// # Arguments
+    ///
+    ///  * `verify_proposal_hashes` - Whether proposal hashes should be checked on load
+    pub fn with_proposal_hash_verification(mut self, verify_proposal_hashes: bool) -> Self {
+        self.verify_proposal_hashes = verify_proposal_hashes;
+        self
+    }
+
+    /// Builds a `YamlAdminServiceStore`. If the file paths provided exist, the existing state
+    /// will be cached in the store. If the files do not exist, they will be created with empty
+    /// state.
+    ///
+    /// Returns an error if the circuit or proposal file path is not set, if a path cannot be
+    /// expanded, if a path is a directory, if the indentation width is less than 2, if both a file
+    /// store and a journal directory are set, or if the files cannot be read from or written to
+    pub fn build(self) -> Result<YamlAdminServiceStore, YamlAdminStoreError> {
+        // The journal moves files into place on the operating system's filesystem, which would
+        // bypass the file store
+        if self.file_store.is_some() && self.journal_directory.is_some() {
+            return Err(YamlAdminStoreError::general_error(
+                "Unable to build YAML admin service store, a journal directory cannot be used \
+                 with a file store",
+            ));
+        }
+
+        if let Some(indent) = self.indent {
+            if indent < MIN_INDENT {
+                return Err(YamlAdminStoreError::general_error(&format!(
+                    "Unable to build YAML admin service store, indent must be at least {}",
+                    MIN_INDENT
+                )));
+            }
+        }
+
+        let circuit_file_path = self.circuit_file_path.ok_or_else(|| {
+            YamlAdminStoreError::general_error(
+                "Unable to build YAML admin service store, missing field: circuit_file_path",
+            )
+        })?;
+
+        let proposal_file_path = self.proposal_file_path.ok_or_else(|| {
+            YamlAdminStoreError::general_error(
+                "Unable to build YAML admin service store, missing field: proposal_file_path",
+            )
+        })?;
+
+        let (circuit_file_path, proposal_file_path) = if self.expand_paths {
+            (
+                expand_path(&circuit_file_path)?,
+                expand_path(&proposal_file_path)?,
+            )
+        } else {
+            (circuit_file_path, proposal_file_path)
+        };
+
+        check_not_directory(&circuit_file_path)?;
+        check_not_directory(&proposal_file_path)?;
+
+        let paths = StatePaths::new(
+            circuit_file_path,
+            proposal_file_path,
+            self.archive_proposals,
+            self.circuit_cache,
+        )?;
+
+        let mut store = YamlAdminServiceStore {
+            paths: RwLock::new(paths),
+            state: ArcSwap::from_pointee(YamlState::default()),
+            write_lock: Mutex::new(()),
+            metrics: self.metrics,
+            cascade_proposals: self.cascade_proposals,
+            prune_nodes_on_remove: self.prune_nodes_on_remove,
+            lenient_proposals: self.lenient_proposals,
+            strict_schema: self.strict_schema.unwrap_or(true),
+            #[cfg(feature = "admin-store-mmap")]
+            mmap_circuit_file: self.mmap_circuit_file,
+            rebuild_on_reload: self.rebuild_on_reload,
+            file_format: self.file_format,
+            enum_case: self.enum_case,
+            timestamp_format: self.timestamp_format,
+            indent: self.indent,
+            init_outcome: InitOutcome::CreatedEmpty,
+            proposal_conflict_policy: self.proposal_conflict_policy,
+            node_conflict_policy: self.node_conflict_policy,
+            membership_policy: self.membership_policy,
+            require_known_members: self.require_known_members,
+            circuit_validators: self.circuit_validators,
+            in_memory: false,
+            write_ahead_log: None,
+            journal: None,
+            write_retry: self.write_retry,
+            verify_proposal_hashes: self.verify_proposal_hashes,
+            clock: self.clock.unwrap_or_else(|| Arc::new(SystemClock)),
+            file_store: self.file_store.unwrap_or_else(|| Arc::new(OsFileStore)),
+            synced_file_hashes: Mutex::new(BTreeMap::new()),
+        };
+
+        // A commit left in the journal is completed before the state files are read
+        if let Some(journal_directory) = self.journal_directory {
+            let journal_directory = if self.expand_paths {
+                expand_path(&journal_directory)?
+            } else {
+                journal_directory
+            };
+
+            let journal = Journal::new(PathBuf::from(journal_directory));
+            journal.recover()?;
+            store.journal = Some(journal);
+        }
+
+        store.init_outcome = store.initialize()?;
+
+        // The log is only attached once the state files have been read, so that it is replayed
+        // over the state in the files
+        if let Some(write_ahead_log_path) = self.write_ahead_log_path {
+            let write_ahead_log_path = if self.expand_paths {
+                expand_path(&write_ahead_log_path)?
+            } else {
+                write_ahead_log_path
+            };
+            check_not_directory(&write_ahead_log_path)?;
+
+            store.write_ahead_log = Some(WriteAheadLog::new(
+                write_ahead_log_path,
+                self.checkpoint_interval
+                    .unwrap_or(DEFAULT_CHECKPOINT_INTERVAL),
+            ));
+            store.recover_from_write_ahead_log()?;
+        }
+
+        Ok(store)
+    }
+}
+
+/// Checks that a state file path does not refer to a directory, so that a clear error is returned
+/// instead of the error from opening the directory as a file
+fn check_not_directory(path: &str) -> Result<(), YamlAdminStoreError> {
+    if fs::metadata(path)
+        .map(|metadata| metadata.is_dir())
+        .unwrap_or(false)
+    {
+        Err(YamlAdminStoreError::general_error(&format!(
+            "Unable to build YAML admin service store, '{}' is a directory but a file was expected",
+            path
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Expands a leading `~` to the home directory and replaces `$VAR` and `${VAR}` with the value of
+/// the environment variable `VAR`
+///
+/// Returns an error if the home directory or a referenced environment variable is not set
+fn expand_path(path: &str) -> Result<String, YamlAdminStoreError> {
+    let mut expanded = String::new();
+
+    let rest = if path == "~" || path.starts_with("~/") {
+        expanded.push_str(&env_var("HOME")?);
+        &path[1..]
+    } else {
+        path
+    };
+
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+
+        let mut name = String::new();
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => {
+                        return Err(YamlAdminStoreError::general_error(&format!(
+                            "Unable to expand path '{}': unterminated variable reference",
+                            path
+                        )))
+                    }
+                }
+            }
+        } else {
+            while let Some(c) = chars.peek() {
+                if c.is_ascii_alphanumeric() || *c == '_' {
+                    name.push(*c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+        }
+
+        if name.is_empty() {
+            expanded.push('$');
+        } else {
+            expanded.push_str(&env_var(&name)?);
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Returns the value of an environment variable used in path expansion
+fn env_var(name: &str) -> Result<String, YamlAdminStoreError> {
+    env::var(name).map_err(|err| {
+        YamlAdminStoreError::general_error_with_source(
+            &format!(
+                "Unable to expand path, environment variable {} is not set",
+                name
+            ),
+            Box::new(err),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempdir::TempDir;
+
+    use super::super::MemFileStore;
+
+    // Validate that a leading tilde is expanded to the home directory, and that a tilde elsewhere
+    // in the path is left as is
+    #[test]
+    fn test_expand_tilde() {
+        let home = env::var("HOME").expect("HOME is not set");
+
+        assert_eq!(
+            expand_path("~/splinter/circuits.yaml").expect("Unable to expand path"),
+            format!("{}/splinter/circuits.yaml", home)
+        );
+        assert_eq!(expand_path("~").expect("Unable to expand path"), home);
+        assert_eq!(
+            expand_path("/var/~lib/circuits.yaml").expect("Unable to expand path"),
+            "/var/~lib/circuits.yaml"
+        );
+    }
+
+    // Validate that environment variables are substituted in both the `$VAR` and `${VAR}` forms,
+    // that an unset variable is an error, and that the builder uses the expanded paths.
+    #[test]
+    fn test_expand_env_var() {
+        let temp_dir = TempDir::new("test_expand_env_var").expect("Failed to create temp dir");
+        let state_dir = temp_dir.path().to_str().expect("Failed to get path");
+        env::set_var("SPLINTER_TEST_EXPAND_ENV_VAR", state_dir);
+
+        assert_eq!(
+            expand_path("$SPLINTER_TEST_EXPAND_ENV_VAR/circuits.yaml")
+                .expect("Unable to expand path"),
+            format!("{}/circuits.yaml", state_dir)
+        );
+        assert_eq!(
+            expand_path("${SPLINTER_TEST_EXPAND_ENV_VAR}/circuits.yaml")
+                .expect("Unable to expand path"),
+            format!("{}/circuits.yaml", state_dir)
+        );
+        assert!(expand_path("$SPLINTER_TEST_EXPAND_ENV_VAR_UNSET/circuits.yaml").is_err());
+        assert!(expand_path("${SPLINTER_TEST_EXPAND_ENV_VAR/circuits.yaml").is_err());
+
+        YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("$SPLINTER_TEST_EXPAND_ENV_VAR/circuits.yaml")
+            .with_proposal_file_path("${SPLINTER_TEST_EXPAND_ENV_VAR}/circuit_proposals.yaml")
+            .with_path_expansion(true)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        assert!(temp_dir.path().join("circuits.yaml").is_file());
+        assert!(temp_dir.path().join("circuit_proposals.yaml").is_file());
+    }
+
+    // Validate that building a store with both a file store and a journal directory returns an
+    // error, rather than writing the state files outside of the file store
+    #[test]
+    fn test_file_store_with_journal_directory() {
+        assert!(YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("circuits.yaml")
+            .with_proposal_file_path("circuit_proposals.yaml")
+            .with_file_store(Arc::new(MemFileStore::new()))
+            .with_journal_directory("journal")
+            .build()
+            .is_err());
+    }
+
+    // Validate that building a store with a directory as a state file path returns an error that
+    // names the directory
+    #[test]
+    fn test_directory_path() {
+        let temp_dir = TempDir::new("test_directory_path").expect("Failed to create temp dir");
+        let dir_path = temp_dir.path().to_str().expect("Failed to get path");
+        let proposals_path = temp_dir
+            .path()
+            .join("circuit_proposals.yaml")
+            .to_str()
+            .expect("Failed to get path")
+            .to_string();
+
+        let err = match YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path(dir_path)
+            .with_proposal_file_path(&proposals_path)
+            .build()
+        {
+            Ok(_) => panic!("Expected an error building the store"),
+            Err(err) => err,
+        };
+
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Unable to build YAML admin service store, '{}' is a directory but a file was \
+                 expected",
+                dir_path
+            )
+        );
+        assert!(!temp_dir.path().join("circuit_proposals.yaml").exists());
+    }
+}
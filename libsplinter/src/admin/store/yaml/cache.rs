@@ -0,0 +1,263 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A binary cache of the circuit state file of a `YamlAdminServiceStore`
+//!
+//! The cache records the SHA-256 hash of the circuit state file it was made from. It is only used
+//! if the hash matches the current contents of the file, so the state file remains the source of
+//! truth and may still be edited by hand.
+
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use openssl::hash::{hash, MessageDigest};
+
+use super::error::YamlAdminStoreError;
+use super::CircuitState;
+use crate::admin::store::{
+    AuthorizationType, Circuit, CircuitNode, CircuitStatus, DurabilityType, PersistenceType,
+    RouteType, Service,
+};
+use crate::hex::to_hex;
+
+/// The version of the cache format, which is changed whenever the format changes so that caches
+/// written by other versions are ignored
+const CACHE_VERSION: u32 = 2;
+
+/// The contents of a cache file
+///
+/// The circuit and node types have their own representation in the cache, because the
+/// serialization of `Circuit` and `CircuitNode` is specific to the state files.
+#[derive(Serialize, Deserialize)]
+struct CircuitCache {
+    version: u32,
+    source_hash: String,
+    nodes: BTreeMap<String, CachedNode>,
+    circuits: BTreeMap<String, CachedCircuit>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedNode {
+    id: String,
+    endpoints: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedCircuit {
+    id: String,
+    roster: Vec<Service>,
+    members: Vec<String>,
+    auth: AuthorizationType,
+    persistence: PersistenceType,
+    durability: DurabilityType,
+    routes: RouteType,
+    circuit_management_type: String,
+    created_at: SystemTime,
+    updated_at: SystemTime,
+    circuit_status: CircuitStatus,
+    display_name: Option<String>,
+}
+
+impl From<&CircuitNode> for CachedNode {
+    fn from(node: &CircuitNode) -> Self {
+        CachedNode {
+            id: node.id.to_string(),
+            endpoints: node.endpoints.clone(),
+        }
+    }
+}
+
+impl From<CachedNode> for CircuitNode {
+    fn from(node: CachedNode) -> Self {
+        CircuitNode {
+            id: node.id,
+            endpoints: node.endpoints,
+        }
+    }
+}
+
+impl From<&Circuit> for CachedCircuit {
+    fn from(circuit: &Circuit) -> Self {
+        CachedCircuit {
+            id: circuit.id.to_string(),
+            roster: circuit.roster.clone(),
+            members: circuit.members.clone(),
+            auth: circuit.auth.clone(),
+            persistence: circuit.persistence.clone(),
+            durability: circuit.durability.clone(),
+            routes: circuit.routes.clone(),
+            circuit_management_type: circuit.circuit_management_type.to_string(),
+            created_at: circuit.created_at,
+            updated_at: circuit.updated_at,
+            circuit_status: circuit.circuit_status.clone(),
+            display_name: circuit.display_name.clone(),
+        }
+    }
+}
+
+impl From<CachedCircuit> for Circuit {
+    fn from(circuit: CachedCircuit) -> Self {
+        Circuit {
+            id: circuit.id,
+            roster: circuit.roster,
+            members: circuit.members,
+            auth: circuit.auth,
+            persistence: circuit.persistence,
+            durability: circuit.durability,
+            routes: circuit.routes,
+            circuit_management_type: circuit.circuit_management_type,
+            created_at: circuit.created_at,
+            updated_at: circuit.updated_at,
+            circuit_status: circuit.circuit_status,
+            display_name: circuit.display_name,
+        }
+    }
+}
+
+/// Encodes a cache of the circuit state read from, or written to, the circuit state file
+///
+/// # Arguments
+///
+///  * `source` - The contents of the circuit state file
+///  * `circuit_state` - The circuit state the file contains
+pub(super) fn encode_cache(
+    source: &[u8],
+    circuit_state: &CircuitState,
+) -> Result<Vec<u8>, YamlAdminStoreError> {
+    let cache = CircuitCache {
+        version: CACHE_VERSION,
+        source_hash: source_hash(source)?,
+        nodes: circuit_state
+            .nodes
+            .iter()
+            .map(|(node_id, node)| (node_id.to_string(), CachedNode::from(node)))
+            .collect(),
+        circuits: circuit_state
+            .circuits
+            .iter()
+            .map(|(circuit_id, circuit)| (circuit_id.to_string(), CachedCircuit::from(circuit)))
+            .collect(),
+    };
+
+    bincode::serialize(&cache).map_err(|err| {
+        YamlAdminStoreError::general_error_with_source("Failed to encode circuit cache", err)
+    })
+}
+
+/// Decodes a cache, returning the circuit state it holds if it was made from the given contents
+/// of the circuit state file. Returns `None` if the cache cannot be decoded, was written by a
+/// different version, or was made from different contents.
+///
+/// # Arguments
+///
+///  * `cache` - The contents of the cache file
+///  * `source` - The current contents of the circuit state file
+pub(super) fn decode_cache(cache: &[u8], source: &[u8]) -> Option<CircuitState> {
+    let cache: CircuitCache = bincode::deserialize(cache).ok()?;
+    if cache.version != CACHE_VERSION || cache.source_hash != source_hash(source).ok()? {
+        return None;
+    }
+
+    Some(CircuitState {
+        nodes: cache
+            .nodes
+            .into_iter()
+            .map(|(node_id, node)| (node_id, CircuitNode::from(node)))
+            .collect(),
+        circuits: cache
+            .circuits
+            .into_iter()
+            .map(|(circuit_id, circuit)| (circuit_id, Circuit::from(circuit)))
+            .collect(),
+    })
+}
+
+/// Returns the SHA-256 hash, in hex, of the contents of a circuit state file
+fn source_hash(source: &[u8]) -> Result<String, YamlAdminStoreError> {
+    let digest = hash(MessageDigest::sha256(), source).map_err(|err| {
+        YamlAdminStoreError::general_error_with_source(
+            "Failed to hash circuit state file",
+            Box::new(err),
+        )
+    })?;
+
+    Ok(to_hex(&digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use super::super::tests::{
+        state_file_paths, store_builder, write_file, CIRCUIT_STATE, PROPOSAL_STATE,
+    };
+    use crate::admin::store::AdminServiceStore;
+
+    // Test that the circuit cache is loaded when it matches the circuit state file and is ignored
+    // when the file has changed
+    //
+    // 1. Setup the temp directory with existing state and create a store with the circuit cache
+    // 2. Check that the cache was written next to the circuit state file
+    // 3. Replace the cache with one of empty circuit state made from the same file contents, and
+    //    check that a new store loads the empty state from the cache
+    // 4. Change the circuit state file and check that a new store parses the file and replaces
+    //    the cache
+    #[test]
+    fn test_circuit_cache() {
+        let (temp_dir, circuit_path, proposals_path) = state_file_paths("test_circuit_cache");
+
+        let cache_path = temp_dir.path().join("circuits.cache");
+
+        // write yaml files to temp_dir
+        write_file(CIRCUIT_STATE, &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let build_store = || {
+            store_builder(&circuit_path, &proposals_path)
+                .with_circuit_cache(true)
+                .build()
+                .expect("Unable to create yaml admin store")
+        };
+
+        let store = build_store();
+        assert!(store.fetch_circuit("WBKLF-AAAAA").unwrap().is_some());
+        assert!(cache_path.is_file());
+
+        let cache_contents =
+            encode_cache(CIRCUIT_STATE, &CircuitState::default()).expect("Unable to encode cache");
+        fs::write(&cache_path, &cache_contents).expect("Unable to write cache");
+
+        let store = build_store();
+        assert_eq!(
+            store
+                .list_circuits(&[])
+                .expect("Unable to list circuits")
+                .count(),
+            0
+        );
+
+        let mut changed_state = CIRCUIT_STATE.to_vec();
+        changed_state.extend_from_slice(b"\n# edited by hand\n");
+        write_file(&changed_state, &circuit_path);
+
+        let store = build_store();
+        assert!(store.fetch_circuit("WBKLF-AAAAA").unwrap().is_some());
+        assert_ne!(
+            fs::read(&cache_path).expect("Unable to read cache"),
+            cache_contents
+        );
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sources of the current time used by the `YamlAdminServiceStore`
+
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Provides the current time used for the creation and update times of circuits
+pub trait Clock: Send + Sync {
+    /// Returns the current time
+    fn now(&self) -> SystemTime;
+}
+
+/// A clock that returns the system time. This is the clock used by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that returns a time that only changes when it is set or advanced
+///
+/// This makes the times recorded by the store predictable, for example in tests.
+#[derive(Debug)]
+pub struct FixedClock {
+    time: Mutex<SystemTime>,
+}
+
+impl FixedClock {
+    /// Creates a clock that returns the given time
+    ///
+    /// # Arguments
+    ///
+    ///  * `time` - The time the clock returns
+    pub fn new(time: SystemTime) -> Self {
+        FixedClock {
+            time: Mutex::new(time),
+        }
+    }
+
+    /// Sets the time the clock returns
+    ///
+    /// # Arguments
+    ///
+    ///  * `time` - The new time
+    pub fn set(&self, time: SystemTime) {
+        *self.time.lock().unwrap_or_else(|err| err.into_inner()) = time;
+    }
+
+    /// Moves the time the clock returns forward
+    ///
+    /// # Arguments
+    ///
+    ///  * `duration` - The amount to move the time forward by
+    pub fn advance(&self, duration: Duration) {
+        *self.time.lock().unwrap_or_else(|err| err.into_inner()) += duration;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        *self.time.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::time::UNIX_EPOCH;
+
+    use super::super::tests::{new_circuit, setup_state_files, store_builder};
+    use crate::admin::store::AdminServiceStore;
+
+    // Test that circuit times are read from the store's clock
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Create a store with a fixed clock and add a circuit, verifying its creation and update
+    //    times are the clock's time
+    // 3. Advance the clock and update the circuit twice, verifying the update time is the clock's
+    //    time after each update and the creation time does not change
+    #[test]
+    fn test_clock() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_clock");
+
+        let start = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let clock = Arc::new(FixedClock::new(start));
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_clock(clock.clone())
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit.clone(), vec![node])
+            .expect("Unable to add circuit");
+
+        let fetch_times = || {
+            let circuit = store
+                .fetch_circuit("WBKLF-DDDDD")
+                .expect("Unable to fetch circuit")
+                .expect("Expected circuit, got none");
+            (circuit.created_at(), circuit.updated_at())
+        };
+        assert_eq!(fetch_times(), (start, start));
+
+        clock.advance(Duration::from_secs(60));
+        store
+            .update_circuit(circuit.clone())
+            .expect("Unable to update circuit");
+        assert_eq!(fetch_times(), (start, start + Duration::from_secs(60)));
+
+        clock.set(start + Duration::from_secs(3600));
+        store
+            .update_circuit(circuit)
+            .expect("Unable to update circuit");
+        assert_eq!(fetch_times(), (start, start + Duration::from_secs(3600)));
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rendering the topology of a `YamlAdminServiceStore` as a Graphviz DOT document
+//!
+//! Each node is a vertex. Each circuit is a cluster holding a vertex for the circuit and one for
+//! each of its services. The circuit vertex is joined to the circuit's members, and each service
+//! vertex is joined by a dashed edge to the nodes it is allowed on. A node can be a member of
+//! several circuits, so nodes are drawn outside of the clusters.
+
+use super::CircuitState;
+
+/// Renders the nodes, circuits and services in the given circuit state as an undirected DOT
+/// graph. Nodes and circuits are written in ID order and services in roster order, so the same
+/// state always renders the same document.
+///
+/// # Arguments
+///
+///  * `circuit_state` - The circuit state to render
+pub(super) fn render_dot(circuit_state: &CircuitState) -> String {
+    let mut lines = vec!["graph splinter {".to_string()];
+
+    for node_id in circuit_state.nodes.keys() {
+        lines.push(format!("    {} [shape=ellipse];", quote(node_id)));
+    }
+
+    for (circuit_id, circuit) in circuit_state.circuits.iter() {
+        let circuit_vertex = quote(&format!("circuit:{}", circuit_id));
+
+        lines.push(format!(
+            "    subgraph {} {{",
+            quote(&format!("cluster_{}", circuit_id))
+        ));
+        lines.push(format!("        label={};", quote(circuit_id)));
+        lines.push(format!(
+            "        {} [label={}, shape=box];",
+            circuit_vertex,
+            quote(circuit_id)
+        ));
+        for service in circuit.roster.iter() {
+            lines.push(format!(
+                "        {} [label={}, shape=component];",
+                service_vertex(circuit_id, &service.service_id),
+                quote(&format!(
+                    "{} ({})",
+                    service.service_id, service.service_type
+                ))
+            ));
+        }
+        lines.push("    }".to_string());
+
+        for member in circuit.members.iter() {
+            lines.push(format!("    {} -- {};", circuit_vertex, quote(member)));
+        }
+
+        for service in circuit.roster.iter() {
+            for node_id in service.allowed_nodes.iter() {
+                lines.push(format!(
+                    "    {} -- {} [style=dashed];",
+                    service_vertex(circuit_id, &service.service_id),
+                    quote(node_id)
+                ));
+            }
+        }
+    }
+
+    lines.push("}".to_string());
+
+    let mut document = lines.join("\n");
+    document.push('\n');
+    document
+}
+
+/// Returns the quoted ID of the vertex for a service. Service IDs are only unique within a
+/// circuit, so the ID includes the circuit ID.
+fn service_vertex(circuit_id: &str, service_id: &str) -> String {
+    quote(&format!("service:{}::{}", circuit_id, service_id))
+}
+
+/// Quotes a string as a DOT ID, escaping any quotes and backslashes it contains
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{CIRCUIT_STATE, PROPOSAL_STATE};
+    use super::super::YamlAdminServiceStore;
+
+    // Test that the store is rendered as a DOT document containing its nodes, circuits and
+    // services
+    //
+    // 1. Create a store from the existing state
+    // 2. Render the store as a DOT document
+    // 3. Verify the document is a graph with a cluster for the circuit, a vertex for each node
+    //    and service, and edges from the circuit to its members and from services to their nodes
+    #[test]
+    fn test_to_dot() {
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
+            .expect("Unable to create yaml admin store");
+
+        let dot = store.to_dot().expect("Unable to render DOT");
+
+        assert!(dot.starts_with("graph splinter {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    \"acme-node-000\" [shape=ellipse];"));
+        assert!(dot.contains("    \"bubba-node-000\" [shape=ellipse];"));
+        assert!(dot.contains("    subgraph \"cluster_WBKLF-AAAAA\" {"));
+        assert!(dot.contains(
+            "        \"service:WBKLF-AAAAA::a000\" [label=\"a000 (scabbard)\", shape=component];"
+        ));
+        assert!(dot.contains(
+            "        \"service:WBKLF-AAAAA::a001\" [label=\"a001 (scabbard)\", shape=component];"
+        ));
+        assert!(dot.contains("    \"circuit:WBKLF-AAAAA\" -- \"acme-node-000\";"));
+        assert!(dot.contains("    \"circuit:WBKLF-AAAAA\" -- \"bubba-node-000\";"));
+        assert!(
+            dot.contains("    \"service:WBKLF-AAAAA::a000\" -- \"acme-node-000\" [style=dashed];")
+        );
+        assert!(
+            dot.contains("    \"service:WBKLF-AAAAA::a001\" -- \"bubba-node-000\" [style=dashed];")
+        );
+    }
+}
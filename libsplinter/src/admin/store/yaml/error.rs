@@ -23,7 +23,7 @@ pub enum YamlAdminStoreError {
     /// A general error occurred in the node registry
     GeneralError {
         context: String,
-        source: Option<Box<dyn Error + Send>>,
+        source: Option<Box<dyn Error + Send + Sync>>,
     },
 }
 
@@ -37,7 +37,7 @@ impl YamlAdminStoreError {
     }
 
     /// Create a new general error with a context string and a source error.
-    pub fn general_error_with_source(context: &str, err: Box<dyn Error + Send>) -> Self {
+    pub fn general_error_with_source(context: &str, err: Box<dyn Error + Send + Sync>) -> Self {
         YamlAdminStoreError::GeneralError {
             context: context.into(),
             source: Some(err),
@@ -0,0 +1,344 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage for the state files of the `YamlAdminServiceStore`
+
+use std::collections::BTreeMap;
+use std::fs;
+#[cfg(feature = "admin-store-mmap")]
+use std::fs::File;
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use atomicwrites::{AllowOverwrite, AtomicFile};
+#[cfg(feature = "admin-store-mmap")]
+use memmap2::Mmap;
+
+/// Reads and writes the circuit, proposal and archived proposal state files
+///
+/// The write-ahead log and journal directory, if the store has them, are always on the operating
+/// system's filesystem.
+pub trait FileStore: Send + Sync {
+    /// Returns whether a file exists at the given path
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Opens the file at the given path and reads its contents
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Opens the file at the given path and returns its contents without copying them, if the
+    /// file store supports it. By default the file is read.
+    fn map(&self, path: &Path) -> io::Result<FileContents> {
+        self.read(path).map(FileContents::Buffered)
+    }
+
+    /// Replaces the contents of the file at the given path, creating it if it does not exist. The
+    /// file must either have its previous contents or the new contents if the write fails.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Returns the time the file at the given path was last modified
+    fn modified(&self, path: &Path) -> io::Result<SystemTime>;
+
+    /// Removes the file at the given path
+    fn remove(&self, path: &Path) -> io::Result<()>;
+}
+
+/// Stores the state files on the operating system's filesystem. This is the file store used by
+/// default.
+///
+/// Files are replaced atomically by writing a temporary file that is renamed over the file. If
+/// the path is a symlink, the file it points to is replaced instead, so the symlink is preserved.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OsFileStore;
+
+impl FileStore for OsFileStore {
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    #[cfg(feature = "admin-store-mmap")]
+    fn map(&self, path: &Path) -> io::Result<FileContents> {
+        let file = File::open(path)?;
+        // Mapping an empty file fails on some platforms, and there is nothing to map
+        if file.metadata()?.len() == 0 {
+            return Ok(FileContents::Buffered(vec![]));
+        }
+
+        // SAFETY: the store replaces state files by renaming a new file over them, so the mapped
+        // file is never modified by the store. A file modified in place by another process while
+        // it is mapped may be read partially changed.
+        unsafe { Mmap::map(&file) }.map(FileContents::Mapped)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        AtomicFile::new(resolve_symlinks(path)?, AllowOverwrite)
+            .write(|file| file.write_all(contents))
+            .map_err(io::Error::from)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        fs::metadata(path)?.modified()
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+}
+
+/// The contents of a file returned by `FileStore::map`
+#[derive(Debug)]
+pub enum FileContents {
+    /// The contents were read into memory
+    Buffered(Vec<u8>),
+    /// The file is mapped into memory
+    #[cfg(feature = "admin-store-mmap")]
+    Mapped(Mmap),
+}
+
+impl Deref for FileContents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileContents::Buffered(contents) => contents,
+            #[cfg(feature = "admin-store-mmap")]
+            FileContents::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// The contents and modification time of each file in a `MemFileStore`
+type Files = BTreeMap<PathBuf, (Vec<u8>, SystemTime)>;
+
+/// Stores the state files in memory
+///
+/// The files are lost when the file store is dropped. The file store can be shared between stores
+/// to simulate restarting a store.
+#[derive(Debug, Default)]
+pub struct MemFileStore {
+    files: Mutex<Files>,
+}
+
+impl MemFileStore {
+    /// Creates an empty in-memory file store
+    pub fn new() -> Self {
+        MemFileStore::default()
+    }
+
+    fn lock_files(&self) -> io::Result<MutexGuard<'_, Files>> {
+        self.files
+            .lock()
+            .map_err(|_| io::Error::other("file store lock poisoned"))
+    }
+
+    fn get(&self, path: &Path) -> io::Result<(Vec<u8>, SystemTime)> {
+        self.lock_files()?.get(path).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' does not exist", path.display()),
+            )
+        })
+    }
+}
+
+impl FileStore for MemFileStore {
+    fn exists(&self, path: &Path) -> bool {
+        self.lock_files()
+            .map(|files| files.contains_key(path))
+            .unwrap_or(false)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.get(path).map(|(contents, _)| contents)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.lock_files()?
+            .insert(path.to_path_buf(), (contents.to_vec(), SystemTime::now()));
+        Ok(())
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        self.get(path).map(|(_, modified)| modified)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.lock_files()?.remove(path).map(|_| ()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' does not exist", path.display()),
+            )
+        })
+    }
+}
+
+/// Follows a chain of symlinks to the path of the file they point to. The file does not need to
+/// exist. Paths that are not symlinks are returned unchanged.
+pub(super) fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    // the same limit Linux places on the number of symlinks followed when resolving a path
+    const MAX_SYMLINKS: usize = 40;
+
+    let mut resolved = path.to_path_buf();
+    for _ in 0..MAX_SYMLINKS {
+        match fs::symlink_metadata(&resolved) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let target = fs::read_link(&resolved)?;
+                resolved = match resolved.parent() {
+                    Some(parent) => parent.join(target),
+                    None => target,
+                };
+            }
+            _ => return Ok(resolved),
+        }
+    }
+
+    Err(io::Error::other(format!(
+        "Too many levels of symlinks resolving '{}'",
+        path.display()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use super::super::error::YamlAdminStoreError;
+    use super::super::tests::new_circuit;
+    use super::super::{InitOutcome, YamlAdminServiceStoreBuilder};
+    use crate::admin::store::{AdminServiceStore, AdminServiceStoreError};
+
+    // Test that a store backed by an in-memory file store persists its state in the file store
+    //
+    // 1. Create a store with a new in-memory file store, so the state files are created empty
+    // 2. Add a circuit and its node
+    // 3. Create a second store sharing the file store and check that the circuit is loaded
+    #[test]
+    fn test_mem_file_store() {
+        let file_store = Arc::new(MemFileStore::new());
+
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/nonexistent/circuits.yaml")
+            .with_proposal_file_path("/nonexistent/circuit_proposals.yaml")
+            .with_file_store(file_store.clone())
+            .build()
+            .expect("Unable to create yaml admin store");
+        assert_eq!(store.init_outcome(), InitOutcome::CreatedEmpty);
+        assert!(file_store.exists(Path::new("/nonexistent/circuits.yaml")));
+        assert!(file_store.exists(Path::new("/nonexistent/circuit_proposals.yaml")));
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit.clone(), vec![node])
+            .expect("Unable to add circuit");
+
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/nonexistent/circuits.yaml")
+            .with_proposal_file_path("/nonexistent/circuit_proposals.yaml")
+            .with_file_store(file_store)
+            .build()
+            .expect("Unable to create yaml admin store");
+        assert_eq!(store.init_outcome(), InitOutcome::LoadedExisting);
+
+        let fetched_circuit = store
+            .fetch_circuit(&circuit.id)
+            .expect("Unable to fetch circuit")
+            .expect("Circuit was not persisted");
+        assert_eq!(fetched_circuit.members, circuit.members);
+        assert_eq!(
+            fetched_circuit
+                .roster
+                .iter()
+                .map(|service| service.service_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a000", "a001"]
+        );
+        assert!(store
+            .fetch_node("new-node-000")
+            .expect("Unable to fetch node")
+            .is_some());
+    }
+
+    // Test that a failed write to the file store is returned as a storage error with the store
+    // error as its source
+    //
+    // 1. Create a store with a file store whose writes can be made to fail
+    // 2. Make writes fail and add a circuit
+    // 3. Check that a storage error is returned with a YamlAdminStoreError as its source
+    #[test]
+    fn test_file_store_write_error() {
+        struct FailingFileStore {
+            files: MemFileStore,
+            fail_writes: Mutex<bool>,
+        }
+
+        impl FileStore for FailingFileStore {
+            fn exists(&self, path: &Path) -> bool {
+                self.files.exists(path)
+            }
+
+            fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+                self.files.read(path)
+            }
+
+            fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+                if *self.fail_writes.lock().expect("lock poisoned") {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "writes are disabled",
+                    ));
+                }
+                self.files.write(path, contents)
+            }
+
+            fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+                self.files.modified(path)
+            }
+
+            fn remove(&self, path: &Path) -> io::Result<()> {
+                self.files.remove(path)
+            }
+        }
+
+        let file_store = Arc::new(FailingFileStore {
+            files: MemFileStore::new(),
+            fail_writes: Mutex::new(false),
+        });
+
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/nonexistent/circuits.yaml")
+            .with_proposal_file_path("/nonexistent/circuit_proposals.yaml")
+            .with_file_store(file_store.clone())
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        *file_store.fail_writes.lock().expect("lock poisoned") = true;
+
+        let (circuit, node) = new_circuit();
+        match store.add_circuit(circuit.clone(), vec![node]) {
+            Err(AdminServiceStoreError::StorageError {
+                source: Some(source),
+                ..
+            }) => assert!(source.downcast_ref::<YamlAdminStoreError>().is_some()),
+            res => panic!("Expected a storage error, got {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Writing YAML state files with a chosen indentation width
+//!
+//! serde_yaml does not allow its indentation to be configured, so documents with a chosen width
+//! are emitted here in the same block style. Scalars are still formatted by serde_yaml, so they
+//! are quoted exactly as they would be otherwise.
+
+use serde_yaml::Value;
+
+use super::error::YamlAdminStoreError;
+
+/// The smallest indentation width, which leaves room for the space that must follow the `-` of
+/// a sequence item
+pub(super) const MIN_INDENT: usize = 2;
+
+/// Serializes a YAML value as a block-style document, indenting each level of nesting by the
+/// given number of spaces
+///
+/// With an indentation width of two the output is the same as serde_yaml's.
+///
+/// # Arguments
+///
+///  * `value` - The value to serialize
+///  * `indent` - The number of spaces each level is indented by, at least `MIN_INDENT`
+///  * `description` - A description of the value used in error messages
+pub(super) fn to_vec_with_indent(
+    value: &Value,
+    indent: usize,
+    description: &str,
+) -> Result<Vec<u8>, YamlAdminStoreError> {
+    let lines = if is_block(value) {
+        let mut lines = vec!["---".to_string()];
+        lines.extend(block_lines(value, indent, description)?);
+        lines
+    } else {
+        vec![format!("--- {}", scalar(value, description)?)]
+    };
+
+    let mut output = lines.join("\n");
+    output.push('\n');
+    Ok(output.into_bytes())
+}
+
+/// Returns whether a value is written as a block, rather than on the line of its key or `-`.
+/// Empty collections are written as `{}` or `[]`.
+fn is_block(value: &Value) -> bool {
+    match value {
+        Value::Mapping(mapping) => !mapping.is_empty(),
+        Value::Sequence(sequence) => !sequence.is_empty(),
+        _ => false,
+    }
+}
+
+/// Returns the lines of a block mapping or sequence, indented relative to its first column
+fn block_lines(
+    value: &Value,
+    indent: usize,
+    description: &str,
+) -> Result<Vec<String>, YamlAdminStoreError> {
+    let padding = " ".repeat(indent);
+    let mut lines = vec![];
+
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, entry) in mapping.iter() {
+                let key = scalar(key, description)?;
+                if is_block(entry) {
+                    lines.push(format!("{}:", key));
+                    for line in block_lines(entry, indent, description)? {
+                        lines.push(format!("{}{}", padding, line));
+                    }
+                } else {
+                    lines.push(format!("{}: {}", key, scalar(entry, description)?));
+                }
+            }
+        }
+        Value::Sequence(sequence) => {
+            // The item's first line follows the `-`, so that its content starts one indentation
+            // width after the `-`
+            let dash = format!("-{}", " ".repeat(indent - 1));
+            for item in sequence.iter() {
+                if is_block(item) {
+                    let mut item_lines = block_lines(item, indent, description)?.into_iter();
+                    if let Some(first) = item_lines.next() {
+                        lines.push(format!("{}{}", dash, first));
+                    }
+                    for line in item_lines {
+                        lines.push(format!("{}{}", padding, line));
+                    }
+                } else {
+                    lines.push(format!("{}{}", dash, scalar(item, description)?));
+                }
+            }
+        }
+        _ => lines.push(scalar(value, description)?),
+    }
+
+    Ok(lines)
+}
+
+/// Formats a scalar or empty collection as serde_yaml would
+fn scalar(value: &Value, description: &str) -> Result<String, YamlAdminStoreError> {
+    let document = serde_yaml::to_string(value).map_err(|err| {
+        YamlAdminStoreError::general_error_with_source(
+            &format!("Failed to write {} to YAML", description),
+            Box::new(err),
+        )
+    })?;
+
+    Ok(document
+        .trim_start_matches("---")
+        .trim_start_matches([' ', '\n'])
+        .trim_end_matches('\n')
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::UNIX_EPOCH;
+
+    use super::super::tests::{
+        create_expected_proposal, new_circuit, CIRCUIT_STATE, PROPOSAL_STATE,
+    };
+    use super::super::{
+        FileStore, FixedClock, MemFileStore, YamlAdminServiceStore, YamlAdminServiceStoreBuilder,
+    };
+    use crate::admin::store::AdminServiceStore;
+
+    // Test that the state files are written with the configured indentation width
+    //
+    // 1. Write the sample state with an indentation width of 2, and verify the files are the
+    //    same as those written by serde_yaml
+    // 2. Write the sample state with an indentation width of 4
+    // 3. Verify every line is indented by a multiple of 4, with nesting at each multiple, and
+    //    that the files load back to the same state
+    // 4. Verify an indentation width less than 2 is rejected
+    #[test]
+    fn test_indent() {
+        let write_with_indent = |indent: Option<usize>| {
+            let file_store = Arc::new(MemFileStore::new());
+            file_store
+                .write(Path::new("/state/circuits.yaml"), CIRCUIT_STATE)
+                .expect("Unable to write circuit state");
+            file_store
+                .write(Path::new("/state/circuit_proposals.yaml"), PROPOSAL_STATE)
+                .expect("Unable to write proposal state");
+
+            let mut builder = YamlAdminServiceStoreBuilder::new()
+                .with_circuit_file_path("/state/circuits.yaml")
+                .with_proposal_file_path("/state/circuit_proposals.yaml")
+                .with_file_store(file_store.clone())
+                .with_clock(Arc::new(FixedClock::new(UNIX_EPOCH)));
+            if let Some(indent) = indent {
+                builder = builder.with_indent(indent);
+            }
+            let store = builder.build().expect("Unable to create yaml admin store");
+            store.reset().expect("Unable to reset store");
+            let (circuit, node) = new_circuit();
+            store
+                .add_circuit(circuit, vec![node])
+                .expect("Unable to add circuit");
+            store
+                .add_proposal(create_expected_proposal())
+                .expect("Unable to add proposal");
+
+            (
+                String::from_utf8(
+                    file_store
+                        .read(Path::new("/state/circuits.yaml"))
+                        .expect("Unable to read circuit state"),
+                )
+                .expect("Circuit state is not UTF-8"),
+                String::from_utf8(
+                    file_store
+                        .read(Path::new("/state/circuit_proposals.yaml"))
+                        .expect("Unable to read proposal state"),
+                )
+                .expect("Proposal state is not UTF-8"),
+            )
+        };
+
+        let default_output = write_with_indent(None);
+        assert_eq!(write_with_indent(Some(2)), default_output);
+
+        let (circuit_output, proposal_output) = write_with_indent(Some(4));
+        for output in &[&circuit_output, &proposal_output] {
+            let widths = output
+                .lines()
+                .map(|line| line.len() - line.trim_start().len())
+                .collect::<std::collections::BTreeSet<_>>();
+            assert!(widths.iter().all(|width| width % 4 == 0), "{}", output);
+            assert!(widths.contains(&4) && widths.contains(&8), "{}", output);
+        }
+
+        let load = |(circuit_output, proposal_output): &(String, String)| {
+            let store = YamlAdminServiceStore::from_bytes(
+                circuit_output.as_bytes(),
+                proposal_output.as_bytes(),
+            )
+            .expect("Unable to load state");
+            (
+                store
+                    .get_circuit("WBKLF-DDDDD")
+                    .expect("Unable to get circuit"),
+                store
+                    .get_proposal("WBKLF-BBBBB")
+                    .expect("Unable to get proposal"),
+            )
+        };
+        assert_eq!(
+            load(&(circuit_output, proposal_output)),
+            load(&default_output)
+        );
+
+        assert!(YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/state/circuits.yaml")
+            .with_proposal_file_path("/state/circuit_proposals.yaml")
+            .with_file_store(Arc::new(MemFileStore::new()))
+            .with_indent(1)
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Iterators and lists over the state cached by the `YamlAdminServiceStore`
+
+use std::collections::BTreeMap;
+use std::ops::{Bound, Index};
+use std::sync::Arc;
+use std::vec;
+
+use crate::admin::store::Circuit;
+
+/// An iterator over the values of a map in a shared snapshot of state
+///
+/// Values are cloned one at a time as the iterator is advanced, so a caller that only consumes
+/// part of the iterator does not pay to clone the rest. The snapshot is kept alive for the
+/// lifetime of the iterator.
+pub(super) struct SnapshotIter<S, T> {
+    source: Arc<S>,
+    select: fn(&S) -> &BTreeMap<String, T>,
+    filter: Box<dyn Fn(&T) -> bool>,
+    last_key: Option<String>,
+    remaining: usize,
+}
+
+impl<S, T> SnapshotIter<S, T> {
+    /// Creates an iterator over the values of the map selected from `source` that match `filter`
+    ///
+    /// # Arguments
+    ///
+    ///  * `source` - The snapshot that holds the map
+    ///  * `select` - Returns the map to iterate over from the snapshot
+    ///  * `filter` - Returns whether a value should be included in the iterator
+    pub fn new(
+        source: Arc<S>,
+        select: fn(&S) -> &BTreeMap<String, T>,
+        filter: Box<dyn Fn(&T) -> bool>,
+    ) -> Self {
+        let remaining = select(&source)
+            .values()
+            .filter(|value| filter(value))
+            .count();
+
+        SnapshotIter {
+            source,
+            select,
+            filter,
+            last_key: None,
+            remaining,
+        }
+    }
+}
+
+impl<S, T: Clone> Iterator for SnapshotIter<S, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let map = (self.select)(&self.source);
+        let lower = match &self.last_key {
+            Some(key) => Bound::Excluded(key.as_str()),
+            None => Bound::Unbounded,
+        };
+
+        let (key, value) = map
+            .range::<str, _>((lower, Bound::Unbounded))
+            .find(|(_, value)| (self.filter)(value))?;
+
+        self.last_key = Some(key.clone());
+        self.remaining -= 1;
+        Some(value.clone())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<S, T: Clone> ExactSizeIterator for SnapshotIter<S, T> {}
+
+/// A list of circuits returned by `YamlAdminServiceStore::list_circuits_indexed`
+///
+/// Unlike the iterator returned by `list_circuits`, the list can be indexed, so a page of
+/// circuits can be read without collecting the circuits again.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CircuitList {
+    circuits: Vec<Circuit>,
+}
+
+impl CircuitList {
+    pub(super) fn new(circuits: Vec<Circuit>) -> Self {
+        CircuitList { circuits }
+    }
+
+    /// Returns the number of circuits in the list
+    pub fn len(&self) -> usize {
+        self.circuits.len()
+    }
+
+    /// Returns true if the list contains no circuits
+    pub fn is_empty(&self) -> bool {
+        self.circuits.is_empty()
+    }
+
+    /// Returns the circuit at the given index, or `None` if the index is out of bounds
+    pub fn get(&self, index: usize) -> Option<&Circuit> {
+        self.circuits.get(index)
+    }
+
+    /// Returns an iterator over the circuits in the list
+    pub fn iter(&self) -> std::slice::Iter<'_, Circuit> {
+        self.circuits.iter()
+    }
+}
+
+impl Index<usize> for CircuitList {
+    type Output = Circuit;
+
+    fn index(&self, index: usize) -> &Circuit {
+        &self.circuits[index]
+    }
+}
+
+impl IntoIterator for CircuitList {
+    type Item = Circuit;
+    type IntoIter = vec::IntoIter<Circuit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.circuits.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CircuitList {
+    type Item = &'a Circuit;
+    type IntoIter = std::slice::Iter<'a, Circuit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.circuits.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A value that counts the number of times it has been cloned
+    struct Counted {
+        value: usize,
+        clones: Arc<AtomicUsize>,
+    }
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            self.clones.fetch_add(1, Ordering::SeqCst);
+            Counted {
+                value: self.value,
+                clones: self.clones.clone(),
+            }
+        }
+    }
+
+    // Validate that values are only cloned as the iterator is advanced
+    //
+    // 1. Create a map of values that count their clones
+    // 2. Create an iterator over the values, filtering out one of them
+    // 3. Consume the first item and check that only one value was cloned
+    // 4. Consume the rest and check the filtered values and clone count
+    #[test]
+    fn test_snapshot_iter_is_lazy() {
+        let clones = Arc::new(AtomicUsize::new(0));
+        let map: BTreeMap<String, Counted> = (0..10)
+            .map(|value| {
+                (
+                    format!("{:02}", value),
+                    Counted {
+                        value,
+                        clones: clones.clone(),
+                    },
+                )
+            })
+            .collect();
+
+        let mut iter = SnapshotIter::new(
+            Arc::new(map),
+            |map| map,
+            Box::new(|counted: &Counted| counted.value != 5),
+        );
+        assert_eq!(iter.len(), 9);
+
+        let first = iter.next().expect("Expected an item");
+        assert_eq!(first.value, 0);
+        assert_eq!(clones.load(Ordering::SeqCst), 1);
+        assert_eq!(iter.len(), 8);
+
+        let rest: Vec<usize> = iter.map(|counted| counted.value).collect();
+        assert_eq!(rest, vec![1, 2, 3, 4, 6, 7, 8, 9]);
+        assert_eq!(clones.load(Ordering::SeqCst), 9);
+    }
+}
@@ -0,0 +1,353 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A journal directory used to replace several state files of a `YamlAdminServiceStore` as a
+//! single commit
+//!
+//! The new contents of each file are written to the journal directory and synced, followed by a
+//! commit marker listing the files. Once the marker is in place the commit is complete: the files
+//! are moved over the state files and the marker is removed. If the store is stopped before the
+//! marker is removed, the commit is completed when the store is next created. Files left in the
+//! journal without a marker are from a commit that never completed, and are discarded.
+//!
+//! The journal directory must be on the same filesystem as the state files, so that the files
+//! can be moved into place atomically.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use super::error::YamlAdminStoreError;
+
+/// The name of the commit marker in the journal directory
+const COMMIT_FILE_NAME: &str = "commit.json";
+
+/// The name the commit marker is written under before it is renamed into place
+const PENDING_COMMIT_FILE_NAME: &str = "commit.json.pending";
+
+/// The commit marker, listing the files to move into place
+#[derive(Debug, Serialize, Deserialize)]
+struct Commit {
+    files: Vec<CommitFile>,
+}
+
+/// A file in a commit
+#[derive(Debug, Serialize, Deserialize)]
+struct CommitFile {
+    /// The name of the file in the journal directory
+    journal_file: String,
+    /// The path the file is moved to
+    target: PathBuf,
+}
+
+/// The journal directory of a `YamlAdminServiceStore`
+pub(super) struct Journal {
+    directory: PathBuf,
+}
+
+impl Journal {
+    /// Creates a journal in the given directory. The directory is created when it is first used.
+    ///
+    /// # Arguments
+    ///
+    ///  * `directory` - The path to the journal directory
+    pub(super) fn new(directory: PathBuf) -> Self {
+        Journal { directory }
+    }
+
+    /// Replaces the contents of each of the given files, so that either all or none of them are
+    /// replaced
+    ///
+    /// # Arguments
+    ///
+    ///  * `files` - The path of each file and its new contents
+    pub(super) fn commit(&self, files: &[(&Path, &[u8])]) -> Result<(), YamlAdminStoreError> {
+        fs::create_dir_all(&self.directory).map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                &format!(
+                    "Failed to create journal directory '{}'",
+                    self.directory.display()
+                ),
+                Box::new(err),
+            )
+        })?;
+
+        let mut commit = Commit { files: vec![] };
+        for (i, (target, contents)) in files.iter().enumerate() {
+            let journal_file = format!("file-{}", i);
+            write_synced(&self.directory.join(&journal_file), contents)?;
+
+            commit.files.push(CommitFile {
+                journal_file,
+                target: target.to_path_buf(),
+            });
+        }
+
+        let commit_contents = serde_json::to_vec(&commit).map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                "Failed to write journal commit",
+                Box::new(err),
+            )
+        })?;
+
+        let pending_commit_path = self.directory.join(PENDING_COMMIT_FILE_NAME);
+        write_synced(&pending_commit_path, &commit_contents)?;
+        rename(&pending_commit_path, &self.directory.join(COMMIT_FILE_NAME))?;
+        sync_directory(&self.directory)?;
+
+        self.apply(&commit)
+    }
+
+    /// Completes a commit left in the journal, or discards the files of a commit that was never
+    /// completed
+    ///
+    /// Returns whether a commit was completed
+    pub(super) fn recover(&self) -> Result<bool, YamlAdminStoreError> {
+        let commit_contents = match fs::read(self.directory.join(COMMIT_FILE_NAME)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                self.discard()?;
+                return Ok(false);
+            }
+            Err(err) => {
+                return Err(YamlAdminStoreError::general_error_with_source(
+                    "Failed to read journal commit",
+                    Box::new(err),
+                ))
+            }
+        };
+
+        let commit: Commit = serde_json::from_slice(&commit_contents).map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                "Failed to parse journal commit",
+                Box::new(err),
+            )
+        })?;
+
+        warn!(
+            "Completing commit left in journal directory '{}'",
+            self.directory.display()
+        );
+        self.apply(&commit)?;
+
+        Ok(true)
+    }
+
+    /// Moves the files of a commit into place and removes the commit marker. Files that are no
+    /// longer in the journal have already been moved.
+    fn apply(&self, commit: &Commit) -> Result<(), YamlAdminStoreError> {
+        for file in commit.files.iter() {
+            let journal_path = self.directory.join(&file.journal_file);
+            if journal_path.exists() {
+                rename(&journal_path, &file.target)?;
+                if let Some(parent) = file.target.parent() {
+                    sync_directory(parent)?;
+                }
+            }
+        }
+
+        remove(&self.directory.join(COMMIT_FILE_NAME))?;
+        sync_directory(&self.directory)
+    }
+
+    /// Removes the files in the journal directory
+    fn discard(&self) -> Result<(), YamlAdminStoreError> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(YamlAdminStoreError::general_error_with_source(
+                    &format!(
+                        "Failed to read journal directory '{}'",
+                        self.directory.display()
+                    ),
+                    Box::new(err),
+                ))
+            }
+        };
+
+        for entry in entries {
+            let entry = entry.map_err(|err| {
+                YamlAdminStoreError::general_error_with_source(
+                    &format!(
+                        "Failed to read journal directory '{}'",
+                        self.directory.display()
+                    ),
+                    Box::new(err),
+                )
+            })?;
+
+            warn!(
+                "Discarding incomplete commit file '{}'",
+                entry.path().display()
+            );
+            remove(&entry.path())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a file and syncs it to disk
+fn write_synced(path: &Path, contents: &[u8]) -> Result<(), YamlAdminStoreError> {
+    File::create(path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                &format!("Failed to write journal file '{}'", path.display()),
+                Box::new(err),
+            )
+        })
+}
+
+/// Syncs a directory to disk, so that files renamed into or removed from it are durable
+fn sync_directory(path: &Path) -> Result<(), YamlAdminStoreError> {
+    File::open(path)
+        .and_then(|directory| directory.sync_all())
+        .map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                &format!("Failed to sync directory '{}'", path.display()),
+                Box::new(err),
+            )
+        })
+}
+
+fn rename(from: &Path, to: &Path) -> Result<(), YamlAdminStoreError> {
+    fs::rename(from, to).map_err(|err| {
+        YamlAdminStoreError::general_error_with_source(
+            &format!("Failed to move '{}' to '{}'", from.display(), to.display()),
+            Box::new(err),
+        )
+    })
+}
+
+fn remove(path: &Path) -> Result<(), YamlAdminStoreError> {
+    fs::remove_file(path).map_err(|err| {
+        YamlAdminStoreError::general_error_with_source(
+            &format!("Failed to remove '{}'", path.display()),
+            Box::new(err),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::tests::{
+        new_circuit, state_file_paths, store_builder, write_file, CIRCUIT_STATE, PROPOSAL_STATE,
+    };
+    use super::super::YamlAdminServiceStore;
+    use crate::admin::store::AdminServiceStore;
+
+    // Test that a commit left in the journal directory is completed when the store is created,
+    // and that files left without a commit are discarded
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Leave a commit in the journal that removes all circuits and proposals, with the circuit
+    //    file already moved into place and the proposal file still in the journal
+    // 3. Create a store with the journal and verify both files reflect the commit
+    // 4. Leave a file in the journal without a commit and create a store again
+    // 5. Verify the file was discarded and the state is unchanged
+    // 6. Add a circuit and verify the journal is empty afterwards and the files have the circuit
+    #[test]
+    fn test_journal_recovery() {
+        let (temp_dir, circuit_path, proposals_path) = state_file_paths("test_journal_recovery");
+
+        let journal_path = temp_dir.path().join("journal");
+
+        // write yaml files to temp_dir
+        write_file(CIRCUIT_STATE, &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        fs::create_dir(&journal_path).expect("Unable to create journal directory");
+        write_file(b"---\nnodes: {}\ncircuits: {}\n", &circuit_path);
+        write_file(
+            b"---\nproposals: {}\n",
+            journal_path
+                .join("file-1")
+                .to_str()
+                .expect("Failed to get path"),
+        );
+        write_file(
+            serde_json::json!({
+                "files": [
+                    {"journal_file": "file-0", "target": &circuit_path},
+                    {"journal_file": "file-1", "target": &proposals_path},
+                ]
+            })
+            .to_string()
+            .as_bytes(),
+            journal_path
+                .join("commit.json")
+                .to_str()
+                .expect("Failed to get path"),
+        );
+
+        let build_store = || {
+            store_builder(&circuit_path, &proposals_path)
+                .with_journal_directory(journal_path.to_str().expect("Failed to get path"))
+                .build()
+                .expect("Unable to create yaml admin store")
+        };
+
+        let store = build_store();
+        assert_eq!(store.list_circuits(&[]).unwrap().len(), 0);
+        assert_eq!(store.list_proposals(&[]).unwrap().len(), 0);
+        assert_eq!(
+            fs::read_dir(&journal_path)
+                .expect("Unable to read journal")
+                .count(),
+            0
+        );
+
+        write_file(
+            PROPOSAL_STATE,
+            journal_path
+                .join("file-1")
+                .to_str()
+                .expect("Failed to get path"),
+        );
+
+        let store = build_store();
+        assert_eq!(store.list_proposals(&[]).unwrap().len(), 0);
+        assert_eq!(
+            fs::read_dir(&journal_path)
+                .expect("Unable to read journal")
+                .count(),
+            0
+        );
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+        assert_eq!(
+            fs::read_dir(&journal_path)
+                .expect("Unable to read journal")
+                .count(),
+            0
+        );
+
+        let (reloaded_store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert!(reloaded_store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .is_some());
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hooks for observing the internal behavior of the `YamlAdminServiceStore`
+
+use std::time::Duration;
+
+/// Receives metrics recorded by a `YamlAdminServiceStore`
+///
+/// All methods have a default implementation that discards the metric, so implementations only
+/// need to override the metrics they are interested in.
+pub trait StoreMetrics: Send + Sync {
+    /// Records the time an operation that changes the store spent waiting to acquire the store's
+    /// internal write lock. Reads do not take the lock.
+    ///
+    /// # Arguments
+    ///
+    ///  * `duration` - The time between requesting and acquiring the lock
+    fn record_lock_wait(&self, _duration: Duration) {}
+
+    /// Records that the state files were rewritten and the write-ahead log truncated because the
+    /// log reached the store's checkpoint interval
+    fn record_checkpoint(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use super::super::tests::{
+        create_expected_proposal, new_proposal, state_file_paths, store_builder, write_file,
+        CIRCUIT_STATE, PROPOSAL_STATE,
+    };
+    use crate::admin::store::{AdminServiceStore, CircuitNode};
+
+    // Test that the write-ahead log is checkpointed once it reaches the checkpoint interval
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Create a store with a write-ahead log, a checkpoint interval of three records and a
+    //    metrics recorder that counts checkpoints
+    // 3. Make two changes and verify no checkpoint was recorded
+    // 4. Make two more changes and verify exactly one checkpoint was recorded
+    #[test]
+    fn test_checkpoint_metrics() {
+        #[derive(Default)]
+        struct CheckpointRecorder {
+            checkpoints: Mutex<usize>,
+        }
+
+        impl StoreMetrics for CheckpointRecorder {
+            fn record_checkpoint(&self) {
+                *self.checkpoints.lock().expect("recorder lock poisoned") += 1;
+            }
+        }
+
+        let (temp_dir, circuit_path, proposals_path) = state_file_paths("test_checkpoint_metrics");
+
+        let wal_path = temp_dir
+            .path()
+            .join("admin.wal")
+            .to_str()
+            .expect("Failed to get path")
+            .to_string();
+
+        // write yaml files to temp_dir
+        write_file(CIRCUIT_STATE, &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let recorder = Arc::new(CheckpointRecorder::default());
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_write_ahead_log(&wal_path)
+            .with_checkpoint_interval(3)
+            .with_metrics(recorder.clone())
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+        store
+            .remove_proposal("WBKLF-BBBBB")
+            .expect("Unable to remove proposal");
+        assert_eq!(
+            *recorder.checkpoints.lock().expect("recorder lock poisoned"),
+            0
+        );
+
+        store
+            .add_proposal(create_expected_proposal())
+            .expect("Unable to add proposal");
+        store
+            .remove_proposal("WBKLF-CCCCC")
+            .expect("Unable to remove proposal");
+        assert_eq!(
+            *recorder.checkpoints.lock().expect("recorder lock poisoned"),
+            1
+        );
+    }
+
+    // Test that the time spent waiting on the store's internal lock is reported to the store's
+    // metrics.
+    //
+    // 1. Create a store with a metrics recorder
+    // 2. Hold the internal lock while another thread adds a node
+    // 3. Release the lock and wait for the change to complete
+    // 4. Validate a non-zero lock wait was recorded
+    #[test]
+    fn test_lock_wait_metrics() {
+        #[derive(Default)]
+        struct LockWaitRecorder {
+            waits: Mutex<Vec<Duration>>,
+        }
+
+        impl StoreMetrics for LockWaitRecorder {
+            fn record_lock_wait(&self, duration: Duration) {
+                self.waits
+                    .lock()
+                    .expect("recorder lock poisoned")
+                    .push(duration);
+            }
+        }
+
+        let (_temp_dir, circuit_path, proposals_path) = state_file_paths("test_lock_wait_metrics");
+
+        let recorder = Arc::new(LockWaitRecorder::default());
+        let store = Arc::new(
+            store_builder(&circuit_path, &proposals_path)
+                .with_metrics(recorder.clone())
+                .build()
+                .expect("Unable to create yaml admin store"),
+        );
+
+        let guard = store.lock_state().expect("Unable to lock state");
+        let thread_store = store.clone();
+        let handle = std::thread::spawn(move || {
+            thread_store
+                .ensure_node(CircuitNode {
+                    id: "new-node-000".to_string(),
+                    endpoints: vec!["tcps://splinterd-node-new:8044".to_string()],
+                })
+                .is_ok()
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        drop(guard);
+
+        assert!(handle.join().expect("Node thread panicked"));
+
+        assert!(recorder
+            .waits
+            .lock()
+            .expect("recorder lock poisoned")
+            .iter()
+            .any(|wait| *wait > Duration::from_millis(0)));
+    }
+}
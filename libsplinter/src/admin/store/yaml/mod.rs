@@ -15,31 +15,475 @@
 //! Defines a YAML backed implementation of the `AdminServiceStore`. The goal of this
 //! implementation is to support Splinter v0.4 YAML state files.
 //!
-//! The public interface includes the struct [`YamlAdminServiceStore`].
+//! The public interface includes the structs [`YamlAdminServiceStore`],
+//! [`YamlAdminServiceStoreBuilder`], [`StoreSnapshot`], [`CircuitList`], [`CircuitState`],
+//! [`ProposalState`], [`SystemClock`], [`FixedClock`], [`VoteCounts`], [`VoteTally`],
+//! [`VoteProgress`], [`ProposalWithProgress`], [`OsFileStore`], [`MemFileStore`] and
+//! [`StateFileWatcher`],
+//! the enums [`CascadeProposals`], [`CircuitSortOrder`], [`EnumCase`], [`FileContents`],
+//! [`FileFormat`], [`InitOutcome`], [`SearchHit`],
+//! [`IntegrityWarning`], [`MembershipPolicy`], [`NodeConflictPolicy`],
+//! [`ProposalConflictPolicy`], [`ProposalWarning`] and [`TimestampFormat`], the trait
+//! [`StoreMetrics`], [`Clock`] and [`FileStore`], the type [`CircuitValidator`], and the
+//! functions [`validate_circuit_file`], [`stores_in_sync`], [`read_circuit_state_from`],
+//! [`write_circuit_state_to`], [`read_circuit_state_stdin`] and [`write_circuit_state_stdout`].
 //!
 //! [`YamlAdminServiceStore`]: struct.YamlAdminServiceStore.html
-
+//! [`YamlAdminServiceStoreBuilder`]: struct.YamlAdminServiceStoreBuilder.html
+//! [`StoreSnapshot`]: struct.StoreSnapshot.html
+//! [`CircuitList`]: struct.CircuitList.html
+//! [`CircuitState`]: struct.CircuitState.html
+//! [`ProposalState`]: struct.ProposalState.html
+//! [`SystemClock`]: struct.SystemClock.html
+//! [`FixedClock`]: struct.FixedClock.html
+//! [`VoteCounts`]: struct.VoteCounts.html
+//! [`VoteTally`]: struct.VoteTally.html
+//! [`VoteProgress`]: struct.VoteProgress.html
+//! [`ProposalWithProgress`]: struct.ProposalWithProgress.html
+//! [`OsFileStore`]: struct.OsFileStore.html
+//! [`MemFileStore`]: struct.MemFileStore.html
+//! [`StateFileWatcher`]: struct.StateFileWatcher.html
+//! [`CascadeProposals`]: enum.CascadeProposals.html
+//! [`CircuitSortOrder`]: enum.CircuitSortOrder.html
+//! [`EnumCase`]: enum.EnumCase.html
+//! [`FileContents`]: enum.FileContents.html
+//! [`FileFormat`]: enum.FileFormat.html
+//! [`InitOutcome`]: enum.InitOutcome.html
+//! [`IntegrityWarning`]: enum.IntegrityWarning.html
+//! [`MembershipPolicy`]: enum.MembershipPolicy.html
+//! [`NodeConflictPolicy`]: enum.NodeConflictPolicy.html
+//! [`ProposalConflictPolicy`]: enum.ProposalConflictPolicy.html
+//! [`ProposalWarning`]: enum.ProposalWarning.html
+//! [`SearchHit`]: enum.SearchHit.html
+//! [`TimestampFormat`]: enum.TimestampFormat.html
+//! [`StoreMetrics`]: trait.StoreMetrics.html
+//! [`Clock`]: trait.Clock.html
+//! [`FileStore`]: trait.FileStore.html
+//! [`CircuitValidator`]: type.CircuitValidator.html
+//! [`validate_circuit_file`]: fn.validate_circuit_file.html
+//! [`stores_in_sync`]: fn.stores_in_sync.html
+//! [`read_circuit_state_from`]: fn.read_circuit_state_from.html
+//! [`write_circuit_state_to`]: fn.write_circuit_state_to.html
+//! [`read_circuit_state_stdin`]: fn.read_circuit_state_stdin.html
+//! [`write_circuit_state_stdout`]: fn.write_circuit_state_stdout.html
+
+mod builder;
+mod cache;
+mod clock;
+mod dot;
 pub mod error;
-
-use std::collections::BTreeMap;
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-
+mod file_store;
+mod indent;
+mod iter;
+mod journal;
+mod metrics;
+mod search;
+mod snapshot;
+mod stream;
+mod tally;
+mod validate;
+mod wal;
+#[cfg(feature = "admin-store-watch")]
+mod watch;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use arc_swap::ArcSwap;
+use openssl::hash::{hash, MessageDigest};
+use protobuf::{Message, RepeatedField};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub use self::builder::YamlAdminServiceStoreBuilder;
+pub use self::clock::{Clock, FixedClock, SystemClock};
 use self::error::YamlAdminStoreError;
+pub use self::file_store::{FileContents, FileStore, MemFileStore, OsFileStore};
+pub use self::iter::CircuitList;
+use self::iter::SnapshotIter;
+use self::journal::Journal;
+pub use self::metrics::StoreMetrics;
+pub use self::search::SearchHit;
+pub use self::snapshot::StoreSnapshot;
+pub use self::tally::{ProposalWithProgress, VoteCounts, VoteProgress, VoteTally};
+pub use self::validate::{validate_circuit_file, IntegrityWarning, ProposalWarning};
+use self::wal::{StateChange, WriteAheadLog};
+#[cfg(feature = "admin-store-watch")]
+pub use self::watch::StateFileWatcher;
 
 use super::{
-    AdminServiceStore, AdminServiceStoreError, AuthorizationType, Circuit, CircuitNode,
-    CircuitPredicate, CircuitProposal, DurabilityType, PersistenceType, RouteType, Service,
-    ServiceId,
+    AdminServiceStore, AdminServiceStoreError, AdminStateView, AuthorizationType, Circuit,
+    CircuitNode, CircuitPredicate, CircuitProposal, CircuitStatus, DurabilityType, PersistenceType,
+    ProposedCircuit, RouteType, Service, ServiceId,
 };
+use crate::hex::to_hex;
+use crate::protos::admin;
+
+/// The name of the circuit state file in archives created by `export_tar`
+#[cfg(feature = "admin-store-tar")]
+const CIRCUITS_TAR_ENTRY: &str = "circuits.yaml";
+/// The name of the proposal state file in archives created by `export_tar`
+#[cfg(feature = "admin-store-tar")]
+const PROPOSALS_TAR_ENTRY: &str = "circuit_proposals.yaml";
+
+/// Determines how `remove_circuit` handles a proposal with the same ID as the removed circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CascadeProposals {
+    /// Leave the proposal in place and log a warning
+    #[default]
+    Warn,
+    /// Remove the proposal along with the circuit
+    Remove,
+}
+
+/// Determines how `add_circuit` handles a node with the same ID as an existing node but different
+/// endpoints. In either case the existing node is not changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NodeConflictPolicy {
+    /// Add the circuit and log a warning
+    #[default]
+    Warn,
+    /// Return an error without adding the circuit
+    Error,
+}
+
+/// A check applied to each circuit before `add_circuit` or `update_circuit` writes it, used to
+/// enforce rules specific to a deployment. Returning an error rejects the circuit, with the error
+/// as the reason.
+pub type CircuitValidator = dyn Fn(&Circuit) -> Result<(), String> + Send + Sync;
+
+/// Determines how `add_circuit` and `update_circuit` handle a circuit with a service that is
+/// allowed on a node that is not a member of the circuit, such as when an update removes a member
+/// that a service still refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MembershipPolicy {
+    /// Write the circuit and log a warning
+    #[default]
+    Warn,
+    /// Return an error without writing the circuit
+    Error,
+}
+
+/// Determines how `add_proposal` handles a proposal with the same ID as an existing proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProposalConflictPolicy {
+    /// Return an error
+    #[default]
+    Strict,
+    /// Succeed without changing the store if the proposals are identical, otherwise return an
+    /// error
+    UpsertIfIdentical,
+    /// Replace the existing proposal
+    Overwrite,
+}
+
+/// The order of the circuits returned by `list_circuits_sorted`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitSortOrder {
+    /// Sort by circuit ID
+    ById,
+    /// Sort by creation time, oldest first. Circuits created at the same time are sorted by ID.
+    ByCreatedAt,
+}
+
+/// Which state files existed when a `YamlAdminServiceStore` was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitOutcome {
+    /// Neither file existed, so both were created with empty state
+    CreatedEmpty,
+    /// Both files existed and their state was loaded
+    LoadedExisting,
+    /// Only one of the files existed. Its state was loaded and the other file was created with
+    /// empty state.
+    PartiallyLoaded,
+}
+
+/// The format of the state files read and written by the `YamlAdminServiceStore`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileFormat {
+    /// YAML, as used by Splinter v0.4
+    #[default]
+    Yaml,
+    /// JSON
+    Json,
+}
+
+impl FileFormat {
+    /// Deserializes a value from a reader
+    ///
+    /// # Arguments
+    ///
+    ///  * `reader` - The reader the value is read from
+    ///  * `description` - A description of the value used in error messages
+    fn read<T: DeserializeOwned, R: Read>(
+        self,
+        reader: R,
+        description: &str,
+    ) -> Result<T, YamlAdminStoreError> {
+        let result = match self {
+            FileFormat::Yaml => serde_yaml::from_reader(reader)
+                .map_err(|err| Box::new(err) as Box<dyn Error + Send + Sync>),
+            FileFormat::Json => serde_json::from_reader(reader)
+                .map_err(|err| Box::new(err) as Box<dyn Error + Send + Sync>),
+        };
+
+        result.map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                &format!("Failed to read {} {}", self, description),
+                err,
+            )
+        })
+    }
+
+    /// Returns whether files in this format end with a newline. YAML files do, following the
+    /// convention for text files, while JSON files end with the closing brace of the document.
+    pub fn trailing_newline(self) -> bool {
+        match self {
+            FileFormat::Yaml => true,
+            FileFormat::Json => false,
+        }
+    }
+
+    /// Serializes a value to bytes, ending with a single newline if the format has a trailing
+    /// newline
+    ///
+    /// # Arguments
+    ///
+    ///  * `value` - The value to serialize
+    ///  * `description` - A description of the value used in error messages
+    fn write<T: Serialize>(
+        self,
+        value: &T,
+        description: &str,
+    ) -> Result<Vec<u8>, YamlAdminStoreError> {
+        let result = match self {
+            FileFormat::Yaml => serde_yaml::to_vec(value)
+                .map_err(|err| Box::new(err) as Box<dyn Error + Send + Sync>),
+            FileFormat::Json => serde_json::to_vec_pretty(value)
+                .map_err(|err| Box::new(err) as Box<dyn Error + Send + Sync>),
+        };
+
+        let mut output = result.map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                &format!("Failed to write {} to {}", description, self),
+                err,
+            )
+        })?;
+
+        if self.trailing_newline() && !output.ends_with(b"\n") {
+            output.push(b'\n');
+        }
+
+        Ok(output)
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileFormat::Yaml => f.write_str("YAML"),
+            FileFormat::Json => f.write_str("JSON"),
+        }
+    }
+}
+
+/// The fields of a circuit in the circuit state file that hold times
+const CIRCUIT_TIMESTAMP_FIELDS: &[&str] = &["created_at", "updated_at"];
+
+/// Epoch times at or above this are read as milliseconds rather than seconds. As seconds it is in
+/// the year 5138, and as milliseconds it is in 1973, so times written by the store in either unit
+/// are read correctly.
+const EPOCH_MILLIS_THRESHOLD: u64 = 100_000_000_000;
+
+/// How the creation and update times of circuits are written to the circuit state file
+///
+/// Times are written to the second in each format. The circuit state file can be read regardless
+/// of the format the times were written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    /// Times are written as RFC 3339 timestamps, such as `"2020-06-01T00:00:00Z"`
+    #[default]
+    Rfc3339,
+    /// Times are written as seconds since the Unix epoch, such as `1590969600`
+    EpochSeconds,
+    /// Times are written as milliseconds since the Unix epoch, such as `1590969600000`
+    EpochMillis,
+}
+
+impl TimestampFormat {
+    /// Rewrites the RFC 3339 timestamps of the given fields of each entry of a collection in
+    /// serialized state in this format. Times before the Unix epoch are left as timestamps.
+    ///
+    /// # Arguments
+    ///
+    ///  * `value` - The serialized state
+    ///  * `collection` - The key of the collection in the state
+    ///  * `fields` - The fields that hold times
+    fn format_fields(self, value: &mut serde_yaml::Value, collection: &str, fields: &[&str]) {
+        if self == TimestampFormat::Rfc3339 {
+            return;
+        }
+
+        let entries = match value
+            .get_mut(collection)
+            .and_then(|entries| entries.as_mapping_mut())
+        {
+            Some(entries) => entries,
+            None => return,
+        };
+
+        for (_, entry) in entries.iter_mut() {
+            for field in fields {
+                if let Some(field_value) = entry.get_mut(*field) {
+                    let since_epoch = field_value
+                        .as_str()
+                        .and_then(|timestamp| humantime::parse_rfc3339_weak(timestamp).ok())
+                        .and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+
+                    if let Some(since_epoch) = since_epoch {
+                        let epoch_time = match self {
+                            TimestampFormat::EpochMillis => since_epoch.as_millis() as u64,
+                            _ => since_epoch.as_secs(),
+                        };
+                        *field_value = serde_yaml::Value::Number(epoch_time.into());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The fields of a circuit in the circuit state file that hold enum values
+const CIRCUIT_ENUM_FIELDS: &[&str] = &["auth", "persistence", "durability", "routes"];
+
+/// The fields of a proposed circuit in the proposal state files that hold enum values
+const PROPOSED_CIRCUIT_ENUM_FIELDS: &[&str] =
+    &["authorization_type", "persistence", "durability", "routes"];
+
+/// How the authorization, persistence, durability and route types of circuits are named when
+/// the state files are written
+///
+/// The state files can be read regardless of the case the values were written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnumCase {
+    /// Values are written as they are named in Splinter v0.4, such as `Trust` and `NoDurability`
+    #[default]
+    PascalCase,
+    /// Values are written in lowercase, such as `trust` and `nodurability`
+    Lowercase,
+    /// Values are written in lowercase with words separated by dashes, such as `trust` and
+    /// `no-durability`
+    KebabCase,
+}
+
+impl EnumCase {
+    /// Returns the name of an enum value, given in Pascal case, in this case
+    fn rename(self, name: &str) -> String {
+        match self {
+            EnumCase::PascalCase => name.to_string(),
+            EnumCase::Lowercase => name.to_lowercase(),
+            EnumCase::KebabCase => {
+                let mut renamed = String::with_capacity(name.len() + 2);
+                for (i, c) in name.chars().enumerate() {
+                    if c.is_uppercase() && i > 0 {
+                        renamed.push('-');
+                    }
+                    renamed.extend(c.to_lowercase());
+                }
+                renamed
+            }
+        }
+    }
+
+    /// Renames the enum values of the given fields of each entry of a collection in serialized
+    /// state
+    ///
+    /// # Arguments
+    ///
+    ///  * `value` - The serialized state
+    ///  * `collection` - The key of the collection in the state
+    ///  * `nested` - The key of the value within each entry that holds the fields, if the fields
+    ///    are not on the entries themselves
+    ///  * `fields` - The fields that hold enum values
+    fn rename_fields(
+        self,
+        value: &mut serde_yaml::Value,
+        collection: &str,
+        nested: Option<&str>,
+        fields: &[&str],
+    ) {
+        let entries = match value
+            .get_mut(collection)
+            .and_then(|entries| entries.as_mapping_mut())
+        {
+            Some(entries) => entries,
+            None => return,
+        };
+
+        for (_, entry) in entries.iter_mut() {
+            let entry = match nested {
+                Some(key) => match entry.get_mut(key) {
+                    Some(nested_entry) => nested_entry,
+                    None => continue,
+                },
+                None => entry,
+            };
+
+            for field in fields {
+                if let Some(field_value) = entry.get_mut(*field) {
+                    if let Some(name) = field_value.as_str() {
+                        *field_value = serde_yaml::Value::String(self.rename(name));
+                    }
+                }
+            }
+        }
+    }
+}
 
 /// A YAML backed implementation of the `AdminServiceStore`
+///
+/// Although the state files are YAML by default, they can be read and written as JSON instead by
+/// setting the file format with `YamlAdminServiceStoreBuilder::with_file_format`.
 pub struct YamlAdminServiceStore {
-    circuit_file_path: String,
-    proposal_file_path: String,
-    state: Arc<Mutex<YamlState>>,
+    paths: RwLock<StatePaths>,
+    state: ArcSwap<YamlState>,
+    write_lock: Mutex<()>,
+    metrics: Option<Arc<dyn StoreMetrics>>,
+    cascade_proposals: CascadeProposals,
+    prune_nodes_on_remove: bool,
+    lenient_proposals: bool,
+    strict_schema: bool,
+    #[cfg(feature = "admin-store-mmap")]
+    mmap_circuit_file: bool,
+    rebuild_on_reload: bool,
+    file_format: FileFormat,
+    enum_case: EnumCase,
+    timestamp_format: TimestampFormat,
+    indent: Option<usize>,
+    init_outcome: InitOutcome,
+    proposal_conflict_policy: ProposalConflictPolicy,
+    node_conflict_policy: NodeConflictPolicy,
+    membership_policy: MembershipPolicy,
+    require_known_members: bool,
+    circuit_validators: Vec<Arc<CircuitValidator>>,
+    in_memory: bool,
+    write_ahead_log: Option<WriteAheadLog>,
+    journal: Option<Journal>,
+    write_retry: WriteRetry,
+    verify_proposal_hashes: bool,
+    clock: Arc<dyn Clock>,
+    file_store: Arc<dyn FileStore>,
+    synced_file_hashes: Mutex<BTreeMap<String, u64>>,
 }
 
 impl YamlAdminServiceStore {
@@ -52,1058 +496,4225 @@ impl YamlAdminServiceStore {
     ///  * `circuit_file_path` - The path to file that contains circuit state
     ///  * `proposal_file_path` - The path to file that contains circuit proposal state
     ///
-    /// Returns an error if the file paths cannot be read from or written to
+    /// Returns the store along with which of the files existed, or an error if the file paths
+    /// cannot be read from or written to
     pub fn new(
         circuit_file_path: String,
         proposal_file_path: String,
+    ) -> Result<(Self, InitOutcome), YamlAdminStoreError> {
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path(&circuit_file_path)
+            .with_proposal_file_path(&proposal_file_path)
+            .build()?;
+        let init_outcome = store.init_outcome();
+
+        Ok((store, init_outcome))
+    }
+
+    /// Creates a new `YamlAdminServiceStore` from YAML circuit and proposal state. The store is
+    /// kept in memory only; changes to it are not written to any file.
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_yaml` - The contents of a circuit state file
+    ///  * `proposal_yaml` - The contents of a proposal state file
+    ///
+    /// Returns an error if the circuit or proposal state cannot be parsed
+    pub fn from_bytes(
+        circuit_yaml: &[u8],
+        proposal_yaml: &[u8],
     ) -> Result<Self, YamlAdminStoreError> {
-        let mut store = YamlAdminServiceStore {
-            circuit_file_path: circuit_file_path.to_string(),
-            proposal_file_path: proposal_file_path.to_string(),
-            state: Arc::new(Mutex::new(YamlState::default())),
+        let store = YamlAdminServiceStore {
+            paths: RwLock::new(StatePaths::default()),
+            state: ArcSwap::from_pointee(YamlState::default()),
+            write_lock: Mutex::new(()),
+            metrics: None,
+            cascade_proposals: CascadeProposals::default(),
+            prune_nodes_on_remove: false,
+            lenient_proposals: false,
+            strict_schema: true,
+            #[cfg(feature = "admin-store-mmap")]
+            mmap_circuit_file: false,
+            rebuild_on_reload: false,
+            file_format: FileFormat::Yaml,
+            enum_case: EnumCase::default(),
+            timestamp_format: TimestampFormat::default(),
+            indent: None,
+            init_outcome: InitOutcome::LoadedExisting,
+            proposal_conflict_policy: ProposalConflictPolicy::default(),
+            node_conflict_policy: NodeConflictPolicy::default(),
+            membership_policy: MembershipPolicy::default(),
+            require_known_members: false,
+            circuit_validators: vec![],
+            in_memory: true,
+            write_ahead_log: None,
+            journal: None,
+            write_retry: WriteRetry::default(),
+            verify_proposal_hashes: false,
+            clock: Arc::new(SystemClock),
+            file_store: Arc::new(OsFileStore),
+            synced_file_hashes: Mutex::new(BTreeMap::new()),
         };
 
-        let circuit_file_path_buf = PathBuf::from(circuit_file_path);
-        let proposal_file_path_buf = PathBuf::from(proposal_file_path);
+        let circuit_state = CircuitState::try_from(circuit_yaml)?;
+        let proposals_state = ProposalState::try_from(proposal_yaml)?;
+
+        {
+            let mut guard = store.lock_state().map_err(|_| {
+                YamlAdminStoreError::general_error(
+                    "YAML admin service store's internal lock poisoned",
+                )
+            })?;
+            let state = Arc::make_mut(&mut guard);
+
+            state.replace_circuit_state(circuit_state);
+            state.proposal_state = proposals_state;
+            guard.publish();
+        }
+
+        Ok(store)
+    }
+
+    /// Returns which of the state files existed when the store was created
+    pub fn init_outcome(&self) -> InitOutcome {
+        self.init_outcome
+    }
+
+    /// Loads the existing state from the store's file paths, creating any files that do not
+    /// exist with empty state
+    fn initialize(&self) -> Result<InitOutcome, YamlAdminStoreError> {
+        let paths = self.paths();
+        let circuit_file_exists = self.file_store.exists(Path::new(&paths.circuit_file_path));
+        let proposal_file_exists = self.file_store.exists(Path::new(&paths.proposal_file_path));
 
         // If file already exists, read it; otherwise initialize it.
-        if circuit_file_path_buf.is_file() && proposal_file_path_buf.is_file() {
-            store.read_state()?;
-        } else if circuit_file_path_buf.is_file() {
+        let init_outcome = if circuit_file_exists && proposal_file_exists {
+            self.read_state()?;
+            InitOutcome::LoadedExisting
+        } else if circuit_file_exists {
             // read circuit
-            store.read_circuit_state()?;
+            self.read_circuit_state()?;
             // write proposals
-            store.write_proposal_state()?;
-        } else if proposal_file_path_buf.is_file() {
+            self.write_proposal_state(&mut self.lock_state().map_err(|_| {
+                YamlAdminStoreError::general_error(
+                    "YAML admin service store's internal lock poisoned",
+                )
+            })?)?;
+            InitOutcome::PartiallyLoaded
+        } else if proposal_file_exists {
             // write circuit
-            store.write_circuit_state()?;
+            self.write_circuit_state(&mut self.lock_state().map_err(|_| {
+                YamlAdminStoreError::general_error(
+                    "YAML admin service store's internal lock poisoned",
+                )
+            })?)?;
             // read proposals
-            store.read_proposal_state()?;
+            self.read_proposal_state()?;
+            InitOutcome::PartiallyLoaded
         } else {
             // write all empty state
-            store.write_state()?;
+            self.write_state(&mut self.lock_state().map_err(|_| {
+                YamlAdminStoreError::general_error(
+                    "YAML admin service store's internal lock poisoned",
+                )
+            })?)?;
+            InitOutcome::CreatedEmpty
+        };
+
+        if let Some(archived_proposal_file_path) = &paths.archived_proposal_file_path {
+            if self
+                .file_store
+                .exists(Path::new(archived_proposal_file_path))
+            {
+                self.read_archived_proposal_state()?;
+            } else {
+                let mut guard = self.lock_state().map_err(|_| {
+                    YamlAdminStoreError::general_error(
+                        "YAML admin service store's internal lock poisoned",
+                    )
+                })?;
+                self.write_archived_proposal_state(&mut guard)?;
+            }
         }
 
-        Ok(store)
+        Ok(init_outcome)
     }
 
-    /// Read circuit state from the circuit file path and cache the contents in the store
-    fn read_circuit_state(&mut self) -> Result<(), YamlAdminStoreError> {
-        let circuit_file = File::open(&self.circuit_file_path).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                "Failed to open YAML circuit state file",
-                Box::new(err),
-            )
-        })?;
-
-        let yaml_state_circuits: YamlCircuitState = serde_yaml::from_reader(&circuit_file)
-            .map_err(|err| {
-                YamlAdminStoreError::general_error_with_source(
-                    "Failed to read YAML circuit state file",
-                    Box::new(err),
-                )
-            })?;
-
-        let yaml_state = CircuitState::from(yaml_state_circuits);
+    /// Applies any changes recorded in the write-ahead log to the cached state, then writes the
+    /// state files and truncates the log. Does nothing if the store does not have a write-ahead
+    /// log.
+    fn recover_from_write_ahead_log(&self) -> Result<(), YamlAdminStoreError> {
+        let write_ahead_log = match &self.write_ahead_log {
+            Some(write_ahead_log) => write_ahead_log,
+            None => return Ok(()),
+        };
 
-        let mut state = self.state.lock().map_err(|_| {
+        let mut guard = self.lock_state().map_err(|_| {
             YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
         })?;
 
-        for (circuit_id, circuit) in yaml_state.circuits.iter() {
-            for service in circuit.roster.iter() {
-                let service_id =
-                    ServiceId::new(service.service_id.to_string(), circuit_id.to_string());
-
-                state.service_directory.insert(service_id, service.clone());
-            }
+        let replayed = write_ahead_log.replay(Arc::make_mut(&mut guard))?;
+        // the replayed changes are in the write-ahead log, so are published before the state
+        // files are written
+        guard.publish();
+        if replayed > 0 {
+            info!("Replayed {} records from the write-ahead log", replayed);
+            self.write_state_files(&guard)?;
         }
 
-        state.circuit_state = yaml_state;
-        Ok(())
+        write_ahead_log.checkpoint()
     }
 
-    /// Read circuit proposal state from the proposal file path and cache the contents in the
-    /// store
-    fn read_proposal_state(&mut self) -> Result<(), YamlAdminStoreError> {
-        let proposal_file = File::open(&self.proposal_file_path).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                "Failed to open YAML proposal state file",
-                Box::new(err),
-            )
-        })?;
+    /// Returns the IDs of the proposals that could not be parsed when the proposal state file was
+    /// loaded
+    ///
+    /// Proposals are only skipped if the store was built with
+    /// `YamlAdminServiceStoreBuilder::with_lenient_proposals`; otherwise an unparseable proposal
+    /// fails the load and this is always empty.
+    pub fn skipped_proposals(&self) -> Result<Vec<String>, AdminServiceStoreError> {
+        Ok(self.shared_state()?.skipped_proposals.clone())
+    }
 
-        let proposals_state: ProposalState =
-            serde_yaml::from_reader(&proposal_file).map_err(|err| {
-                YamlAdminStoreError::general_error_with_source(
-                    "Failed to read YAML proposal state file",
-                    Box::new(err),
-                )
+    /// Reloads the circuit and proposal state from the store's files, replacing the cached state
+    ///
+    /// The service directory is updated from the reloaded circuit rosters. If the store was built
+    /// with `YamlAdminServiceStoreBuilder::with_rebuild_on_reload`, the directory is rebuilt from
+    /// scratch afterwards, as with `rebuild_service_directory`.
+    ///
+    /// Returns an error if the files cannot be read, or if the store was created with
+    /// `from_bytes` and has no files
+    pub fn reload(&self) -> Result<(), AdminServiceStoreError> {
+        if self.in_memory {
+            return Err(AdminServiceStoreError::OperationError {
+                context: "An in-memory YAML admin service store cannot be reloaded".to_string(),
+                source: None,
+            });
+        }
+
+        self.read_state()
+            .and_then(|_| self.recover_from_write_ahead_log())
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to reload yaml state files".to_string(),
+                source: Some(Box::new(err)),
             })?;
 
-        let mut state = self.state.lock().map_err(|_| {
-            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
+        self.read_archived_proposal_state().map_err(|err| {
+            AdminServiceStoreError::StorageError {
+                context: "Unable to reload archived proposal state yaml file".to_string(),
+                source: Some(Box::new(err)),
+            }
         })?;
 
-        state.proposal_state = proposals_state;
-        Ok(())
+        if self.rebuild_on_reload {
+            self.rebuild_service_directory()
+        } else {
+            let state = self.lock_state()?;
+            debug_assert_service_directory(&state);
+            Ok(())
+        }
     }
 
-    /// Read circuit state from the circuit file path and cache the contents in the store and then
-    /// read circuit proposal state from the proposal file path and cache the contents in the
-    /// store
-    fn read_state(&mut self) -> Result<(), YamlAdminStoreError> {
-        let circuit_file = File::open(&self.circuit_file_path).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                "Failed to open YAML circuit state file",
-                Box::new(err),
-            )
-        })?;
+    /// Reloads the store's state, as with `reload`, if the circuit or proposal state file has
+    /// changed since the store last read or wrote it
+    ///
+    /// Returns whether the state was reloaded
+    pub fn reload_if_changed(&self) -> Result<bool, AdminServiceStoreError> {
+        if self.in_memory {
+            return Ok(false);
+        }
 
-        let yaml_state_circuits: YamlCircuitState = serde_yaml::from_reader(&circuit_file)
-            .map_err(|err| {
-                YamlAdminStoreError::general_error_with_source(
-                    "Failed to read YAML circuit state file",
-                    Box::new(err),
-                )
-            })?;
+        let paths = self.paths();
+        let changed = self.state_file_changed(&paths.circuit_file_path)
+            || self.state_file_changed(&paths.proposal_file_path);
+        if changed {
+            self.reload()?;
+        }
 
-        let yaml_state = CircuitState::from(yaml_state_circuits);
+        Ok(changed)
+    }
 
-        let proposal_file = File::open(&self.proposal_file_path).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                "Failed to open YAML proposal state file",
-                Box::new(err),
-            )
-        })?;
+    /// Starts watching the circuit and proposal state files, reloading the store whenever either
+    /// is changed by something other than the store, as with `reload_if_changed`
+    ///
+    /// The files are watched on the operating system's filesystem, so the watcher has no effect
+    /// if the store was built with a different file store. The files are watched until the
+    /// returned watcher is dropped.
+    ///
+    /// Returns an error if the store was created with `from_bytes` and has no files, or if the
+    /// files cannot be watched
+    #[cfg(feature = "admin-store-watch")]
+    pub fn watch_state_files(self: &Arc<Self>) -> Result<StateFileWatcher, AdminServiceStoreError> {
+        if self.in_memory {
+            return Err(AdminServiceStoreError::OperationError {
+                context: "An in-memory YAML admin service store has no files to watch".to_string(),
+                source: None,
+            });
+        }
 
-        let proposals_state: ProposalState =
-            serde_yaml::from_reader(&proposal_file).map_err(|err| {
-                YamlAdminStoreError::general_error_with_source(
-                    "Failed to read YAML proposal state file",
-                    Box::new(err),
-                )
-            })?;
+        let paths = self.paths();
+        StateFileWatcher::new(
+            Arc::downgrade(self),
+            &[&paths.circuit_file_path, &paths.proposal_file_path],
+        )
+        .map_err(|err| AdminServiceStoreError::StorageError {
+            context: "Unable to watch yaml state files".to_string(),
+            source: Some(Box::new(err)),
+        })
+    }
 
-        let mut state = self.state.lock().map_err(|_| {
-            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
+    /// Moves the store's state files to new paths, writing the current state to the new files.
+    /// All later changes are written to the new files.
+    ///
+    /// The archived proposal file and the circuit cache, if the store has them, are moved next to
+    /// the new proposal and circuit state files. The write-ahead log and journal directory stay
+    /// where they are, and a watcher started by `watch_state_files` keeps watching the old files.
+    ///
+    /// # Arguments
+    ///
+    ///  * `new_circuit_path` - The path the circuit state is written to
+    ///  * `new_proposal_path` - The path the proposal state is written to
+    ///  * `remove_old_files` - Whether the old state files are removed once the state has been
+    ///    written to the new files
+    ///
+    /// Returns an error if the store was created with `from_bytes` and has no files, or if the
+    /// new files cannot be written, in which case the store keeps using the old files. If an old
+    /// file cannot be removed an error is returned, but the store uses the new files.
+    pub fn relocate(
+        &self,
+        new_circuit_path: String,
+        new_proposal_path: String,
+        remove_old_files: bool,
+    ) -> Result<(), AdminServiceStoreError> {
+        if self.in_memory {
+            return Err(AdminServiceStoreError::OperationError {
+                context: "An in-memory YAML admin service store cannot be relocated".to_string(),
+                source: None,
+            });
+        }
+
+        // The state stays locked until the new files are written, so no change is written to the
+        // old files after they have been copied
+        let state = self.lock_state()?;
+        let old_paths = self.paths();
+        let new_paths = StatePaths::new(
+            new_circuit_path,
+            new_proposal_path,
+            old_paths.archived_proposal_file_path.is_some(),
+            old_paths.circuit_cache_path.is_some(),
+        )
+        .map_err(|err| AdminServiceStoreError::OperationError {
+            context: "Unable to relocate yaml state files".to_string(),
+            source: Some(Box::new(err)),
         })?;
 
-        for (circuit_id, circuit) in yaml_state.circuits.iter() {
-            for service in circuit.roster.iter() {
-                let service_id =
-                    ServiceId::new(service.service_id.to_string(), circuit_id.to_string());
+        self.set_paths(new_paths.clone());
+        if let Err(err) = self.write_state_files(&state) {
+            self.set_paths(old_paths);
+            return Err(AdminServiceStoreError::StorageError {
+                context: "Unable to write yaml state files to their new paths".to_string(),
+                source: Some(Box::new(err)),
+            });
+        }
 
-                state.service_directory.insert(service_id, service.clone());
-            }
+        // The new files hold everything that was logged, so the log can be truncated
+        if let Some(write_ahead_log) = &self.write_ahead_log {
+            write_ahead_log
+                .checkpoint()
+                .map_err(|err| AdminServiceStoreError::StorageError {
+                    context: "Unable to checkpoint write-ahead log".to_string(),
+                    source: Some(Box::new(err)),
+                })?;
         }
 
-        state.circuit_state = yaml_state;
-        state.proposal_state = proposals_state;
+        if remove_old_files {
+            let new_files = new_paths.files();
+            for path in old_paths.files() {
+                if new_files.contains(&path) || !self.file_store.exists(Path::new(path)) {
+                    continue;
+                }
+
+                self.file_store.remove(Path::new(path)).map_err(|err| {
+                    AdminServiceStoreError::StorageError {
+                        context: format!("Unable to remove old state file '{}'", path),
+                        source: Some(Box::new(err)),
+                    }
+                })?;
+            }
+        }
 
         Ok(())
     }
 
-    /// Write the current circuit state to file at the circuit file path
-    fn write_circuit_state(&self) -> Result<(), YamlAdminStoreError> {
-        let state = self.state.lock().map_err(|_| {
-            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
-        })?;
-
-        let circuit_output = serde_yaml::to_vec(&YamlCircuitState::from(
-            state.circuit_state.clone(),
-        ))
-        .map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                "Failed to write circuit state to YAML",
-                Box::new(err),
-            )
-        })?;
+    /// Replaces the paths of the state files
+    fn set_paths(&self, paths: StatePaths) {
+        *self.paths.write().unwrap_or_else(PoisonError::into_inner) = paths;
+    }
 
-        let mut circuit_file = File::create(&self.circuit_file_path).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to open YAML circuit state file '{}'",
-                    self.circuit_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+    /// Returns whether the contents of a state file differ from the contents the store last read
+    /// from or wrote to it. A file that cannot be read is not considered changed.
+    fn state_file_changed(&self, path: &str) -> bool {
+        let contents = match self.file_store.read(Path::new(path)) {
+            Ok(contents) => contents,
+            Err(_) => return false,
+        };
 
-        circuit_file.write_all(&circuit_output).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to write to YAML circuit state file '{}'",
-                    self.circuit_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+        match self.synced_file_hashes.lock() {
+            Ok(hashes) => hashes.get(path) != Some(&contents_hash(&contents)),
+            Err(_) => true,
+        }
+    }
 
-        // Append newline to file
-        writeln!(circuit_file).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to write to YAML circuit file '{}'",
-                    self.circuit_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+    /// Records the contents of a state file the store has read or written, so that changes made
+    /// to the file outside of the store can be detected
+    fn record_synced_file(&self, path: &str, contents: &[u8]) {
+        if let Ok(mut hashes) = self.synced_file_hashes.lock() {
+            hashes.insert(path.to_string(), contents_hash(contents));
+        }
+    }
 
-        Ok(())
+    /// Finishes a write of the locked state. If the write succeeded, the state is published to
+    /// readers. If it failed, the locked state is rolled back to the state before the change, so
+    /// the cached state is not left ahead of the files.
+    ///
+    /// # Arguments
+    ///
+    ///  * `guard` - The guard holding the state that was written
+    ///  * `result` - The result of the write
+    fn finish_write<T>(
+        &self,
+        guard: &mut StateGuard<'_>,
+        result: Result<T, YamlAdminStoreError>,
+    ) -> Result<T, YamlAdminStoreError> {
+        match result {
+            Ok(value) => {
+                guard.publish();
+                Ok(value)
+            }
+            Err(err) if is_read_only(&err) => {
+                guard.roll_back();
+                Err(YamlAdminStoreError::general_error_with_source(
+                    "Unable to write state files on a read-only filesystem; the change has been \
+                     rolled back",
+                    Box::new(err),
+                ))
+            }
+            Err(err) => {
+                guard.roll_back();
+                Err(err)
+            }
+        }
     }
 
-    /// Write the current circuit proposal state to file at the proposal file path
-    fn write_proposal_state(&self) -> Result<(), YamlAdminStoreError> {
-        let state = self.state.lock().map_err(|_| {
-            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
-        })?;
+    /// Removes all circuits, nodes and proposals from the store and writes both state files with
+    /// empty state. Archived proposals are not removed.
+    pub fn reset(&self) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        guard.record(StateChange::State);
+        let state = Arc::make_mut(&mut guard);
 
-        let proposal_output = serde_yaml::to_vec(&state.proposal_state).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                "Failed to write proposal state to YAML",
-                Box::new(err),
-            )
-        })?;
+        state.circuit_state = CircuitState::default();
+        state.proposal_state = ProposalState::default();
+        state.service_directory = BTreeMap::new();
 
-        let mut proposal_file = File::create(&self.proposal_file_path).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to open YAML proposal state file '{}'",
-                    self.proposal_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+        self.write_state(&mut guard)
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to write yaml state files".to_string(),
+                source: Some(Box::new(err)),
+            })
+    }
 
-        proposal_file.write_all(&proposal_output).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to write to YAML proposal state file '{}'",
-                    self.proposal_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+    /// Discards the service directory and reconstructs it from the rosters of the cached
+    /// circuits. A warning is logged if the directory did not match the rosters.
+    pub fn rebuild_service_directory(&self) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        let service_directory = build_service_directory(&guard.circuit_state.circuits);
 
-        // Append newline to file
-        writeln!(proposal_file).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to write to YAML proposal file '{}'",
-                    self.proposal_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+        if guard.service_directory != service_directory {
+            warn!("Service directory does not match the circuit rosters, rebuilding it");
+            Arc::make_mut(&mut guard).service_directory = service_directory;
+        }
+        guard.publish();
 
         Ok(())
     }
 
-    /// Write the current circuit state to file at the circuit file path and then write the current
-    /// proposal state to the file at the proposal file path
-    fn write_state(&self) -> Result<(), YamlAdminStoreError> {
-        let state = self.state.lock().map_err(|_| {
-            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
-        })?;
-
-        let circuit_output = serde_yaml::to_vec(&YamlCircuitState::from(
-            state.circuit_state.clone(),
-        ))
-        .map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                "Failed to write circuit state to YAML",
-                Box::new(err),
-            )
-        })?;
-
-        let mut circuit_file = File::create(&self.circuit_file_path).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to open YAML circuit state file '{}'",
-                    self.circuit_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
-
-        circuit_file.write_all(&circuit_output).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to write to YAML circuit state file '{}'",
-                    self.circuit_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
-
-        // Append newline to file
-        writeln!(circuit_file).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to write to YAML circuit file '{}'",
-                    self.circuit_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
-
-        let proposal_output = serde_yaml::to_vec(&state.proposal_state).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                "Failed to write proposal state to YAML",
-                Box::new(err),
-            )
-        })?;
-
-        let mut proposal_file = File::create(&self.proposal_file_path).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to open YAML proposal state file '{}'",
-                    self.proposal_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+    /// Returns whether a service is part of a circuit in the store, without fetching the service
+    ///
+    /// # Arguments
+    ///
+    ///  * `service_id` - The `ServiceId` of a service made up of the circuit ID and service ID
+    pub fn service_exists(&self, service_id: &ServiceId) -> Result<bool, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .service_directory
+            .contains_key(service_id))
+    }
 
-        proposal_file.write_all(&proposal_output).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to write to YAML proposal state file '{}'",
-                    self.proposal_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+    /// Lists the `ServiceId` of every service that is part of a circuit in the store, in
+    /// `ServiceId` order, without fetching the services
+    pub fn list_service_ids(&self) -> Result<Vec<ServiceId>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .service_directory
+            .keys()
+            .cloned()
+            .collect())
+    }
 
-        // Append newline to file
-        writeln!(proposal_file).map_err(|err| {
-            YamlAdminStoreError::general_error_with_source(
-                &format!(
-                    "Failed to write to YAML proposal file '{}'",
-                    self.proposal_file_path
-                ),
-                Box::new(err),
-            )
-        })?;
+    /// Returns the IDs of the circuits each node is a member of, keyed by node ID. Circuit IDs are
+    /// listed in ID order. Nodes that are not a member of any circuit are not included.
+    pub fn circuits_by_node(
+        &self,
+    ) -> Result<BTreeMap<String, Vec<String>>, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        let mut circuits_by_node: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (circuit_id, circuit) in state.circuit_state.circuits.iter() {
+            for member in circuit.members.iter() {
+                circuits_by_node
+                    .entry(member.to_string())
+                    .or_default()
+                    .push(circuit_id.to_string());
+            }
+        }
 
-        Ok(())
+        Ok(circuits_by_node)
     }
-}
 
-/// Defines methods for CRUD operations and fetching and listing circuits, proposals, nodes and
-/// services from a YAML file backend
-impl AdminServiceStore for YamlAdminServiceStore {
-    /// Adds a circuit proposal to the underlying storage
+    /// Adds a node to the store if a node with the same ID does not already exist. The circuit
+    /// state file is only written if the node was added.
     ///
     /// # Arguments
     ///
-    ///  * `proposal` - The proposal to be added
+    ///  * `node` - The node to add
     ///
-    ///  Returns an error if a `CircuitProposal` with the same ID already exists
-    fn add_proposal(&self, proposal: CircuitProposal) -> Result<(), AdminServiceStoreError> {
-        {
-            let mut state =
-                self.state
-                    .lock()
-                    .map_err(|_| AdminServiceStoreError::StorageError {
-                        context: "YAML admin service store's internal lock was poisoned"
-                            .to_string(),
-                        source: None,
-                    })?;
-
-            if state
-                .proposal_state
-                .proposals
-                .contains_key(&proposal.circuit_id)
-            {
-                return Err(AdminServiceStoreError::OperationError {
-                    context: format!("A proposal with ID {} already exists", proposal.circuit_id),
-                    source: None,
-                });
-            } else {
-                state
-                    .proposal_state
-                    .proposals
-                    .insert(proposal.circuit_id.to_string(), proposal);
-            }
+    /// Returns `true` if the node was added, or `false` if a node with the same ID already existed
+    pub fn ensure_node(&self, node: CircuitNode) -> Result<bool, AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        // check before calling make_mut, which may copy the state
+        if guard.circuit_state.nodes.contains_key(&node.id) {
+            return Ok(false);
         }
 
-        self.write_proposal_state()
-            .map_err(|err| AdminServiceStoreError::StorageError {
-                context: "Unable to write proposal state yaml file".to_string(),
+        guard.record(StateChange::Node(node.id.to_string()));
+        Arc::make_mut(&mut guard).insert_node_if_absent(node);
+        self.write_circuit_state(&mut guard).map_err(|err| {
+            AdminServiceStoreError::StorageError {
+                context: "Unable to write circuit state yaml file".to_string(),
                 source: Some(Box::new(err)),
-            })
+            }
+        })?;
+
+        Ok(true)
     }
 
-    /// Updates a circuit proposal in the underlying storage
+    /// Changes the ID of a circuit, moving its services in the service directory to the new ID.
+    /// Proposals are not changed.
     ///
     /// # Arguments
     ///
-    ///  * `proposal` - The proposal with the updated information
+    ///  * `old_id` - The current ID of the circuit
+    ///  * `new_id` - The ID the circuit will be renamed to
     ///
-    ///  Returns an error if a `CircuitProposal` with the same ID does not exist
-    fn update_proposal(&self, proposal: CircuitProposal) -> Result<(), AdminServiceStoreError> {
-        {
-            let mut state =
-                self.state
-                    .lock()
-                    .map_err(|_| AdminServiceStoreError::StorageError {
-                        context: "YAML admin service store's internal lock was poisoned"
-                            .to_string(),
-                        source: None,
-                    })?;
+    /// Returns an error if a circuit with `old_id` does not exist or a circuit with `new_id`
+    /// already exists
+    pub fn rename_circuit(&self, old_id: &str, new_id: &str) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+
+        if !guard.circuit_state.circuits.contains_key(old_id) {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!("A circuit with ID {} does not exist", old_id),
+                source: None,
+            });
+        }
 
-            if state
-                .proposal_state
-                .proposals
-                .contains_key(&proposal.circuit_id)
-            {
-                state
-                    .proposal_state
-                    .proposals
-                    .insert(proposal.circuit_id.to_string(), proposal);
-            } else {
-                return Err(AdminServiceStoreError::OperationError {
-                    context: format!("A proposal with ID {} does not exist", proposal.circuit_id),
-                    source: None,
-                });
+        if guard.circuit_state.circuits.contains_key(new_id) {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!("A circuit with ID {} already exists", new_id),
+                source: None,
+            });
+        }
+
+        guard.record(StateChange::Circuit(old_id.to_string()));
+        guard.record(StateChange::Circuit(new_id.to_string()));
+        let state = Arc::make_mut(&mut guard);
+
+        if let Some(mut circuit) = state.circuit_state.circuits.remove(old_id) {
+            for service in circuit.roster.iter() {
+                state.service_directory.remove(&ServiceId::new(
+                    service.service_id.to_string(),
+                    old_id.to_string(),
+                ));
+                state.service_directory.insert(
+                    ServiceId::new(service.service_id.to_string(), new_id.to_string()),
+                    service.clone(),
+                );
             }
+
+            circuit.id = new_id.to_string();
+            circuit.updated_at = self.clock.now();
+            state
+                .circuit_state
+                .circuits
+                .insert(new_id.to_string(), circuit);
         }
 
-        self.write_proposal_state()
+        debug_assert_service_directory(state);
+
+        self.write_circuit_state(&mut guard)
             .map_err(|err| AdminServiceStoreError::StorageError {
-                context: "Unable to write proposal state yaml file".to_string(),
+                context: "Unable to write circuit state yaml file".to_string(),
                 source: Some(Box::new(err)),
             })
     }
 
-    /// Removes a circuit proposal from the underlying storage
+    /// Marks a circuit as disbanded
+    ///
+    /// A disbanded circuit remains in the store and can still be fetched; it can be excluded when
+    /// listing circuits with `CircuitPredicate::Status(CircuitStatus::Active)`.
     ///
     /// # Arguments
     ///
-    ///  * `proposal_id` - The unique ID of the circuit proposal to be removed
+    ///  * `circuit_id` - The ID of the circuit to disband
     ///
-    ///  Returns an error if a `CircuitProposal` with specified ID does not exist
-    fn remove_proposal(&self, proposal_id: &str) -> Result<(), AdminServiceStoreError> {
-        {
-            let mut state =
-                self.state
-                    .lock()
-                    .map_err(|_| AdminServiceStoreError::StorageError {
-                        context: "YAML admin service store's internal lock was poisoned"
-                            .to_string(),
-                        source: None,
-                    })?;
+    /// Returns an error if a circuit with `circuit_id` does not exist
+    pub fn disband_circuit(&self, circuit_id: &str) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
 
-            if state.proposal_state.proposals.contains_key(proposal_id) {
-                state.proposal_state.proposals.remove(proposal_id);
-            } else {
-                return Err(AdminServiceStoreError::OperationError {
-                    context: format!("A proposal with ID {} does not exist", proposal_id),
-                    source: None,
-                });
-            }
+        if !guard.circuit_state.circuits.contains_key(circuit_id) {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!("A circuit with ID {} does not exist", circuit_id),
+                source: None,
+            });
+        }
+
+        guard.record(StateChange::Circuit(circuit_id.to_string()));
+        let state = Arc::make_mut(&mut guard);
+
+        if let Some(circuit) = state.circuit_state.circuits.get_mut(circuit_id) {
+            circuit.circuit_status = CircuitStatus::Disbanded;
+            circuit.updated_at = self.clock.now();
         }
 
-        self.write_proposal_state()
+        self.write_circuit_state(&mut guard)
             .map_err(|err| AdminServiceStoreError::StorageError {
-                context: "Unable to write proposal state yaml file".to_string(),
+                context: "Unable to write circuit state yaml file".to_string(),
                 source: Some(Box::new(err)),
             })
     }
 
-    /// Fetches a circuit proposal from the underlying storage
+    /// Sets or clears the human-readable name of a circuit, leaving the rest of the circuit
+    /// unchanged
     ///
     /// # Arguments
     ///
-    ///  * `proposal_id` - The unique ID of the circuit proposal to be returned
-    fn fetch_proposal(
-        &self,
-        proposal_id: &str,
-    ) -> Result<Option<CircuitProposal>, AdminServiceStoreError> {
-        Ok(self
-            .state
-            .lock()
-            .map_err(|_| AdminServiceStoreError::StorageError {
-                context: "YAML admin service store's internal lock was poisoned".to_string(),
-                source: None,
-            })?
-            .proposal_state
-            .proposals
-            .get(proposal_id)
-            .cloned())
-    }
-
-    /// List circuit proposals from the underlying storage
+    ///  * `circuit_id` - The ID of the circuit to name
+    ///  * `display_name` - The new display name, or `None` to remove the circuit's display name
     ///
-    /// The proposals returned can be filtered by provided CircuitPredicate. This enables
-    /// filtering by management type and members.
-    fn list_proposals(
+    /// Returns an error if a circuit with `circuit_id` does not exist
+    pub fn set_circuit_display_name(
         &self,
-        predicates: &[CircuitPredicate],
-    ) -> Result<Box<dyn ExactSizeIterator<Item = CircuitProposal>>, AdminServiceStoreError> {
-        let mut proposals: Vec<CircuitProposal> = self
-            .state
-            .lock()
-            .map_err(|_| AdminServiceStoreError::StorageError {
-                context: "YAML admin service store's internal lock was poisoned".to_string(),
+        circuit_id: &str,
+        display_name: Option<String>,
+    ) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+
+        if !guard.circuit_state.circuits.contains_key(circuit_id) {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!("A circuit with ID {} does not exist", circuit_id),
                 source: None,
-            })?
-            .proposal_state
-            .proposals
-            .iter()
-            .map(|(_, proposal)| proposal.clone())
-            .collect::<Vec<CircuitProposal>>();
+            });
+        }
 
-        proposals.retain(|proposal| {
-            predicates
-                .iter()
-                .all(|predicate| predicate.apply_to_proposals(proposal))
-        });
+        guard.record(StateChange::Circuit(circuit_id.to_string()));
+        let state = Arc::make_mut(&mut guard);
+
+        if let Some(circuit) = state.circuit_state.circuits.get_mut(circuit_id) {
+            circuit.display_name = display_name;
+            circuit.updated_at = self.clock.now();
+        }
 
-        Ok(Box::new(proposals.into_iter()))
+        self.write_circuit_state(&mut guard)
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to write circuit state yaml file".to_string(),
+                source: Some(Box::new(err)),
+            })
     }
 
-    /// Adds a circuit to the underlying storage. Also includes the associated Services and
-    /// Nodes
+    /// Adds a service to the roster of a circuit, leaving the rest of the circuit unchanged
+    ///
+    /// The updated circuit is checked against the store's membership policy and circuit
+    /// validators, as with `update_circuit`.
     ///
     /// # Arguments
     ///
-    ///  * `circuit` - The circuit to be added to state
-    ///  * `nodes` - A list of nodes that represent the circuit's members
+    ///  * `circuit_id` - The ID of the circuit the service is added to
+    ///  * `service` - The service to add
     ///
-    ///  Returns an error if a `Circuit` with the same ID already exists
-    fn add_circuit(
+    /// Returns an error if a circuit with `circuit_id` does not exist, if the circuit already has
+    /// a service with the same ID, or if the updated circuit is rejected
+    pub fn add_service_to_circuit(
         &self,
-        circuit: Circuit,
-        nodes: Vec<CircuitNode>,
+        circuit_id: &str,
+        service: Service,
     ) -> Result<(), AdminServiceStoreError> {
-        {
-            let mut state =
-                self.state
-                    .lock()
-                    .map_err(|_| AdminServiceStoreError::StorageError {
-                        context: "YAML admin service store's internal lock was poisoned"
-                            .to_string(),
-                        source: None,
-                    })?;
+        let mut guard = self.lock_state()?;
 
-            if state.circuit_state.circuits.contains_key(&circuit.id) {
+        let mut circuit = match guard.circuit_state.circuits.get(circuit_id) {
+            Some(circuit) => circuit.clone(),
+            None => {
                 return Err(AdminServiceStoreError::OperationError {
-                    context: format!("A circuit with ID {} already exists", circuit.id),
+                    context: format!("A circuit with ID {} does not exist", circuit_id),
                     source: None,
-                });
-            } else {
-                for service in circuit.roster.iter() {
-                    let service_id =
-                        ServiceId::new(service.service_id.to_string(), circuit.id.to_string());
+                })
+            }
+        };
 
-                    state.service_directory.insert(service_id, service.clone());
-                }
+        if circuit
+            .roster
+            .iter()
+            .any(|existing| existing.service_id == service.service_id)
+        {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!(
+                    "Circuit {} already has a service with ID {}",
+                    circuit_id, service.service_id
+                ),
+                source: None,
+            });
+        }
 
-                for node in nodes.into_iter() {
-                    if !state.circuit_state.nodes.contains_key(&node.id) {
-                        state.circuit_state.nodes.insert(node.id.to_string(), node);
-                    }
-                }
+        circuit.roster.push(service);
+        self.check_allowed_nodes(&circuit)?;
+        self.check_circuit_validators(&circuit)?;
+        circuit.updated_at = self.clock.now();
 
-                state
-                    .circuit_state
-                    .circuits
-                    .insert(circuit.id.to_string(), circuit);
-            }
-        }
+        guard.record(StateChange::Circuit(circuit_id.to_string()));
+        let state = Arc::make_mut(&mut guard);
+        state.insert_circuit(circuit);
+
+        debug_assert_service_directory(state);
 
-        self.write_circuit_state()
+        self.write_circuit_state(&mut guard)
             .map_err(|err| AdminServiceStoreError::StorageError {
                 context: "Unable to write circuit state yaml file".to_string(),
                 source: Some(Box::new(err)),
             })
     }
 
-    /// Updates a circuit in the underlying storage
+    /// Removes a service from the roster of a circuit, leaving the rest of the circuit unchanged
+    ///
+    /// The updated circuit is checked against the store's circuit validators, as with
+    /// `update_circuit`.
     ///
     /// # Arguments
     ///
-    ///  * `circuit` - The circuit with the updated information
+    ///  * `circuit_id` - The ID of the circuit the service is removed from
+    ///  * `service_id` - The ID of the service to remove
     ///
-    ///  Returns an error if a `CircuitProposal` with the same ID does not exist
-    fn update_circuit(&self, circuit: Circuit) -> Result<(), AdminServiceStoreError> {
-        {
-            let mut state =
-                self.state
-                    .lock()
-                    .map_err(|_| AdminServiceStoreError::StorageError {
-                        context: "YAML admin service store's internal lock was poisoned"
-                            .to_string(),
-                        source: None,
-                    })?;
+    /// Returns an error if a circuit with `circuit_id` does not exist, if the circuit does not
+    /// have a service with `service_id`, or if the updated circuit is rejected
+    pub fn remove_service_from_circuit(
+        &self,
+        circuit_id: &str,
+        service_id: &str,
+    ) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
 
-            if state.circuit_state.circuits.contains_key(&circuit.id) {
-                state
-                    .circuit_state
-                    .circuits
-                    .insert(circuit.id.to_string(), circuit);
-            } else {
+        let mut circuit = match guard.circuit_state.circuits.get(circuit_id) {
+            Some(circuit) => circuit.clone(),
+            None => {
                 return Err(AdminServiceStoreError::OperationError {
-                    context: format!("A circuit with ID {} does not exist", circuit.id),
+                    context: format!("A circuit with ID {} does not exist", circuit_id),
                     source: None,
-                });
+                })
             }
+        };
+
+        let roster_len = circuit.roster.len();
+        circuit
+            .roster
+            .retain(|service| service.service_id != service_id);
+        if circuit.roster.len() == roster_len {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!(
+                    "Circuit {} does not have a service with ID {}",
+                    circuit_id, service_id
+                ),
+                source: None,
+            });
         }
 
-        self.write_circuit_state()
+        self.check_circuit_validators(&circuit)?;
+        circuit.updated_at = self.clock.now();
+
+        guard.record(StateChange::Circuit(circuit_id.to_string()));
+        let state = Arc::make_mut(&mut guard);
+        state.insert_circuit(circuit);
+
+        debug_assert_service_directory(state);
+
+        self.write_circuit_state(&mut guard)
             .map_err(|err| AdminServiceStoreError::StorageError {
                 context: "Unable to write circuit state yaml file".to_string(),
                 source: Some(Box::new(err)),
             })
     }
 
-    /// Removes a circuit from the underlying storage
+    /// Adds a circuit if a circuit with the same ID does not exist, or updates the existing
+    /// circuit if it does. The nodes are added if they do not already exist, as with
+    /// `add_circuit`. The circuit is checked as it would be by `add_circuit` or `update_circuit`.
     ///
     /// # Arguments
     ///
-    ///  * `circuit_id` - The unique ID of the circuit to be removed
+    ///  * `circuit` - The circuit to be added or updated
+    ///  * `nodes` - A list of the nodes that are members of the circuit
     ///
-    ///  Returns an error if a `Circuit` with the specified ID does not exist
-    fn remove_circuit(&self, circuit_id: &str) -> Result<(), AdminServiceStoreError> {
-        {
-            let mut state =
-                self.state
-                    .lock()
-                    .map_err(|_| AdminServiceStoreError::StorageError {
-                        context: "YAML admin service store's internal lock was poisoned"
-                            .to_string(),
-                        source: None,
-                    })?;
-            if state.circuit_state.circuits.contains_key(circuit_id) {
-                let circuit = state.circuit_state.circuits.remove(circuit_id);
-                if let Some(circuit) = circuit {
-                    for service in circuit.roster.iter() {
-                        let service_id =
-                            ServiceId::new(service.service_id.to_string(), circuit_id.to_string());
-                        state.service_directory.remove(&service_id);
-                    }
-                }
-            } else {
-                return Err(AdminServiceStoreError::OperationError {
-                    context: format!("A circuit with ID {} does not exist", circuit_id),
-                    source: None,
-                });
+    /// Returns `true` if the circuit was added or `false` if an existing circuit was updated, or
+    /// an error if the circuit is rejected by the membership policy or a circuit validator, if a
+    /// node conflicts with an existing node under `NodeConflictPolicy::Error`, or if the circuit
+    /// is new and the store requires known members and a member has no node definition
+    pub fn upsert_circuit(
+        &self,
+        circuit: Circuit,
+        nodes: Vec<CircuitNode>,
+    ) -> Result<bool, AdminServiceStoreError> {
+        let mut circuit = circuit;
+
+        let mut guard = self.lock_state()?;
+        let inserted = {
+            self.check_allowed_nodes(&circuit)?;
+            self.check_circuit_validators(&circuit)?;
+            self.check_node_conflicts(&guard, &circuit.id, &nodes)?;
+            if !guard.circuit_state.circuits.contains_key(&circuit.id) {
+                self.check_known_members(&guard, &circuit, &nodes)?;
             }
-        }
 
-        self.write_circuit_state()
-            .map_err(|err| AdminServiceStoreError::StorageError {
+            guard.record(StateChange::Circuit(circuit.id.to_string()));
+            guard.record_nodes(nodes.iter().map(|node| &node.id));
+            let state = Arc::make_mut(&mut guard);
+
+            let inserted = match state.circuit_state.circuits.get(&circuit.id) {
+                Some(existing) => {
+                    circuit.created_at = existing.created_at;
+                    circuit.updated_at = self.clock.now();
+                    false
+                }
+                None => {
+                    circuit.created_at = self.clock.now();
+                    circuit.updated_at = circuit.created_at;
+                    true
+                }
+            };
+
+            for node in nodes.into_iter() {
+                state.insert_node_if_absent(node);
+            }
+
+            state.insert_circuit(circuit);
+
+            debug_assert_service_directory(state);
+
+            inserted
+        };
+
+        self.write_circuit_state(&mut guard).map_err(|err| {
+            AdminServiceStoreError::StorageError {
                 context: "Unable to write circuit state yaml file".to_string(),
                 source: Some(Box::new(err)),
-            })
+            }
+        })?;
+
+        Ok(inserted)
     }
 
-    /// Fetches a circuit from the underlying storage
+    /// Applies the node conflict policy to nodes being added with a circuit, returning an error
+    /// if a node exists with different endpoints and the policy is `NodeConflictPolicy::Error`
+    fn check_node_conflicts(
+        &self,
+        state: &YamlState,
+        circuit_id: &str,
+        nodes: &[CircuitNode],
+    ) -> Result<(), AdminServiceStoreError> {
+        for node in nodes.iter() {
+            match state.circuit_state.nodes.get(&node.id) {
+                Some(existing) if existing.endpoints != node.endpoints => {
+                    match self.node_conflict_policy {
+                        NodeConflictPolicy::Warn => warn!(
+                            "Node {} in circuit {} has endpoints {:?}, keeping the existing \
+                             endpoints {:?}",
+                            node.id, circuit_id, node.endpoints, existing.endpoints
+                        ),
+                        NodeConflictPolicy::Error => {
+                            return Err(AdminServiceStoreError::OperationError {
+                                context: format!(
+                                    "Node {} already exists with different endpoints",
+                                    node.id
+                                ),
+                                source: None,
+                            })
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error naming the first member of a circuit being added that has no node
+    /// definition in the given nodes or the store, if the store requires known members
+    fn check_known_members(
+        &self,
+        state: &YamlState,
+        circuit: &Circuit,
+        nodes: &[CircuitNode],
+    ) -> Result<(), AdminServiceStoreError> {
+        if !self.require_known_members {
+            return Ok(());
+        }
+
+        let unknown_member = circuit.members.iter().find(|member| {
+            !state.circuit_state.nodes.contains_key(*member)
+                && !nodes.iter().any(|node| &node.id == *member)
+        });
+
+        match unknown_member {
+            Some(member) => Err(AdminServiceStoreError::OperationError {
+                context: format!(
+                    "Member {} of circuit {} is not a known node",
+                    member, circuit.id
+                ),
+                source: None,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies the membership policy to a circuit being written, returning an error if a service
+    /// is allowed on a node that is not a member of the circuit and the policy is
+    /// `MembershipPolicy::Error`
+    fn check_allowed_nodes(&self, circuit: &Circuit) -> Result<(), AdminServiceStoreError> {
+        for warning in validate::allowed_nodes_not_members(circuit) {
+            match self.membership_policy {
+                MembershipPolicy::Warn => warn!("{}", warning),
+                MembershipPolicy::Error => {
+                    return Err(AdminServiceStoreError::OperationError {
+                        context: warning.to_string(),
+                        source: None,
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the store's circuit validators to a circuit being written, returning an error
+    /// with the reason given by the first validator that rejects it
+    fn check_circuit_validators(&self, circuit: &Circuit) -> Result<(), AdminServiceStoreError> {
+        for validator in self.circuit_validators.iter() {
+            validator(circuit).map_err(|reason| AdminServiceStoreError::OperationError {
+                context: format!("Circuit {} was rejected: {}", circuit.id, reason),
+                source: None,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a proposal's circuit hash matches its proposed circuit
+    ///
+    /// The hash is recomputed the same way the admin service computes it when the proposal is
+    /// created: the SHA-256 digest, in hex, of the proposed circuit serialized as a protobuf
+    /// `Circuit` message.
     ///
     /// # Arguments
     ///
-    ///  * `circuit_id` - The unique ID of the circuit to be returned
-    fn fetch_circuit(&self, circuit_id: &str) -> Result<Option<Circuit>, AdminServiceStoreError> {
-        Ok(self
-            .state
-            .lock()
-            .map_err(|_| AdminServiceStoreError::StorageError {
-                context: "YAML admin service store's internal lock was poisoned".to_string(),
+    ///  * `proposal_id` - The ID of the proposal to verify
+    ///
+    /// Returns whether the hash matches. Returns `AdminServiceStoreError::NotFoundError` if a
+    /// proposal with `proposal_id` does not exist, and an error if the proposed circuit uses
+    /// challenge authorization, which a circuit message cannot represent, or its hash cannot
+    /// otherwise be computed.
+    pub fn verify_proposal_hash(&self, proposal_id: &str) -> Result<bool, AdminServiceStoreError> {
+        let proposal = self.get_proposal(proposal_id)?;
+
+        if proposal.circuit.authorization_type == AuthorizationType::Challenge {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!(
+                    "Unable to verify the circuit hash of proposal {}: challenge authorization \
+                     cannot be represented in a circuit message",
+                    proposal_id
+                ),
                 source: None,
-            })?
-            .circuit_state
-            .circuits
+            });
+        }
+
+        let circuit_hash = proposed_circuit_hash(&proposal.circuit).map_err(|err| {
+            AdminServiceStoreError::OperationError {
+                context: format!(
+                    "Unable to compute the circuit hash of proposal {}",
+                    proposal_id
+                ),
+                source: Some(Box::new(err)),
+            }
+        })?;
+
+        Ok(circuit_hash == proposal.circuit_hash)
+    }
+
+    /// Fetches a proposal that was archived when it was upgraded to a circuit
+    ///
+    /// Upgraded proposals are only archived if the store was built with
+    /// `YamlAdminServiceStoreBuilder::with_archived_proposals`; otherwise this always returns
+    /// `None`.
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_id` - The ID of the circuit that was created from the proposal
+    pub fn fetch_archived_proposal(
+        &self,
+        circuit_id: &str,
+    ) -> Result<Option<CircuitProposal>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .archived_proposal_state
+            .proposals
             .get(circuit_id)
             .cloned())
     }
 
-    /// List all circuits from the underlying storage
+    /// Removes the nodes that are not a member of any circuit, such as the members of circuits
+    /// that have been removed. The circuit state file is only written if a node was removed.
     ///
-    /// The proposals returned can be filtered by provided CircuitPredicate. This enables
-    /// filtering by management type and members.
-    fn list_circuits(
-        &self,
-        predicates: &[CircuitPredicate],
-    ) -> Result<Box<dyn ExactSizeIterator<Item = Circuit>>, AdminServiceStoreError> {
-        let mut circuits: Vec<Circuit> = self
-            .state
-            .lock()
-            .map_err(|_| AdminServiceStoreError::StorageError {
-                context: "YAML admin service store's internal lock was poisoned".to_string(),
-                source: None,
-            })?
-            .circuit_state
-            .circuits
-            .iter()
-            .map(|(_, circuit)| circuit.clone())
-            .collect();
+    /// Returns the IDs of the removed nodes, in node ID order
+    pub fn prune_orphan_nodes(&self) -> Result<Vec<String>, AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        let pruned = {
+            if guard.orphan_node_ids().is_empty() {
+                return Ok(vec![]);
+            }
 
-        circuits.retain(|circuit| {
-            predicates
-                .iter()
-                .all(|predicate| predicate.apply_to_circuit(circuit))
-        });
+            Arc::make_mut(&mut guard).remove_orphan_nodes()
+        };
+        guard.record_nodes(&pruned);
 
-        Ok(Box::new(circuits.into_iter()))
+        self.write_circuit_state(&mut guard).map_err(|err| {
+            AdminServiceStoreError::StorageError {
+                context: "Unable to write circuit state yaml file".to_string(),
+                source: Some(Box::new(err)),
+            }
+        })?;
+
+        Ok(pruned)
     }
 
-    /// Adds a circuit to the underlying storage based on the proposal that is already in state..
-    /// Also includes the associated Services and Nodes. The associated circuit proposal for
-    /// the circuit ID is also removed
+    /// Removes a circuit, as with `remove_circuit`, unless it does not exist. The state files are
+    /// only written if the circuit was removed, so a removal can be safely retried.
     ///
     /// # Arguments
     ///
-    ///  * `circuit_id` - The ID of the circuit proposal that should be converted to a circuit
-    fn upgrade_proposal_to_circuit(&self, circuit_id: &str) -> Result<(), AdminServiceStoreError> {
-        {
-            let mut state =
-                self.state
-                    .lock()
-                    .map_err(|_| AdminServiceStoreError::StorageError {
-                        context: "YAML admin service store's internal lock was poisoned"
-                            .to_string(),
-                        source: None,
-                    })?;
-
-            if let Some(proposal) = state.proposal_state.proposals.remove(circuit_id) {
-                let nodes = proposal.circuit.members.to_vec();
-                let services = proposal.circuit.roster.to_vec();
-
-                let circuit = Circuit::from(proposal.circuit);
-                state
-                    .circuit_state
-                    .circuits
-                    .insert(circuit.id.to_string(), circuit);
+    ///  * `circuit_id` - The ID of the circuit to remove
+    ///
+    /// Returns `true` if the circuit was removed, or `false` if it did not exist
+    pub fn remove_circuit_if_present(
+        &self,
+        circuit_id: &str,
+    ) -> Result<bool, AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        let proposal_removed = {
+            let members = match guard.circuit_state.circuits.get(circuit_id) {
+                Some(circuit) => circuit.members.clone(),
+                None => return Ok(false),
+            };
+
+            guard.record(StateChange::Circuit(circuit_id.to_string()));
+            if self.prune_nodes_on_remove {
+                // the nodes that may be pruned are the existing orphans and the circuit's members
+                let orphan_node_ids = guard.orphan_node_ids();
+                guard.record_nodes(orphan_node_ids.iter().chain(members.iter()));
+            }
+            if self.cascade_proposals == CascadeProposals::Remove {
+                guard.record(StateChange::Proposal(circuit_id.to_string()));
+            }
 
-                for service in services.into_iter() {
+            let state = Arc::make_mut(&mut guard);
+            if let Some(circuit) = state.circuit_state.circuits.remove(circuit_id) {
+                for service in circuit.roster.iter() {
                     let service_id =
                         ServiceId::new(service.service_id.to_string(), circuit_id.to_string());
+                    state.service_directory.remove(&service_id);
+                }
+            }
+
+            debug_assert_service_directory(state);
 
-                    state
-                        .service_directory
-                        .insert(service_id, Service::from(service));
+            if self.prune_nodes_on_remove {
+                for node_id in state.remove_orphan_nodes() {
+                    debug!("Removed node {} with circuit {}", node_id, circuit_id);
                 }
+            }
 
-                for node in nodes.into_iter() {
-                    if !state.circuit_state.nodes.contains_key(&node.node_id) {
-                        state
-                            .circuit_state
-                            .nodes
-                            .insert(node.node_id.to_string(), CircuitNode::from(node));
+            if state.proposal_state.proposals.contains_key(circuit_id) {
+                match self.cascade_proposals {
+                    CascadeProposals::Warn => {
+                        warn!(
+                            "Removed circuit {} while a proposal for it still exists",
+                            circuit_id
+                        );
+                        false
+                    }
+                    CascadeProposals::Remove => {
+                        state.proposal_state.remove_proposal(circuit_id);
+                        true
                     }
                 }
             } else {
-                return Err(AdminServiceStoreError::OperationError {
-                    context: format!("A circuit with ID {} does not exist", circuit_id),
-                    source: None,
-                });
+                false
             }
+        };
+
+        if proposal_removed {
+            self.write_state(&mut guard)
+                .map_err(|err| AdminServiceStoreError::StorageError {
+                    context: "Unable to write yaml state files".to_string(),
+                    source: Some(Box::new(err)),
+                })?;
+        } else {
+            self.write_circuit_state(&mut guard).map_err(|err| {
+                AdminServiceStoreError::StorageError {
+                    context: "Unable to write circuit state yaml file".to_string(),
+                    source: Some(Box::new(err)),
+                }
+            })?;
         }
 
-        self.write_state()
-            .map_err(|err| AdminServiceStoreError::StorageError {
-                context: "Unable to write circiut state yaml files".to_string(),
-                source: Some(Box::new(err)),
-            })
+        Ok(true)
     }
 
-    /// Fetches a node from the underlying storage
+    /// Removes a proposal, as with `remove_proposal`, unless it does not exist. The proposal
+    /// state file is only written if the proposal was removed, so a removal can be safely
+    /// retried.
     ///
     /// # Arguments
     ///
-    ///  * `node_id` - The unique ID of the node to be returned
-    fn fetch_node(&self, node_id: &str) -> Result<Option<CircuitNode>, AdminServiceStoreError> {
-        Ok(self
-            .state
-            .lock()
-            .map_err(|_| AdminServiceStoreError::StorageError {
-                context: "YAML admin service store's internal lock was poisoned".to_string(),
-                source: None,
-            })?
-            .circuit_state
-            .nodes
-            .get(node_id)
-            .cloned())
+    ///  * `proposal_id` - The ID of the proposal to remove
+    ///
+    /// Returns `true` if the proposal was removed, or `false` if it did not exist
+    pub fn remove_proposal_if_present(
+        &self,
+        proposal_id: &str,
+    ) -> Result<bool, AdminServiceStoreError> {
+        Ok(self.take_proposal_if_present(proposal_id)?.is_some())
     }
 
-    /// List all nodes from the underlying storage
-    fn list_nodes(
+    /// Removes a proposal and writes the proposal state file, if the proposal exists
+    ///
+    /// Returns the removed proposal, or `None` if it did not exist
+    fn take_proposal_if_present(
         &self,
-    ) -> Result<Box<dyn ExactSizeIterator<Item = CircuitNode>>, AdminServiceStoreError> {
-        let nodes: Vec<CircuitNode> = self
-            .state
-            .lock()
-            .map_err(|_| AdminServiceStoreError::StorageError {
-                context: "YAML admin service store's internal lock was poisoned".to_string(),
-                source: None,
-            })?
-            .circuit_state
-            .nodes
-            .iter()
-            .map(|(_, node)| node.clone())
-            .collect();
+        proposal_id: &str,
+    ) -> Result<Option<CircuitProposal>, AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        let proposal = {
+            if !guard.proposal_state.proposals.contains_key(proposal_id) {
+                return Ok(None);
+            }
+
+            guard.record(StateChange::Proposal(proposal_id.to_string()));
+            Arc::make_mut(&mut guard)
+                .proposal_state
+                .remove_proposal(proposal_id)
+        };
+
+        self.write_proposal_state(&mut guard).map_err(|err| {
+            AdminServiceStoreError::StorageError {
+                context: "Unable to write proposal state yaml file".to_string(),
+                source: Some(Box::new(err)),
+            }
+        })?;
 
-        Ok(Box::new(nodes.into_iter()))
+        Ok(proposal)
     }
 
-    /// Fetches a service from the underlying storage
+    /// Converts a proposal to the circuit it proposes, as with `upgrade_proposal_to_circuit`,
+    /// returning the consumed proposal along with the created circuit
     ///
     /// # Arguments
     ///
-    ///  * `service_id` - The `ServiceId` of a service made up of the circuit ID and service ID
-    fn fetch_service(
+    ///  * `proposal_id` - The ID of the proposal to convert to a circuit
+    ///
+    /// Returns `AdminServiceStoreError::NotFoundError` if a proposal with `proposal_id` does not
+    /// exist, and an error if its IDs do not match the key it is stored under or if a circuit
+    /// with the proposed circuit's ID already exists
+    pub fn finalize_proposal(
         &self,
-        service_id: &ServiceId,
-    ) -> Result<Option<Service>, AdminServiceStoreError> {
-        Ok(self
-            .state
-            .lock()
-            .map_err(|_| AdminServiceStoreError::StorageError {
-                context: "YAML admin service store's internal lock was poisoned".to_string(),
-                source: None,
-            })?
-            .service_directory
-            .get(service_id)
-            .cloned())
+        proposal_id: &str,
+    ) -> Result<(CircuitProposal, Circuit), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        let (proposal, circuit) = {
+            let proposal = guard
+                .proposal_state
+                .proposals
+                .get(proposal_id)
+                .cloned()
+                .ok_or_else(|| {
+                    AdminServiceStoreError::NotFoundError(format!(
+                        "Cannot find circuit proposal with id: {}",
+                        proposal_id
+                    ))
+                })?;
+
+            if let Some(warning) = validate::audit_proposal(proposal_id, &proposal)
+                .into_iter()
+                .find(|warning| {
+                    matches!(
+                        warning,
+                        ProposalWarning::MismatchedProposalId { .. }
+                            | ProposalWarning::MismatchedProposedCircuitId { .. }
+                    )
+                })
+            {
+                return Err(AdminServiceStoreError::OperationError {
+                    context: warning.to_string(),
+                    source: None,
+                });
+            }
+
+            if guard
+                .circuit_state
+                .circuits
+                .contains_key(&proposal.circuit.circuit_id)
+            {
+                return Err(AdminServiceStoreError::OperationError {
+                    context: format!(
+                        "A circuit with ID {} already exists",
+                        proposal.circuit.circuit_id
+                    ),
+                    source: None,
+                });
+            }
+
+            let circuit_id = proposal.circuit.circuit_id.to_string();
+            let member_ids = proposal
+                .circuit
+                .members
+                .iter()
+                .map(|node| node.node_id.to_string())
+                .collect::<Vec<_>>();
+            guard.record(StateChange::Proposal(proposal_id.to_string()));
+            guard.record(StateChange::ArchivedProposal(proposal_id.to_string()));
+            guard.record(StateChange::Circuit(circuit_id));
+            guard.record_nodes(&member_ids);
+
+            let state = Arc::make_mut(&mut guard);
+
+            state.proposal_state.remove_proposal(proposal_id);
+
+            if self.paths().archived_proposal_file_path.is_some() {
+                state
+                    .archived_proposal_state
+                    .insert_proposal(proposal.clone());
+            }
+
+            let mut circuit = Circuit::from(proposal.circuit.clone());
+            circuit.created_at = self.clock.now();
+            circuit.updated_at = circuit.created_at;
+            state.insert_circuit(circuit.clone());
+
+            for node in proposal.circuit.members.iter() {
+                state.insert_node_if_absent(CircuitNode::from(node.clone()));
+            }
+
+            debug_assert_service_directory(state);
+
+            (proposal, circuit)
+        };
+
+        self.write_state(&mut guard)
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to write circuit state yaml files".to_string(),
+                source: Some(Box::new(err)),
+            })?;
+
+        Ok((proposal, circuit))
     }
 
-    /// List all services in a specific circuit from the underlying storage
+    /// Fetches a node, as with `fetch_node`, for callers that require the node to exist
     ///
     /// # Arguments
     ///
-    ///  * `circuit_id` - The unique ID of the circuit the services belong to
-    fn list_services(
-        &self,
-        circuit_id: &str,
-    ) -> Result<Box<dyn ExactSizeIterator<Item = Service>>, AdminServiceStoreError> {
-        let services: Vec<Service> = self
-            .state
-            .lock()
-            .map_err(|_| AdminServiceStoreError::StorageError {
-                context: "YAML admin service store's internal lock was poisoned".to_string(),
-                source: None,
-            })?
-            .circuit_state
-            .circuits
-            .get(circuit_id)
-            .ok_or(AdminServiceStoreError::OperationError {
-                context: format!("Circuit {} does not exist", circuit_id),
-                source: None,
-            })?
-            .roster
-            .clone();
+    ///  * `node_id` - The ID of the node to fetch
+    ///
+    /// Returns `AdminServiceStoreError::NotFoundError` if a node with `node_id` does not exist
+    pub fn get_node(&self, node_id: &str) -> Result<CircuitNode, AdminServiceStoreError> {
+        self.fetch_node(node_id)?.ok_or_else(|| {
+            AdminServiceStoreError::NotFoundError(format!("Cannot find node with id: {}", node_id))
+        })
+    }
 
-        Ok(Box::new(services.into_iter()))
+    /// Fetches a circuit, as with `fetch_circuit`, for callers that require the circuit to exist
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_id` - The ID of the circuit to fetch
+    ///
+    /// Returns `AdminServiceStoreError::NotFoundError` if a circuit with `circuit_id` does not
+    /// exist
+    pub fn get_circuit(&self, circuit_id: &str) -> Result<Circuit, AdminServiceStoreError> {
+        self.fetch_circuit(circuit_id)?.ok_or_else(|| {
+            AdminServiceStoreError::NotFoundError(format!(
+                "Cannot find circuit with id: {}",
+                circuit_id
+            ))
+        })
     }
-}
 
-/// YAML file specific circuit definition. This circuit definition in the 0.4v YAML stores service
-/// arguments in a map format, which differs from the definition defined in the AdminServiceStore.
-/// To handle this, circuit needs to be converted to the correct format during read/write
-/// operations.
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
-struct YamlCircuit {
-    id: String,
-    roster: Vec<YamlService>,
-    members: Vec<String>,
-    auth: AuthorizationType,
-    persistence: PersistenceType,
-    durability: DurabilityType,
-    routes: RouteType,
-    circuit_management_type: String,
-}
+    /// Fetches a proposal, as with `fetch_proposal`, for callers that require the proposal to
+    /// exist
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_id` - The ID of the proposal to fetch
+    ///
+    /// Returns `AdminServiceStoreError::NotFoundError` if a proposal with `proposal_id` does not
+    /// exist
+    pub fn get_proposal(
+        &self,
+        proposal_id: &str,
+    ) -> Result<CircuitProposal, AdminServiceStoreError> {
+        self.fetch_proposal(proposal_id)?.ok_or_else(|| {
+            AdminServiceStoreError::NotFoundError(format!(
+                "Cannot find circuit proposal with id: {}",
+                proposal_id
+            ))
+        })
+    }
 
-impl From<YamlCircuit> for Circuit {
-    fn from(circuit: YamlCircuit) -> Self {
-        Circuit {
-            id: circuit.id,
-            roster: circuit.roster.into_iter().map(Service::from).collect(),
-            members: circuit.members,
-            auth: circuit.auth,
-            persistence: circuit.persistence,
-            durability: circuit.durability,
-            routes: circuit.routes,
-            circuit_management_type: circuit.circuit_management_type,
+    /// List circuits in the given order
+    ///
+    /// The circuits returned can be filtered by provided `CircuitPredicate`. This enables
+    /// filtering by management type and members.
+    ///
+    /// # Arguments
+    ///
+    ///  * `predicates` - The predicates the circuits must match
+    ///  * `order` - The order the circuits are returned in
+    pub fn list_circuits_sorted(
+        &self,
+        predicates: &[CircuitPredicate],
+        order: CircuitSortOrder,
+    ) -> Result<Box<dyn ExactSizeIterator<Item = Circuit>>, AdminServiceStoreError> {
+        let mut circuits: Vec<Circuit> = self.list_circuits(predicates)?.collect();
+
+        if order == CircuitSortOrder::ByCreatedAt {
+            // circuits are listed in ID order, and the sort is stable
+            circuits.sort_by_key(|circuit| circuit.created_at);
         }
+
+        Ok(Box::new(circuits.into_iter()))
     }
-}
 
-impl From<Circuit> for YamlCircuit {
-    fn from(circuit: Circuit) -> Self {
-        YamlCircuit {
-            id: circuit.id,
-            roster: circuit.roster.into_iter().map(YamlService::from).collect(),
-            members: circuit.members,
-            auth: circuit.auth,
-            persistence: circuit.persistence,
-            durability: circuit.durability,
-            routes: circuit.routes,
-            circuit_management_type: circuit.circuit_management_type,
-        }
+    /// List circuits in ID order as a `CircuitList`, which can be indexed as well as iterated
+    ///
+    /// The circuits returned can be filtered by provided `CircuitPredicate`, as with
+    /// `list_circuits`.
+    pub fn list_circuits_indexed(
+        &self,
+        predicates: &[CircuitPredicate],
+    ) -> Result<CircuitList, AdminServiceStoreError> {
+        Ok(CircuitList::new(self.list_circuits(predicates)?.collect()))
     }
-}
 
-/// YAML file specific service definition. This service definition in the 0.4v YAML stores
-/// arguments in a map format, which differs from the definition defined in the AdminServiceStore.
-/// To handle this, service needs to be converted to the correct format during read/write
-/// operations.
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
-struct YamlService {
-    service_id: String,
-    service_type: String,
-    allowed_nodes: Vec<String>,
-    arguments: BTreeMap<String, String>,
-}
+    /// List circuits that match all of the given predicates
+    ///
+    /// This is the same as `list_circuits`, but accepts any iterator of predicates, such as
+    /// `Some(&predicate)` for a single predicate, instead of requiring a slice.
+    pub fn list_circuits_matching<'a, I>(
+        &self,
+        predicates: I,
+    ) -> Result<Box<dyn ExactSizeIterator<Item = Circuit>>, AdminServiceStoreError>
+    where
+        I: IntoIterator<Item = &'a CircuitPredicate>,
+    {
+        let predicates: Vec<CircuitPredicate> = predicates.into_iter().cloned().collect();
+        self.list_circuits(&predicates)
+    }
 
-impl From<YamlService> for Service {
-    fn from(service: YamlService) -> Self {
-        Service {
-            service_id: service.service_id,
-            service_type: service.service_type,
-            allowed_nodes: service.allowed_nodes,
-            arguments: service
-                .arguments
-                .into_iter()
-                .map(|(key, value)| (key, value))
-                .collect(),
-        }
+    /// List circuit proposals that match all of the given predicates
+    ///
+    /// This is the same as `list_proposals`, but accepts any iterator of predicates, such as
+    /// `Some(&predicate)` for a single predicate, instead of requiring a slice.
+    pub fn list_proposals_matching<'a, I>(
+        &self,
+        predicates: I,
+    ) -> Result<Box<dyn ExactSizeIterator<Item = CircuitProposal>>, AdminServiceStoreError>
+    where
+        I: IntoIterator<Item = &'a CircuitPredicate>,
+    {
+        let predicates: Vec<CircuitPredicate> = predicates.into_iter().cloned().collect();
+        self.list_proposals(&predicates)
     }
-}
 
-impl From<Service> for YamlService {
-    fn from(service: Service) -> Self {
-        YamlService {
-            service_id: service.service_id,
-            service_type: service.service_type,
-            allowed_nodes: service.allowed_nodes,
-            arguments: service
-                .arguments
-                .into_iter()
-                .map(|(key, value)| (key, value))
-                .collect(),
-        }
+    /// List the circuits that were added or updated after the given time, in ID order
+    ///
+    /// # Arguments
+    ///
+    ///  * `since` - Only circuits with an update time after this time are returned
+    pub fn list_circuits_modified_since(
+        &self,
+        since: SystemTime,
+    ) -> Result<Vec<Circuit>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .circuit_state
+            .circuits
+            .values()
+            .filter(|circuit| circuit.updated_at > since)
+            .cloned()
+            .collect())
     }
-}
 
-/// YAML file specific state definition that can be read and written to the circuit YAML state file
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
-struct YamlCircuitState {
-    nodes: BTreeMap<String, CircuitNode>,
-    circuits: BTreeMap<String, YamlCircuit>,
-}
+    /// List all circuits in ID order, each paired with the proposal with the same ID, if any
+    pub fn list_circuits_with_pending_proposals(
+        &self,
+    ) -> Result<Vec<(Circuit, Option<CircuitProposal>)>, AdminServiceStoreError> {
+        let state = self.shared_state()?;
 
-impl From<YamlCircuitState> for CircuitState {
-    fn from(state: YamlCircuitState) -> Self {
-        CircuitState {
-            nodes: state.nodes,
-            circuits: state
-                .circuits
-                .into_iter()
-                .map(|(id, circuit)| (id, Circuit::from(circuit)))
-                .collect(),
-        }
+        Ok(state
+            .circuit_state
+            .circuits
+            .iter()
+            .map(|(circuit_id, circuit)| {
+                (
+                    circuit.clone(),
+                    state.proposal_state.proposals.get(circuit_id).cloned(),
+                )
+            })
+            .collect())
     }
-}
 
-impl From<CircuitState> for YamlCircuitState {
-    fn from(state: CircuitState) -> Self {
-        YamlCircuitState {
-            nodes: state.nodes,
-            circuits: state
-                .circuits
-                .into_iter()
-                .map(|(id, circuit)| (id, YamlCircuit::from(circuit)))
-                .collect(),
+    /// List the proposals, in ID order, that have been accepted by all of their members and can
+    /// be upgraded to a circuit
+    ///
+    /// A proposal is upgradeable if every member node, including the requester's node, has
+    /// voted to accept it and no node has voted to reject it.
+    pub fn list_upgradeable_proposals(
+        &self,
+    ) -> Result<Vec<CircuitProposal>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .proposal_state
+            .proposals
+            .values()
+            .filter(|proposal| VoteProgress::new(proposal).is_upgradeable())
+            .cloned()
+            .collect())
+    }
+
+    /// List the proposals in the order they were submitted to the store
+    ///
+    /// Proposals read from files written before submission order was recorded are listed first,
+    /// in ID order.
+    pub fn list_proposals_by_submission_order(
+        &self,
+    ) -> Result<Vec<CircuitProposal>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .proposal_state
+            .proposals_by_submission_order()
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// Fetches a proposal together with the progress of the vote on it
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_id` - The unique ID of the circuit proposal to be returned
+    pub fn fetch_proposal_with_progress(
+        &self,
+        proposal_id: &str,
+    ) -> Result<Option<ProposalWithProgress>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .proposal_state
+            .proposals
+            .get(proposal_id)
+            .cloned()
+            .map(ProposalWithProgress::new))
+    }
+
+    /// Counts the votes cast on all of the proposals, in total and for each circuit management
+    /// type
+    pub fn vote_tally(&self) -> Result<VoteTally, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        let mut tally = VoteTally::default();
+        for proposal in state.proposal_state.proposals.values() {
+            for vote_record in proposal.votes.iter() {
+                tally.add(&proposal.circuit.circuit_management_type, &vote_record.vote);
+            }
         }
+
+        Ok(tally)
     }
-}
 
-/// The circuit state that is cached by the YAML admin service store and used to respond to fetch
-/// requests
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
-struct CircuitState {
-    nodes: BTreeMap<String, CircuitNode>,
-    circuits: BTreeMap<String, Circuit>,
-}
+    /// Counts the proposals for each circuit management type. Management types without any
+    /// proposals are not included.
+    pub fn proposal_counts_by_management_type(
+        &self,
+    ) -> Result<BTreeMap<String, usize>, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        let mut counts = BTreeMap::new();
+        for proposal in state.proposal_state.proposals.values() {
+            *counts
+                .entry(proposal.circuit.circuit_management_type.to_string())
+                .or_insert(0) += 1;
+        }
 
-/// The proposal state that is cached by the YAML admin service store and used to respond to fetch
-/// requests
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
-struct ProposalState {
-    proposals: BTreeMap<String, CircuitProposal>,
-}
+        Ok(counts)
+    }
 
-/// The combination of circuit and circuit proposal state
-#[derive(Debug, Clone, Default)]
-struct YamlState {
-    circuit_state: CircuitState,
-    proposal_state: ProposalState,
-    service_directory: BTreeMap<ServiceId, Service>,
-}
+    /// Searches the IDs, management types and member node IDs of circuits, and the IDs and types
+    /// of their services, for a query, ignoring case
+    ///
+    /// # Arguments
+    ///
+    ///  * `query` - The text to search for
+    ///
+    /// Returns a hit for each field that contains the query, in circuit ID order
+    pub fn search(&self, query: &str) -> Result<Vec<SearchHit>, AdminServiceStoreError> {
+        Ok(search::search_circuits(
+            &self.shared_state()?.circuit_state,
+            query,
+        ))
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::io::Read;
+    /// List a page of the nodes, in node ID order, together with the total number of nodes
+    ///
+    /// # Arguments
+    ///
+    ///  * `offset` - The number of nodes to skip
+    ///  * `limit` - The maximum number of nodes to return
+    pub fn list_nodes_paged(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<CircuitNode>, usize), AdminServiceStoreError> {
+        let state = self.shared_state()?;
+        let nodes = &state.circuit_state.nodes;
+
+        Ok((
+            nodes.values().skip(offset).take(limit).cloned().collect(),
+            nodes.len(),
+        ))
+    }
 
-    use tempdir::TempDir;
+    /// Checks every proposal in the store for problems, reporting them rather than rejecting the
+    /// proposals
+    ///
+    /// A proposal is reported if it is stored under a different ID than its own, if it proposes a
+    /// circuit with a different ID, if a node voted on it more than once or without being a
+    /// member of the proposed circuit, or if its requester is not a compressed public key.
+    ///
+    /// Returns the problems found, in proposal ID order
+    pub fn audit_proposals(&self) -> Result<Vec<ProposalWarning>, AdminServiceStoreError> {
+        let state = self.shared_state()?;
 
-    use super::*;
+        Ok(state
+            .proposal_state
+            .proposals
+            .iter()
+            .flat_map(|(key, proposal)| validate::audit_proposal(key, proposal))
+            .collect())
+    }
 
-    use crate::admin::store::builders::{
-        CircuitBuilder, CircuitNodeBuilder, CircuitProposalBuilder, ProposedCircuitBuilder,
-        ProposedNodeBuilder, ProposedServiceBuilder, ServiceBuilder,
-    };
-    use crate::admin::store::{ProposalType, Vote, VoteRecord};
-    use crate::hex::parse_hex;
+    /// Returns whether the store has no circuits, proposals or nodes. Archived proposals are not
+    /// considered.
+    pub fn is_empty(&self) -> Result<bool, AdminServiceStoreError> {
+        let state = self.shared_state()?;
 
-    const CIRCUIT_STATE: &[u8] = b"---
-nodes:
-    acme-node-000:
-        id: acme-node-000
-        endpoints:
-          - \"tcps://splinterd-node-acme:8044\"
-    bubba-node-000:
-        id: bubba-node-000
-        endpoints:
-          - \"tcps://splinterd-node-bubba:8044\"
-circuits:
-    WBKLF-AAAAA:
-        id: WBKLF-AAAAA
-        auth: Trust
-        members:
-          - bubba-node-000
-          - acme-node-000
-        roster:
-          - service_id: a000
-            service_type: scabbard
-            allowed_nodes:
-              - acme-node-000
-            arguments:
-              admin_keys: '[\"035724d11cae47c8907f8bfdf510488f49df8494ff81b63825bad923733c4ac550\"]'
-              peer_services: '[\"a001\"]'
-          - service_id: a001
-            service_type: scabbard
-            allowed_nodes:
-              - bubba-node-000
-            arguments:
-              admin_keys: '[\"035724d11cae47c8907f8bfdf510488f49df8494ff81b63825bad923733c4ac550\"]'
-              peer_services: '[\"a000\"]'
-        persistence: Any
-        durability: NoDurability
-        routes: Any
-        circuit_management_type: gameroom";
+        Ok(state.circuit_state.circuits.is_empty()
+            && state.proposal_state.proposals.is_empty()
+            && state.circuit_state.nodes.is_empty())
+    }
 
-    const PROPOSAL_STATE: &[u8] = b"---
-proposals:
-    WBKLF-BBBBB:
-        proposal_type: Create
-        circuit_id: WBKLF-BBBBB
-        circuit_hash: 7ddc426972710adc0b2ecd49e89a9dd805fb9206bf516079724c887bedbcdf1d
-        circuit:
-            circuit_id: WBKLF-BBBBB
+    /// Returns whether a node is a member of any circuit
+    ///
+    /// # Arguments
+    ///
+    ///  * `node_id` - The ID of the node
+    pub fn node_in_use(&self, node_id: &str) -> Result<bool, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .circuit_state
+            .circuits
+            .values()
+            .any(|circuit| circuit.members.iter().any(|member| member == node_id)))
+    }
+
+    /// List the circuits, in circuit ID order, whose roster includes a service with the given ID
+    ///
+    /// # Arguments
+    ///
+    ///  * `service_id` - The ID of the service, without its circuit ID
+    pub fn list_circuits_with_service(
+        &self,
+        service_id: &str,
+    ) -> Result<Vec<Circuit>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .circuit_state
+            .circuits
+            .values()
+            .filter(|circuit| {
+                circuit
+                    .roster
+                    .iter()
+                    .any(|service| service.service_id == service_id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// List the member node IDs of each circuit, in circuit ID order, that do not have an entry
+    /// in the store's nodes
+    ///
+    /// Circuits whose members are all known nodes are not included.
+    pub fn missing_member_nodes(
+        &self,
+    ) -> Result<Vec<(String, Vec<String>)>, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        Ok(state
+            .circuit_state
+            .circuits
+            .values()
+            .filter_map(|circuit| {
+                let missing = circuit
+                    .members
+                    .iter()
+                    .filter(|node_id| !state.circuit_state.nodes.contains_key(*node_id))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                if missing.is_empty() {
+                    None
+                } else {
+                    Some((circuit.id.to_string(), missing))
+                }
+            })
+            .collect())
+    }
+
+    /// Calls a function with the store's cached circuit and proposal state and returns its
+    /// result. The function sees a single consistent state, even if the store is changed while it
+    /// runs.
+    ///
+    /// # Arguments
+    ///
+    ///  * `f` - The function that reads the state
+    pub fn with_state<R>(
+        &self,
+        f: impl FnOnce(&CircuitState, &ProposalState) -> R,
+    ) -> Result<R, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+        Ok(f(&state.circuit_state, &state.proposal_state))
+    }
+
+    /// Takes a point-in-time copy of the store's state. All queries made against the returned
+    /// snapshot observe the same state, regardless of later changes to the store.
+    pub fn snapshot(&self) -> Result<StoreSnapshot, AdminServiceStoreError> {
+        Ok(StoreSnapshot::new(self.shared_state()?))
+    }
+
+    /// Takes a point-in-time copy of the circuits that match the given predicates, together with
+    /// the nodes that are members of those circuits and the services in their rosters. The
+    /// snapshot does not include any proposals.
+    ///
+    /// # Arguments
+    ///
+    ///  * `predicates` - The predicates the exported circuits must match
+    pub fn export_circuits(
+        &self,
+        predicates: &[CircuitPredicate],
+    ) -> Result<StoreSnapshot, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        let circuits: BTreeMap<String, Circuit> = state
+            .circuit_state
+            .circuits
+            .iter()
+            .filter(|(_, circuit)| {
+                predicates
+                    .iter()
+                    .all(|predicate| predicate.apply_to_circuit(circuit))
+            })
+            .map(|(circuit_id, circuit)| (circuit_id.to_string(), circuit.clone()))
+            .collect();
+
+        let nodes = state
+            .circuit_state
+            .nodes
+            .iter()
+            .filter(|(node_id, _)| {
+                circuits
+                    .values()
+                    .any(|circuit| circuit.members.contains(node_id))
+            })
+            .map(|(node_id, node)| (node_id.to_string(), node.clone()))
+            .collect();
+
+        let service_directory = build_service_directory(&circuits);
+
+        Ok(StoreSnapshot::new(Arc::new(YamlState {
+            circuit_state: CircuitState { nodes, circuits },
+            service_directory,
+            ..YamlState::default()
+        })))
+    }
+
+    /// Returns a reference to the current state. The state is loaded without taking the store's
+    /// internal lock, so readers never wait for writers.
+    fn shared_state(&self) -> Result<Arc<YamlState>, AdminServiceStoreError> {
+        Ok(self.state.load_full())
+    }
+
+    /// Returns the paths of the state files. The paths are only replaced as a whole, so they
+    /// are still usable if the lock was poisoned.
+    fn paths(&self) -> StatePaths {
+        self.paths
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Acquires the store's internal write lock, reporting the time spent waiting for it to the
+    /// store's metrics, if any. Only writers take the lock; readers load the state with
+    /// `shared_state` without waiting.
+    ///
+    /// The state is shared with readers, list iterators and snapshots, so it must be modified
+    /// through `Arc::make_mut`, which clones the state first. The modified state is published to
+    /// readers by `StateGuard::publish`, which the `write_*` methods call once the change has been
+    /// written; a change that is not published is discarded when the guard is dropped.
+    fn lock_state(&self) -> Result<StateGuard<'_>, AdminServiceStoreError> {
+        let poisoned = |_| AdminServiceStoreError::StorageError {
+            context: "YAML admin service store's internal lock was poisoned".to_string(),
+            source: None,
+        };
+
+        let write_lock = match &self.metrics {
+            Some(metrics) => {
+                let start = Instant::now();
+                let write_lock = self.write_lock.lock().map_err(poisoned)?;
+                metrics.record_lock_wait(start.elapsed());
+                write_lock
+            }
+            None => self.write_lock.lock().map_err(poisoned)?,
+        };
+
+        let state = self.state.load_full();
+        Ok(StateGuard {
+            _write_lock: write_lock,
+            original: Arc::clone(&state),
+            state,
+            changes: vec![],
+            published: &self.state,
+        })
+    }
+
+    /// Renders the nodes, circuits and services in the store as a Graphviz DOT document
+    ///
+    /// Each circuit is drawn as a cluster containing the circuit and its services. The circuit is
+    /// joined to its member nodes, and each service is joined by a dashed edge to the nodes it is
+    /// allowed on.
+    pub fn to_dot(&self) -> Result<String, AdminServiceStoreError> {
+        Ok(dot::render_dot(&self.shared_state()?.circuit_state))
+    }
+
+    /// Returns the size, in bytes, of the circuit state file that would be written for the
+    /// current circuit state, without writing it. The state is serialized as it is written to the
+    /// file, in the store's file format, enum case and indentation.
+    pub fn circuit_state_size_bytes(&self) -> Result<usize, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        self.serialize_circuit_state(&state.circuit_state)
+            .map(|output| output.len())
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to serialize circuit state".to_string(),
+                source: Some(Box::new(err)),
+            })
+    }
+
+    /// Returns the size, in bytes, of the proposal state file that would be written for the
+    /// current proposal state, without writing it, as with `circuit_state_size_bytes`
+    pub fn proposal_state_size_bytes(&self) -> Result<usize, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        self.serialize_proposal_state(&state.proposal_state, "proposal state")
+            .map(|output| output.len())
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to serialize proposal state".to_string(),
+                source: Some(Box::new(err)),
+            })
+    }
+
+    /// Writes the current circuit and proposal state to a tar archive containing a
+    /// `circuits.yaml` and a `circuit_proposals.yaml` file. The state is serialized as it is
+    /// written to the state files, in the store's file format, enum case, timestamp format and
+    /// indentation, and each file is given the current time as its modification time.
+    ///
+    /// # Arguments
+    ///
+    ///  * `writer` - The writer the tar archive will be written to
+    #[cfg(feature = "admin-store-tar")]
+    pub fn export_tar(&self, writer: impl Write) -> Result<(), AdminServiceStoreError> {
+        let (circuit_output, proposal_output) = {
+            let state = self.shared_state()?;
+
+            let circuit_output =
+                self.serialize_circuit_state(&state.circuit_state)
+                    .map_err(|err| AdminServiceStoreError::StorageError {
+                        context: "Unable to serialize circuit state".to_string(),
+                        source: Some(Box::new(err)),
+                    })?;
+
+            let proposal_output = self
+                .serialize_proposal_state(&state.proposal_state, "proposal state")
+                .map_err(|err| AdminServiceStoreError::StorageError {
+                    context: "Unable to serialize proposal state".to_string(),
+                    source: Some(Box::new(err)),
+                })?;
+
+            (circuit_output, proposal_output)
+        };
+
+        let modified_time = self.clock.now();
+        let mut archive = tar::Builder::new(writer);
+        append_tar_entry(
+            &mut archive,
+            CIRCUITS_TAR_ENTRY,
+            &circuit_output,
+            modified_time,
+        )?;
+        append_tar_entry(
+            &mut archive,
+            PROPOSALS_TAR_ENTRY,
+            &proposal_output,
+            modified_time,
+        )?;
+        archive
+            .into_inner()
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Failed to finish writing tar archive".to_string(),
+                source: Some(Box::new(err)),
+            })?;
+
+        Ok(())
+    }
+
+    /// Writes the current nodes, circuits and proposals to a writer one record at a time, so the
+    /// state is not copied in memory while it is written. Changes made to the store while the
+    /// export is written are not included.
+    ///
+    /// # Arguments
+    ///
+    ///  * `writer` - The writer the state will be written to
+    pub fn export_to_writer(&self, writer: impl Write) -> Result<(), AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        stream::export_records(&state, writer)
+    }
+
+    /// Reads nodes, circuits and proposals written by `export_to_writer` one record at a time and
+    /// imports them into the store. Both state files are written once the state has been
+    /// imported.
+    ///
+    /// # Arguments
+    ///
+    ///  * `reader` - The reader the state will be read from
+    ///  * `overwrite` - If true, the existing state is replaced by the imported state. If false,
+    ///    the imported state is merged with the existing state.
+    ///
+    /// Returns an error if a record cannot be read, or if `overwrite` is false and an imported
+    /// circuit or proposal already exists in the store
+    pub fn import_from_reader(
+        &self,
+        reader: impl Read,
+        overwrite: bool,
+    ) -> Result<(), AdminServiceStoreError> {
+        let (circuit_state, proposal_state) = stream::import_records(reader)?;
+
+        self.import_state(circuit_state, proposal_state, overwrite)
+    }
+
+    /// Reads circuit and proposal state from a tar archive created by `export_tar` and imports
+    /// it into the store. Both state files are written once the state has been imported.
+    ///
+    /// The files are read as the state files are, in the store's file format. As when the
+    /// circuit state file is loaded, circuits without a creation time are given the time the
+    /// `circuits.yaml` file was last modified.
+    ///
+    /// # Arguments
+    ///
+    ///  * `reader` - The reader the tar archive will be read from
+    ///  * `overwrite` - If true, the existing state is replaced by the imported state. If false,
+    ///    the imported state is merged with the existing state.
+    ///
+    /// Returns an error if the archive does not contain both state files, or if `overwrite` is
+    /// false and an imported circuit or proposal already exists in the store
+    #[cfg(feature = "admin-store-tar")]
+    pub fn import_tar(
+        &self,
+        reader: impl Read,
+        overwrite: bool,
+    ) -> Result<(), AdminServiceStoreError> {
+        let mut circuit_state = None;
+        let mut proposal_state = None;
+
+        let mut archive = tar::Archive::new(reader);
+        let entries = archive
+            .entries()
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Failed to read tar archive".to_string(),
+                source: Some(Box::new(err)),
+            })?;
+
+        for entry in entries {
+            let entry = entry.map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Failed to read tar archive entry".to_string(),
+                source: Some(Box::new(err)),
+            })?;
+
+            let path = entry
+                .path()
+                .map_err(|err| AdminServiceStoreError::StorageError {
+                    context: "Failed to read tar archive entry path".to_string(),
+                    source: Some(Box::new(err)),
+                })?
+                .into_owned();
+
+            if path == Path::new(CIRCUITS_TAR_ENTRY) {
+                // Circuits without a creation time are given the time the file was last modified
+                let modified_time = entry
+                    .header()
+                    .mtime()
+                    .map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime))
+                    .unwrap_or(UNIX_EPOCH);
+
+                circuit_state = Some(self.parse_circuit_state(entry, modified_time).map_err(
+                    |err| AdminServiceStoreError::StorageError {
+                        context: format!("Failed to read {} from tar archive", CIRCUITS_TAR_ENTRY),
+                        source: Some(Box::new(err)),
+                    },
+                )?);
+            } else if path == Path::new(PROPOSALS_TAR_ENTRY) {
+                let (state, _) = self.read_proposal_state_from(entry).map_err(|err| {
+                    AdminServiceStoreError::StorageError {
+                        context: format!("Failed to read {} from tar archive", PROPOSALS_TAR_ENTRY),
+                        source: Some(Box::new(err)),
+                    }
+                })?;
+                proposal_state = Some(state);
+            }
+        }
+
+        let circuit_state = circuit_state.ok_or_else(|| AdminServiceStoreError::StorageError {
+            context: format!("Tar archive does not contain {}", CIRCUITS_TAR_ENTRY),
+            source: None,
+        })?;
+        let proposal_state =
+            proposal_state.ok_or_else(|| AdminServiceStoreError::StorageError {
+                context: format!("Tar archive does not contain {}", PROPOSALS_TAR_ENTRY),
+                source: None,
+            })?;
+
+        self.import_state(circuit_state, proposal_state, overwrite)
+    }
+
+    /// Read circuit state from the circuit file path and cache the contents in the store
+    fn read_circuit_state(&self) -> Result<(), YamlAdminStoreError> {
+        let yaml_state = self.parse_circuit_file()?;
+
+        let mut guard = self.lock_state().map_err(|_| {
+            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
+        })?;
+        let state = Arc::make_mut(&mut guard);
+
+        state.replace_circuit_state(yaml_state);
+        guard.publish();
+        Ok(())
+    }
+
+    /// Parse the circuit state file. An empty file is parsed as empty state. If the store has a
+    /// circuit cache that was made from the file's current contents, the cache is loaded instead.
+    /// The file is memory-mapped rather than read if the store was built with
+    /// `YamlAdminServiceStoreBuilder::with_mmap_circuit_file`.
+    fn parse_circuit_file(&self) -> Result<CircuitState, YamlAdminStoreError> {
+        let paths = self.paths();
+        #[cfg(feature = "admin-store-mmap")]
+        let circuit_contents = if self.mmap_circuit_file {
+            self.map_state_file(&paths.circuit_file_path, "circuit state")?
+        } else {
+            FileContents::Buffered(self.read_state_file(&paths.circuit_file_path, "circuit state")?)
+        };
+        #[cfg(not(feature = "admin-store-mmap"))]
+        let circuit_contents = FileContents::Buffered(
+            self.read_state_file(&paths.circuit_file_path, "circuit state")?,
+        );
+
+        if is_empty_file(&circuit_contents, &paths.circuit_file_path) {
+            return Ok(CircuitState::default());
+        }
+
+        if let Some(circuit_state) = self.read_circuit_cache(&circuit_contents) {
+            return Ok(circuit_state);
+        }
+
+        // Circuits without a creation time are given the time the file was last modified
+        let modified_time = self
+            .file_store
+            .modified(Path::new(&paths.circuit_file_path))
+            .unwrap_or(UNIX_EPOCH);
+
+        let circuit_state = match self.parse_circuit_state(&*circuit_contents, modified_time) {
+            Ok(circuit_state) => circuit_state,
+            Err(err) => {
+                if let Some(swapped_err) = swapped_paths_error(
+                    &circuit_contents,
+                    &paths.circuit_file_path,
+                    "circuit",
+                    PROPOSAL_STATE_KEYS,
+                ) {
+                    return Err(swapped_err);
+                }
+
+                match self.fill_missing_keys(
+                    &circuit_contents,
+                    &paths.circuit_file_path,
+                    "circuit",
+                    CIRCUIT_STATE_KEYS,
+                ) {
+                    Some(filled_contents) => {
+                        self.parse_circuit_state(filled_contents?.as_slice(), modified_time)?
+                    }
+                    None => return Err(err),
+                }
+            }
+        };
+
+        self.write_circuit_cache(&circuit_contents, &circuit_state);
+
+        Ok(circuit_state)
+    }
+
+    /// Reads circuit state in the store's file format from a reader, such as the contents of a
+    /// circuit state file that were fetched from elsewhere. The state is returned without being
+    /// loaded into the store.
+    ///
+    /// Circuits without a creation time are given `UNIX_EPOCH`, the time used for circuits whose
+    /// creation time is not known.
+    ///
+    /// # Arguments
+    ///
+    ///  * `reader` - The reader the circuit state is read from
+    pub fn read_circuit_state_from(
+        &self,
+        reader: impl Read,
+    ) -> Result<CircuitState, YamlAdminStoreError> {
+        self.parse_circuit_state(reader, UNIX_EPOCH)
+    }
+
+    /// Parses circuit state in the store's file format, giving circuits without a creation time
+    /// the given default
+    fn parse_circuit_state(
+        &self,
+        reader: impl Read,
+        default_created_at: SystemTime,
+    ) -> Result<CircuitState, YamlAdminStoreError> {
+        let yaml_state_circuits: YamlCircuitState =
+            self.file_format.read(reader, "circuit state file")?;
+
+        Ok(yaml_state_circuits.into_circuit_state(default_created_at))
+    }
+
+    /// Loads the circuit state from the store's circuit cache, if it has one and it was made from
+    /// the given contents of the circuit state file
+    fn read_circuit_cache(&self, circuit_contents: &[u8]) -> Option<CircuitState> {
+        let cache_path = self.paths().circuit_cache_path?;
+        let cache_contents = self.file_store.read(Path::new(&cache_path)).ok()?;
+
+        match cache::decode_cache(&cache_contents, circuit_contents) {
+            Some(circuit_state) => {
+                debug!("Loaded circuit state from cache '{}'", cache_path);
+                Some(circuit_state)
+            }
+            None => {
+                debug!(
+                    "Circuit cache '{}' does not match the circuit state file, ignoring it",
+                    cache_path
+                );
+                None
+            }
+        }
+    }
+
+    /// Replaces the store's circuit cache, if it has one, with a cache of the given circuit state
+    /// and the contents of the circuit state file it is stored in. The cache is only an
+    /// optimization, so a failure to write it is logged rather than returned.
+    fn write_circuit_cache(&self, circuit_contents: &[u8], circuit_state: &CircuitState) {
+        if let Some(cache_path) = &self.paths().circuit_cache_path {
+            let result =
+                cache::encode_cache(circuit_contents, circuit_state).and_then(|cache_contents| {
+                    self.file_store
+                        .write(Path::new(cache_path), &cache_contents)
+                        .map_err(|err| {
+                            YamlAdminStoreError::general_error_with_source(
+                                "Failed to write circuit cache",
+                                Box::new(err),
+                            )
+                        })
+                });
+
+            if let Err(err) = result {
+                warn!("Unable to write circuit cache '{}': {}", cache_path, err);
+            }
+        }
+    }
+
+    /// Read circuit proposal state from the proposal file path and cache the contents in the
+    /// store
+    fn read_proposal_state(&self) -> Result<(), YamlAdminStoreError> {
+        let (proposals_state, skipped_proposals) = self.parse_proposal_file()?;
+
+        let mut guard = self.lock_state().map_err(|_| {
+            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
+        })?;
+        let state = Arc::make_mut(&mut guard);
+
+        state.proposal_state = proposals_state;
+        state.skipped_proposals = skipped_proposals;
+        guard.publish();
+        Ok(())
+    }
+
+    /// Parse the proposal state file, returning the proposal state and the IDs of any skipped
+    /// proposals. Proposals are only skipped if lenient proposal parsing is enabled. An empty
+    /// file is parsed as empty state.
+    fn parse_proposal_file(&self) -> Result<(ProposalState, Vec<String>), YamlAdminStoreError> {
+        let paths = self.paths();
+        let proposal_contents =
+            self.read_state_file(&paths.proposal_file_path, "proposal state")?;
+
+        if is_empty_file(&proposal_contents, &paths.proposal_file_path) {
+            return Ok((ProposalState::default(), vec![]));
+        }
+
+        match self.read_proposal_state_from(proposal_contents.as_slice()) {
+            Ok(proposal_state) => Ok(proposal_state),
+            Err(err) => {
+                if let Some(swapped_err) = swapped_paths_error(
+                    &proposal_contents,
+                    &paths.proposal_file_path,
+                    "proposal",
+                    CIRCUIT_STATE_KEYS,
+                ) {
+                    return Err(swapped_err);
+                }
+
+                match self.fill_missing_keys(
+                    &proposal_contents,
+                    &paths.proposal_file_path,
+                    "proposal",
+                    PROPOSAL_STATE_KEYS,
+                ) {
+                    Some(filled_contents) => {
+                        self.read_proposal_state_from(filled_contents?.as_slice())
+                    }
+                    None => Err(err),
+                }
+            }
+        }
+    }
+
+    /// Handles a state file that could not be parsed because it is missing required top-level
+    /// keys, such as a file containing an empty mapping or a different document
+    ///
+    /// If the store has a strict schema, an error naming the missing keys is returned. Otherwise a
+    /// warning is logged and the contents are returned with each missing key added with no
+    /// entries.
+    ///
+    /// # Arguments
+    ///
+    ///  * `contents` - The contents of the state file that could not be parsed
+    ///  * `path` - The path of the state file
+    ///  * `description` - A description of the state file used in messages
+    ///  * `required_keys` - The top-level keys the state file must have
+    ///
+    /// Returns `None` if no required key is missing, so the file could not be parsed for another
+    /// reason
+    fn fill_missing_keys(
+        &self,
+        contents: &[u8],
+        path: &str,
+        description: &str,
+        required_keys: &[&str],
+    ) -> Option<Result<Vec<u8>, YamlAdminStoreError>> {
+        // YAML is a superset of JSON, so this works for either file format
+        let mut mapping = match serde_yaml::from_slice(contents).ok()? {
+            serde_yaml::Value::Mapping(mapping) => mapping,
+            serde_yaml::Value::Null => serde_yaml::Mapping::new(),
+            _ => return None,
+        };
+
+        let missing_keys = required_keys
+            .iter()
+            .filter(|key| !mapping.contains_key(&serde_yaml::Value::from(**key)))
+            .copied()
+            .collect::<Vec<_>>();
+        if missing_keys.is_empty() {
+            return None;
+        }
+
+        if self.strict_schema {
+            return Some(Err(YamlAdminStoreError::general_error(&format!(
+                "Failed to read {} state file '{}': it is missing the top-level key(s) {}",
+                description,
+                path,
+                missing_keys.join(", ")
+            ))));
+        }
+
+        warn!(
+            "The {} state file '{}' is missing the top-level key(s) {}, reading them as empty",
+            description,
+            path,
+            missing_keys.join(", ")
+        );
+
+        for key in missing_keys {
+            mapping.insert(
+                serde_yaml::Value::from(key),
+                serde_yaml::Value::Mapping(serde_yaml::Mapping::new()),
+            );
+        }
+
+        Some(self.file_format.write(
+            &serde_yaml::Value::Mapping(mapping),
+            &format!("{} state", description),
+        ))
+    }
+
+    /// Reads proposal state in the store's file format from a reader, such as the contents of a
+    /// proposal state file that were fetched from elsewhere. The state is returned without being
+    /// loaded into the store.
+    ///
+    /// If lenient proposal parsing is enabled, proposals that cannot be parsed are skipped and
+    /// their IDs are returned with the state. If proposal hash verification is enabled, a warning
+    /// is logged for each proposal whose circuit hash does not match its proposed circuit.
+    ///
+    /// # Arguments
+    ///
+    ///  * `reader` - The reader the proposal state is read from
+    pub fn read_proposal_state_from(
+        &self,
+        reader: impl Read,
+    ) -> Result<(ProposalState, Vec<String>), YamlAdminStoreError> {
+        let (proposal_state, skipped_proposals) = if self.lenient_proposals {
+            read_proposals_lenient(reader, self.file_format)?
+        } else {
+            let proposal_state = self.file_format.read(reader, "proposal state file")?;
+            (proposal_state, vec![])
+        };
+
+        if self.verify_proposal_hashes {
+            for proposal in proposal_state.proposals.values() {
+                match proposed_circuit_hash(&proposal.circuit) {
+                    Ok(circuit_hash) if circuit_hash == proposal.circuit_hash => (),
+                    Ok(_) => warn!(
+                        "Circuit hash of proposal {} does not match the proposed circuit",
+                        proposal.circuit_id
+                    ),
+                    Err(err) => warn!(
+                        "Unable to verify the circuit hash of proposal {}: {}",
+                        proposal.circuit_id, err
+                    ),
+                }
+            }
+        }
+
+        Ok((proposal_state, skipped_proposals))
+    }
+
+    /// Read archived proposal state from the archived proposal file path and cache the contents in
+    /// the store
+    fn read_archived_proposal_state(&self) -> Result<(), YamlAdminStoreError> {
+        let archived_proposal_file_path = match self.paths().archived_proposal_file_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let archived_proposal_contents =
+            self.read_state_file(&archived_proposal_file_path, "archived proposal state")?;
+
+        let archived_proposals_state: ProposalState =
+            if is_empty_file(&archived_proposal_contents, &archived_proposal_file_path) {
+                ProposalState::default()
+            } else {
+                self.file_format.read(
+                    archived_proposal_contents.as_slice(),
+                    "archived proposal state file",
+                )?
+            };
+
+        let mut guard = self.lock_state().map_err(|_| {
+            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
+        })?;
+        let state = Arc::make_mut(&mut guard);
+
+        state.archived_proposal_state = archived_proposals_state;
+        guard.publish();
+        Ok(())
+    }
+
+    /// Read circuit state from the circuit file path and cache the contents in the store and then
+    /// read circuit proposal state from the proposal file path and cache the contents in the
+    /// store
+    fn read_state(&self) -> Result<(), YamlAdminStoreError> {
+        let yaml_state = self.parse_circuit_file()?;
+
+        let (proposals_state, skipped_proposals) = self.parse_proposal_file()?;
+
+        let mut guard = self.lock_state().map_err(|_| {
+            YamlAdminStoreError::general_error("YAML admin service store's internal lock poisoned")
+        })?;
+        let state = Arc::make_mut(&mut guard);
+
+        state.replace_circuit_state(yaml_state);
+        state.proposal_state = proposals_state;
+        state.skipped_proposals = skipped_proposals;
+        guard.publish();
+
+        Ok(())
+    }
+
+    /// Write the locked circuit state to file at the circuit file path
+    fn write_circuit_state(&self, guard: &mut StateGuard<'_>) -> Result<(), YamlAdminStoreError> {
+        if self.in_memory {
+            guard.publish();
+            return Ok(());
+        }
+
+        if let Some(write_ahead_log) = &self.write_ahead_log {
+            return self.append_to_write_ahead_log(write_ahead_log, guard);
+        }
+
+        // The paths are read while the state is locked, so that a relocation is not missed
+        let paths = self.paths();
+        let result =
+            self.serialize_circuit_state(&guard.circuit_state)
+                .and_then(|circuit_output| {
+                    self.write_state_file(
+                        &paths.circuit_file_path,
+                        "circuit state",
+                        &circuit_output,
+                    )?;
+                    Ok(circuit_output)
+                });
+        let circuit_output = self.finish_write(guard, result)?;
+        self.write_circuit_cache(&circuit_output, &guard.circuit_state);
+
+        Ok(())
+    }
+
+    /// Write the locked circuit proposal state to file at the proposal file path
+    fn write_proposal_state(&self, guard: &mut StateGuard<'_>) -> Result<(), YamlAdminStoreError> {
+        if self.in_memory {
+            guard.publish();
+            return Ok(());
+        }
+
+        if let Some(write_ahead_log) = &self.write_ahead_log {
+            return self.append_to_write_ahead_log(write_ahead_log, guard);
+        }
+
+        // The paths are read while the state is locked, so that a relocation is not missed
+        let paths = self.paths();
+        let result = self
+            .serialize_proposal_state(&guard.proposal_state, "proposal state")
+            .and_then(|proposal_output| {
+                self.write_state_file(
+                    &paths.proposal_file_path,
+                    "proposal state",
+                    &proposal_output,
+                )
+            });
+        self.finish_write(guard, result)
+    }
+
+    /// Write the locked archived proposal state to file at the archived proposal file path, if
+    /// proposal archiving is enabled
+    fn write_archived_proposal_state(
+        &self,
+        guard: &mut StateGuard<'_>,
+    ) -> Result<(), YamlAdminStoreError> {
+        // The paths are read while the state is locked, so that a relocation is not missed
+        let archived_proposal_file_path = match self.paths().archived_proposal_file_path {
+            Some(path) if !self.in_memory => path,
+            _ => {
+                guard.publish();
+                return Ok(());
+            }
+        };
+
+        let result = self
+            .serialize_proposal_state(&guard.archived_proposal_state, "archived proposal state")
+            .and_then(|archived_proposal_output| {
+                self.write_state_file(
+                    &archived_proposal_file_path,
+                    "archived proposal state",
+                    &archived_proposal_output,
+                )
+            });
+        self.finish_write(guard, result)
+    }
+
+    /// Write the locked circuit state to file at the circuit file path and then write the locked
+    /// proposal state to the file at the proposal file path
+    fn write_state(&self, guard: &mut StateGuard<'_>) -> Result<(), YamlAdminStoreError> {
+        if self.in_memory {
+            guard.publish();
+            return Ok(());
+        }
+
+        if let Some(write_ahead_log) = &self.write_ahead_log {
+            self.append_to_write_ahead_log(write_ahead_log, guard)
+        } else {
+            let result = self.write_state_files(guard);
+            self.finish_write(guard, result)
+        }
+    }
+
+    /// Reads the contents of a state file from the store's file store
+    ///
+    /// # Arguments
+    ///
+    ///  * `path` - The path of the state file
+    ///  * `description` - A description of the state file used in error messages
+    fn read_state_file(
+        &self,
+        path: &str,
+        description: &str,
+    ) -> Result<Vec<u8>, YamlAdminStoreError> {
+        let contents = self.file_store.read(Path::new(path)).map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                &format!("Failed to open YAML {} file", description),
+                Box::new(err),
+            )
+        })?;
+
+        self.record_synced_file(path, &contents);
+
+        Ok(contents)
+    }
+
+    /// Maps a state file from the store's file store, recording its contents as the contents the
+    /// store last read from the file
+    ///
+    /// # Arguments
+    ///
+    ///  * `path` - The path of the state file
+    ///  * `description` - A description of the state file used in error messages
+    #[cfg(feature = "admin-store-mmap")]
+    fn map_state_file(
+        &self,
+        path: &str,
+        description: &str,
+    ) -> Result<FileContents, YamlAdminStoreError> {
+        let contents = self.file_store.map(Path::new(path)).map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                &format!("Failed to open YAML {} file", description),
+                Box::new(err),
+            )
+        })?;
+
+        self.record_synced_file(path, &contents);
+
+        Ok(contents)
+    }
+
+    /// Replaces the contents of a state file in the store's file store, retrying transient
+    /// errors with the store's write retry policy
+    ///
+    /// # Arguments
+    ///
+    ///  * `path` - The path of the state file
+    ///  * `description` - A description of the state file used in error messages
+    ///  * `contents` - The contents to write to the file
+    fn write_state_file(
+        &self,
+        path: &str,
+        description: &str,
+        contents: &[u8],
+    ) -> Result<(), YamlAdminStoreError> {
+        retry_io(self.write_retry, || {
+            self.file_store.write(Path::new(path), contents)
+        })
+        .map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                &format!("Failed to write to YAML {} file '{}'", description, path),
+                Box::new(err),
+            )
+        })?;
+
+        self.record_synced_file(path, contents);
+
+        Ok(())
+    }
+
+    /// Serializes circuit state in the store's file format, naming enum values in the store's
+    /// enum case and writing times in the store's timestamp format
+    fn serialize_circuit_state(
+        &self,
+        circuit_state: &CircuitState,
+    ) -> Result<Vec<u8>, YamlAdminStoreError> {
+        let yaml_state = YamlCircuitState::from(circuit_state.clone());
+
+        if self.enum_case == EnumCase::PascalCase
+            && self.timestamp_format == TimestampFormat::Rfc3339
+            && self.indent.is_none()
+        {
+            return self.file_format.write(&yaml_state, "circuit state");
+        }
+
+        let mut value = to_yaml_value(&yaml_state, "circuit state")?;
+        self.enum_case
+            .rename_fields(&mut value, "circuits", None, CIRCUIT_ENUM_FIELDS);
+        self.timestamp_format
+            .format_fields(&mut value, "circuits", CIRCUIT_TIMESTAMP_FIELDS);
+
+        self.write_value(&value, "circuit state")
+    }
+
+    /// Serializes proposal state in the store's file format, naming enum values in the store's
+    /// enum case
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_state` - The proposal state to serialize
+    ///  * `description` - A description of the state used in error messages
+    fn serialize_proposal_state(
+        &self,
+        proposal_state: &ProposalState,
+        description: &str,
+    ) -> Result<Vec<u8>, YamlAdminStoreError> {
+        if self.enum_case == EnumCase::PascalCase && self.indent.is_none() {
+            return self.file_format.write(proposal_state, description);
+        }
+
+        let mut value = to_yaml_value(proposal_state, description)?;
+        self.enum_case.rename_fields(
+            &mut value,
+            "proposals",
+            Some("circuit"),
+            PROPOSED_CIRCUIT_ENUM_FIELDS,
+        );
+
+        self.write_value(&value, description)
+    }
+
+    /// Serializes a state value in the store's file format, with the store's indentation width
+    /// if it has one and the format is YAML
+    ///
+    /// # Arguments
+    ///
+    ///  * `value` - The state to serialize
+    ///  * `description` - A description of the state used in error messages
+    fn write_value(
+        &self,
+        value: &serde_yaml::Value,
+        description: &str,
+    ) -> Result<Vec<u8>, YamlAdminStoreError> {
+        match (self.file_format, self.indent) {
+            (FileFormat::Yaml, Some(indent)) => {
+                indent::to_vec_with_indent(value, indent, description)
+            }
+            _ => self.file_format.write(value, description),
+        }
+    }
+
+    /// Write the given circuit state, proposal state and archived proposal state to the circuit,
+    /// proposal and archived proposal files. The archived proposal state is only written if
+    /// proposal archiving is enabled.
+    fn write_state_files(&self, state: &YamlState) -> Result<(), YamlAdminStoreError> {
+        let paths = self.paths();
+        let circuit_output = self.serialize_circuit_state(&state.circuit_state)?;
+        let proposal_output =
+            self.serialize_proposal_state(&state.proposal_state, "proposal state")?;
+        let archived_proposal_output = match &paths.archived_proposal_file_path {
+            Some(path) => Some((
+                path,
+                self.serialize_proposal_state(
+                    &state.archived_proposal_state,
+                    "archived proposal state",
+                )?,
+            )),
+            None => None,
+        };
+
+        if let Some(journal) = &self.journal {
+            let resolve = |path: &str| {
+                file_store::resolve_symlinks(Path::new(path)).map_err(|err| {
+                    YamlAdminStoreError::general_error_with_source(
+                        &format!("Failed to resolve state file path '{}'", path),
+                        Box::new(err),
+                    )
+                })
+            };
+
+            let mut files = vec![
+                (
+                    resolve(&paths.circuit_file_path)?,
+                    circuit_output.as_slice(),
+                ),
+                (
+                    resolve(&paths.proposal_file_path)?,
+                    proposal_output.as_slice(),
+                ),
+            ];
+            if let Some((path, output)) = &archived_proposal_output {
+                files.push((resolve(path)?, output.as_slice()));
+            }
+
+            journal.commit(
+                &files
+                    .iter()
+                    .map(|(path, output)| (path.as_path(), *output))
+                    .collect::<Vec<_>>(),
+            )?;
+            self.record_synced_file(&paths.circuit_file_path, &circuit_output);
+            self.record_synced_file(&paths.proposal_file_path, &proposal_output);
+            self.write_circuit_cache(&circuit_output, &state.circuit_state);
+
+            return Ok(());
+        }
+
+        self.write_state_file(&paths.circuit_file_path, "circuit state", &circuit_output)?;
+        self.write_circuit_cache(&circuit_output, &state.circuit_state);
+
+        self.write_state_file(
+            &paths.proposal_file_path,
+            "proposal state",
+            &proposal_output,
+        )?;
+
+        if let Some((path, output)) = &archived_proposal_output {
+            self.write_state_file(path, "archived proposal state", output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Append the changes recorded on the locked state to the write-ahead log, writing the state
+    /// files and truncating the log if a checkpoint is due. A change that replaces the whole state
+    /// is not logged; the state files are written and the log is truncated instead.
+    ///
+    /// Once the changes are appended they are published and not rolled back, even if the
+    /// checkpoint fails, because they are recovered from the log.
+    fn append_to_write_ahead_log(
+        &self,
+        write_ahead_log: &WriteAheadLog,
+        guard: &mut StateGuard<'_>,
+    ) -> Result<(), YamlAdminStoreError> {
+        if guard.changes.contains(&StateChange::State) {
+            let result = self
+                .write_state_files(guard)
+                .and_then(|_| write_ahead_log.checkpoint());
+            return self.finish_write(guard, result);
+        }
+
+        let result = write_ahead_log.append(&guard.changes, guard);
+        if self.finish_write(guard, result)? {
+            self.write_state_files(guard)?;
+            write_ahead_log.checkpoint()?;
+
+            if let Some(metrics) = &self.metrics {
+                metrics.record_checkpoint();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Imports circuit and proposal state into the store and writes both state files
+    ///
+    /// If `overwrite` is true the existing state is replaced. Otherwise the imported state is
+    /// merged with the existing state, and an error is returned without changing the store if any
+    /// imported circuit or proposal already exists.
+    fn import_state(
+        &self,
+        circuit_state: CircuitState,
+        proposal_state: ProposalState,
+        overwrite: bool,
+    ) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        guard.record(StateChange::State);
+        let state = Arc::make_mut(&mut guard);
+
+        if overwrite {
+            state.circuit_state = CircuitState::default();
+            state.proposal_state = ProposalState::default();
+            state.service_directory = BTreeMap::new();
+        } else {
+            if let Some(circuit_id) = circuit_state
+                .circuits
+                .keys()
+                .find(|circuit_id| state.circuit_state.circuits.contains_key(*circuit_id))
+            {
+                return Err(AdminServiceStoreError::OperationError {
+                    context: format!("A circuit with ID {} already exists", circuit_id),
+                    source: None,
+                });
+            }
+
+            if let Some(proposal_id) = proposal_state
+                .proposals
+                .keys()
+                .find(|proposal_id| state.proposal_state.proposals.contains_key(*proposal_id))
+            {
+                return Err(AdminServiceStoreError::OperationError {
+                    context: format!("A proposal with ID {} already exists", proposal_id),
+                    source: None,
+                });
+            }
+        }
+
+        for (circuit_id, circuit) in circuit_state.circuits.into_iter() {
+            for service in circuit.roster.iter() {
+                let service_id =
+                    ServiceId::new(service.service_id.to_string(), circuit_id.to_string());
+
+                state.service_directory.insert(service_id, service.clone());
+            }
+
+            state.circuit_state.circuits.insert(circuit_id, circuit);
+        }
+
+        for (node_id, node) in circuit_state.nodes.into_iter() {
+            state.circuit_state.nodes.entry(node_id).or_insert(node);
+        }
+
+        for proposal in proposal_state.proposals_by_submission_order() {
+            state.proposal_state.insert_proposal(proposal.clone());
+        }
+
+        debug_assert_service_directory(state);
+
+        self.write_state(&mut guard)
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to write circuit state yaml files".to_string(),
+                source: Some(Box::new(err)),
+            })
+    }
+}
+
+/// Reads circuit state in the given file format from a reader, such as a circuit state file
+/// piped through a tool, without a store
+///
+/// Circuits without a creation time are given `UNIX_EPOCH`, the time used for circuits whose
+/// creation time is not known.
+///
+/// # Arguments
+///
+///  * `reader` - The reader the circuit state is read from
+///  * `file_format` - The format the circuit state is in
+pub fn read_circuit_state_from(
+    reader: impl Read,
+    file_format: FileFormat,
+) -> Result<CircuitState, YamlAdminStoreError> {
+    let yaml_state_circuits: YamlCircuitState = file_format.read(reader, "circuit state")?;
+
+    Ok(yaml_state_circuits.into_circuit_state(UNIX_EPOCH))
+}
+
+/// Writes circuit state in the given file format to a writer, as it would be written to a circuit
+/// state file by a store with the default enum case and indentation
+///
+/// # Arguments
+///
+///  * `writer` - The writer the circuit state is written to
+///  * `circuit_state` - The circuit state to write
+///  * `file_format` - The format to write the circuit state in
+pub fn write_circuit_state_to(
+    mut writer: impl Write,
+    circuit_state: &CircuitState,
+    file_format: FileFormat,
+) -> Result<(), YamlAdminStoreError> {
+    let output = file_format.write(
+        &YamlCircuitState::from(circuit_state.clone()),
+        "circuit state",
+    )?;
+
+    writer
+        .write_all(&output)
+        .and_then(|_| writer.flush())
+        .map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                "Failed to write circuit state",
+                Box::new(err),
+            )
+        })
+}
+
+/// Reads YAML circuit state from standard input, as with `read_circuit_state_from`
+pub fn read_circuit_state_stdin() -> Result<CircuitState, YamlAdminStoreError> {
+    read_circuit_state_from(io::stdin().lock(), FileFormat::Yaml)
+}
+
+/// Writes circuit state as YAML to standard output, as with `write_circuit_state_to`
+///
+/// # Arguments
+///
+///  * `circuit_state` - The circuit state to write
+pub fn write_circuit_state_stdout(circuit_state: &CircuitState) -> Result<(), YamlAdminStoreError> {
+    write_circuit_state_to(io::stdout().lock(), circuit_state, FileFormat::Yaml)
+}
+
+/// Returns whether two stores hold the same circuit and proposal state, such as a primary and a
+/// replica whose state files are mirrored. Archived proposals are not compared.
+///
+/// The state of each store is loaded once, so each is compared as it was at a single point in
+/// time. If the stores differ, the IDs of the nodes, circuits and proposals that differ are
+/// logged at debug level.
+///
+/// # Arguments
+///
+///  * `a` - The first store to compare
+///  * `b` - The second store to compare
+pub fn stores_in_sync(
+    a: &YamlAdminServiceStore,
+    b: &YamlAdminServiceStore,
+) -> Result<bool, AdminServiceStoreError> {
+    let a_state = a.shared_state()?;
+    let b_state = b.shared_state()?;
+
+    if a_state.circuit_state == b_state.circuit_state
+        && a_state.proposal_state == b_state.proposal_state
+    {
+        return Ok(true);
+    }
+
+    debug!(
+        "Stores are not in sync; differing nodes: {:?}, circuits: {:?}, proposals: {:?}",
+        differing_keys(&a_state.circuit_state.nodes, &b_state.circuit_state.nodes),
+        differing_keys(
+            &a_state.circuit_state.circuits,
+            &b_state.circuit_state.circuits
+        ),
+        differing_keys(
+            &a_state.proposal_state.proposals,
+            &b_state.proposal_state.proposals
+        ),
+    );
+
+    Ok(false)
+}
+
+/// Returns the keys, in order, that are in only one of two maps or whose values differ
+fn differing_keys<'a, V: PartialEq>(
+    a: &'a BTreeMap<String, V>,
+    b: &'a BTreeMap<String, V>,
+) -> Vec<&'a str> {
+    a.keys()
+        .chain(b.keys())
+        .map(String::as_str)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|key| a.get(*key) != b.get(*key))
+        .collect()
+}
+
+/// Appends a file with the given contents and modification time to a tar archive
+#[cfg(feature = "admin-store-tar")]
+fn append_tar_entry<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+    modified_time: SystemTime,
+) -> Result<(), AdminServiceStoreError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        modified_time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0),
+    );
+    header.set_cksum();
+
+    archive.append_data(&mut header, path, data).map_err(|err| {
+        AdminServiceStoreError::StorageError {
+            context: format!("Failed to write {} to tar archive", path),
+            source: Some(Box::new(err)),
+        }
+    })
+}
+
+/// Serializes a value to a YAML value
+///
+/// # Arguments
+///
+///  * `value` - The value to serialize
+///  * `description` - A description of the value used in error messages
+fn to_yaml_value<T: Serialize>(
+    value: &T,
+    description: &str,
+) -> Result<serde_yaml::Value, YamlAdminStoreError> {
+    serde_yaml::to_value(value).map_err(|err| {
+        YamlAdminStoreError::general_error_with_source(
+            &format!("Failed to serialize {}", description),
+            Box::new(err),
+        )
+    })
+}
+
+/// Computes the hash of a proposed circuit, as set in the `circuit_hash` of the proposal by the
+/// admin service
+fn proposed_circuit_hash(circuit: &ProposedCircuit) -> Result<String, YamlAdminStoreError> {
+    let mut proto = admin::Circuit::new();
+
+    proto.set_circuit_id(circuit.circuit_id.to_string());
+    proto.set_roster(RepeatedField::from_vec(
+        circuit
+            .roster
+            .iter()
+            .map(|service| {
+                let mut proto = admin::SplinterService::new();
+                proto.set_service_id(service.service_id.to_string());
+                proto.set_service_type(service.service_type.to_string());
+                proto.set_allowed_nodes(RepeatedField::from_vec(service.allowed_nodes.clone()));
+                proto.set_arguments(RepeatedField::from_vec(
+                    service
+                        .arguments
+                        .iter()
+                        .map(|(key, value)| {
+                            let mut argument = admin::SplinterService_Argument::new();
+                            argument.set_key(key.to_string());
+                            argument.set_value(value.to_string());
+                            argument
+                        })
+                        .collect(),
+                ));
+                proto
+            })
+            .collect(),
+    ));
+    proto.set_members(RepeatedField::from_vec(
+        circuit
+            .members
+            .iter()
+            .map(|node| {
+                let mut proto = admin::SplinterNode::new();
+                proto.set_node_id(node.node_id.to_string());
+                proto.set_endpoints(RepeatedField::from_vec(node.endpoints.clone()));
+                proto
+            })
+            .collect(),
+    ));
+
+    proto.set_circuit_management_type(circuit.circuit_management_type.to_string());
+    proto.set_application_metadata(circuit.application_metadata.clone());
+    proto.set_comments(circuit.comments.to_string());
+
+    match circuit.authorization_type {
+        AuthorizationType::Trust => {
+            proto.set_authorization_type(admin::Circuit_AuthorizationType::TRUST_AUTHORIZATION)
+        }
+        AuthorizationType::Challenge => {
+            return Err(YamlAdminStoreError::general_error(
+                "Challenge authorization cannot be represented in a circuit message",
+            ))
+        }
+    };
+
+    match circuit.persistence {
+        PersistenceType::Any => {
+            proto.set_persistence(admin::Circuit_PersistenceType::ANY_PERSISTENCE)
+        }
+    };
+    match circuit.durability {
+        DurabilityType::NoDurability => {
+            proto.set_durability(admin::Circuit_DurabilityType::NO_DURABILITY)
+        }
+    };
+    match circuit.routes {
+        RouteType::Any => proto.set_routes(admin::Circuit_RouteType::ANY_ROUTE),
+    };
+
+    let bytes = proto.write_to_bytes().map_err(|err| {
+        YamlAdminStoreError::general_error_with_source(
+            "Failed to serialize proposed circuit",
+            Box::new(err),
+        )
+    })?;
+
+    hash(MessageDigest::sha256(), &bytes)
+        .map(|digest| to_hex(&digest))
+        .map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                "Failed to hash proposed circuit",
+                Box::new(err),
+            )
+        })
+}
+
+/// How file writes that fail with a transient error are retried
+#[derive(Clone, Copy, Debug, Default)]
+struct WriteRetry {
+    /// The number of times a failed write is retried
+    max_retries: u32,
+    /// The time waited before the first retry, doubled for each retry after it
+    initial_backoff: Duration,
+}
+
+/// Runs an I/O operation, retrying it with exponential backoff while it fails with an error that
+/// may be transient. Other errors are returned immediately.
+fn retry_io<T, F>(retry: WriteRetry, mut operation: F) -> io::Result<T>
+where
+    F: FnMut() -> io::Result<T>,
+{
+    let mut backoff = retry.initial_backoff;
+    let mut retries = 0;
+
+    loop {
+        match operation() {
+            Err(err) if retries < retry.max_retries && is_transient(&err) => {
+                warn!(
+                    "File write failed, retrying in {:?} ({} of {}): {}",
+                    backoff,
+                    retries + 1,
+                    retry.max_retries,
+                    err
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns whether an error was caused by an I/O error reporting that the filesystem is
+/// read-only. The error is identified by its OS error code, so this is always false on platforms
+/// other than Unix.
+fn is_read_only(err: &YamlAdminStoreError) -> bool {
+    let mut source = err.source();
+    while let Some(err) = source {
+        #[cfg(unix)]
+        if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            if io_err.raw_os_error() == Some(libc::EROFS) {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+
+    false
+}
+
+/// Returns whether an I/O error may succeed if the operation is retried
+fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// Checks whether a state file that could not be parsed has a top-level key that belongs in the
+/// other state file, which suggests the circuit and proposal file paths were swapped. Returns an
+/// error describing the likely mistake if so.
+///
+/// # Arguments
+///
+///  * `contents` - The contents of the state file that could not be parsed
+///  * `path` - The path of the state file
+///  * `description` - The kind of state file expected at the path
+///  * `unexpected_keys` - The top-level keys of the other state file
+fn swapped_paths_error(
+    contents: &[u8],
+    path: &str,
+    description: &str,
+    unexpected_keys: &[&str],
+) -> Option<YamlAdminStoreError> {
+    // YAML is a superset of JSON, so this works for either file format
+    let value: serde_yaml::Value = serde_yaml::from_slice(contents).ok()?;
+    let mapping = value.as_mapping()?;
+    let key = unexpected_keys
+        .iter()
+        .find(|key| mapping.contains_key(&serde_yaml::Value::from(**key)))?;
+
+    Some(YamlAdminStoreError::general_error(&format!(
+        "Failed to read {} state file '{}': it has a top-level '{}' key, so the circuit and \
+         proposal file paths may be swapped",
+        description, path, key
+    )))
+}
+
+/// The top-level keys a circuit state file must have
+const CIRCUIT_STATE_KEYS: &[&str] = &["nodes", "circuits"];
+
+/// The top-level keys a proposal state file must have
+const PROPOSAL_STATE_KEYS: &[&str] = &["proposals"];
+
+/// Returns whether a state file is empty, logging a warning if it is
+///
+/// An empty state file can be left behind by a write that failed before state files were written
+/// atomically. It is read as empty state, the same as a newly created file, rather than failing.
+fn is_empty_file(contents: &[u8], path: &str) -> bool {
+    let empty = contents.is_empty();
+
+    if empty {
+        warn!("State file {} is empty, reading it as empty state", path);
+    }
+
+    empty
+}
+
+/// Defines methods for CRUD operations and fetching and listing circuits, proposals, nodes and
+/// services from a YAML file backend
+impl AdminServiceStore for YamlAdminServiceStore {
+    /// Adds a circuit proposal to the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal` - The proposal to be added
+    ///
+    ///  Returns an error if a `CircuitProposal` with the same ID already exists, unless the
+    ///  store's `ProposalConflictPolicy` allows it
+    fn add_proposal(&self, proposal: CircuitProposal) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+
+        if let Some(existing) = guard.proposal_state.proposals.get(&proposal.circuit_id) {
+            match self.proposal_conflict_policy {
+                ProposalConflictPolicy::UpsertIfIdentical if existing == &proposal => return Ok(()),
+                ProposalConflictPolicy::Overwrite => (),
+                _ => {
+                    return Err(AdminServiceStoreError::OperationError {
+                        context: format!(
+                            "A proposal with ID {} already exists",
+                            proposal.circuit_id
+                        ),
+                        source: None,
+                    })
+                }
+            }
+        }
+
+        guard.record(StateChange::Proposal(proposal.circuit_id.to_string()));
+        Arc::make_mut(&mut guard)
+            .proposal_state
+            .insert_proposal(proposal);
+
+        self.write_proposal_state(&mut guard)
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to write proposal state yaml file".to_string(),
+                source: Some(Box::new(err)),
+            })
+    }
+
+    /// Updates a circuit proposal in the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal` - The proposal with the updated information
+    ///
+    ///  Returns an error if a `CircuitProposal` with the same ID does not exist
+    fn update_proposal(&self, proposal: CircuitProposal) -> Result<(), AdminServiceStoreError> {
+        let mut guard = self.lock_state()?;
+        guard.record(StateChange::Proposal(proposal.circuit_id.to_string()));
+        let state = Arc::make_mut(&mut guard);
+
+        if state
+            .proposal_state
+            .proposals
+            .contains_key(&proposal.circuit_id)
+        {
+            state.proposal_state.insert_proposal(proposal);
+        } else {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!("A proposal with ID {} does not exist", proposal.circuit_id),
+                source: None,
+            });
+        }
+
+        self.write_proposal_state(&mut guard)
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to write proposal state yaml file".to_string(),
+                source: Some(Box::new(err)),
+            })
+    }
+
+    /// Removes a circuit proposal from the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_id` - The unique ID of the circuit proposal to be removed
+    ///
+    ///  Returns a `NotFoundError` if a `CircuitProposal` with specified ID does not exist
+    fn remove_proposal(&self, proposal_id: &str) -> Result<(), AdminServiceStoreError> {
+        self.take_proposal(proposal_id).map(|_| ())
+    }
+
+    /// Removes a circuit proposal from the underlying storage, returning the removed proposal.
+    /// The proposal is removed and returned under the store's write lock.
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_id` - The unique ID of the circuit proposal to be removed
+    ///
+    ///  Returns a `NotFoundError` if a `CircuitProposal` with specified ID does not exist
+    fn take_proposal(&self, proposal_id: &str) -> Result<CircuitProposal, AdminServiceStoreError> {
+        self.take_proposal_if_present(proposal_id)?.ok_or_else(|| {
+            AdminServiceStoreError::NotFoundError(format!(
+                "Cannot find circuit proposal with id: {}",
+                proposal_id
+            ))
+        })
+    }
+
+    /// Fetches a circuit proposal from the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_id` - The unique ID of the circuit proposal to be returned
+    fn fetch_proposal(
+        &self,
+        proposal_id: &str,
+    ) -> Result<Option<CircuitProposal>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .proposal_state
+            .proposals
+            .get(proposal_id)
+            .cloned())
+    }
+
+    /// List circuit proposals from the underlying storage
+    ///
+    /// The proposals returned can be filtered by provided CircuitPredicate. This enables
+    /// filtering by management type and members.
+    fn list_proposals(
+        &self,
+        predicates: &[CircuitPredicate],
+    ) -> Result<Box<dyn ExactSizeIterator<Item = CircuitProposal>>, AdminServiceStoreError> {
+        let predicates = predicates.to_vec();
+
+        Ok(Box::new(SnapshotIter::new(
+            self.shared_state()?,
+            |state| &state.proposal_state.proposals,
+            Box::new(move |proposal| {
+                predicates
+                    .iter()
+                    .all(|predicate| predicate.apply_to_proposals(proposal))
+            }),
+        )))
+    }
+
+    /// Adds a circuit to the underlying storage. Also includes the associated Services and
+    /// Nodes
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit` - The circuit to be added to state
+    ///  * `nodes` - A list of nodes that represent the circuit's members
+    ///
+    ///  Returns an error if a `Circuit` with the same ID already exists, or if the store requires
+    ///  known members and a member has no node definition
+    fn add_circuit(
+        &self,
+        circuit: Circuit,
+        nodes: Vec<CircuitNode>,
+    ) -> Result<(), AdminServiceStoreError> {
+        let mut circuit = circuit;
+        circuit.created_at = self.clock.now();
+        circuit.updated_at = circuit.created_at;
+
+        let mut guard = self.lock_state()?;
+        guard.record(StateChange::Circuit(circuit.id.to_string()));
+        guard.record_nodes(nodes.iter().map(|node| &node.id));
+        let state = Arc::make_mut(&mut guard);
+
+        if state.circuit_state.circuits.contains_key(&circuit.id) {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!("A circuit with ID {} already exists", circuit.id),
+                source: None,
+            });
+        } else {
+            self.check_allowed_nodes(&circuit)?;
+            self.check_circuit_validators(&circuit)?;
+            self.check_node_conflicts(state, &circuit.id, &nodes)?;
+            self.check_known_members(state, &circuit, &nodes)?;
+
+            for node in nodes.into_iter() {
+                state.insert_node_if_absent(node);
+            }
+
+            state.insert_circuit(circuit);
+        }
+
+        debug_assert_service_directory(state);
+
+        self.write_circuit_state(&mut guard)
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to write circuit state yaml file".to_string(),
+                source: Some(Box::new(err)),
+            })
+    }
+
+    /// Updates a circuit in the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit` - The circuit with the updated information
+    ///
+    ///  Returns an error if a `CircuitProposal` with the same ID does not exist
+    fn update_circuit(&self, circuit: Circuit) -> Result<(), AdminServiceStoreError> {
+        let mut circuit = circuit;
+
+        let mut guard = self.lock_state()?;
+        guard.record(StateChange::Circuit(circuit.id.to_string()));
+        let state = Arc::make_mut(&mut guard);
+
+        if let Some(existing) = state.circuit_state.circuits.get(&circuit.id) {
+            self.check_allowed_nodes(&circuit)?;
+            self.check_circuit_validators(&circuit)?;
+
+            circuit.created_at = existing.created_at;
+            circuit.updated_at = self.clock.now();
+
+            state.insert_circuit(circuit);
+        } else {
+            return Err(AdminServiceStoreError::OperationError {
+                context: format!("A circuit with ID {} does not exist", circuit.id),
+                source: None,
+            });
+        }
+
+        debug_assert_service_directory(state);
+
+        self.write_circuit_state(&mut guard)
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Unable to write circuit state yaml file".to_string(),
+                source: Some(Box::new(err)),
+            })
+    }
+
+    /// Removes a circuit from the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_id` - The unique ID of the circuit to be removed
+    ///
+    ///  If the store was built with `YamlAdminServiceStoreBuilder::with_prune_nodes_on_remove`,
+    ///  nodes that are no longer a member of any circuit are removed with the circuit.
+    ///
+    ///  Returns an error if a `Circuit` with the specified ID does not exist
+    fn remove_circuit(&self, circuit_id: &str) -> Result<(), AdminServiceStoreError> {
+        if self.remove_circuit_if_present(circuit_id)? {
+            Ok(())
+        } else {
+            Err(AdminServiceStoreError::OperationError {
+                context: format!("A circuit with ID {} does not exist", circuit_id),
+                source: None,
+            })
+        }
+    }
+
+    /// Fetches a circuit from the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_id` - The unique ID of the circuit to be returned
+    fn fetch_circuit(&self, circuit_id: &str) -> Result<Option<Circuit>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .circuit_state
+            .circuits
+            .get(circuit_id)
+            .cloned())
+    }
+
+    /// List all circuits from the underlying storage
+    ///
+    /// The proposals returned can be filtered by provided CircuitPredicate. This enables
+    /// filtering by management type and members.
+    fn list_circuits(
+        &self,
+        predicates: &[CircuitPredicate],
+    ) -> Result<Box<dyn ExactSizeIterator<Item = Circuit>>, AdminServiceStoreError> {
+        let predicates = predicates.to_vec();
+
+        Ok(Box::new(SnapshotIter::new(
+            self.shared_state()?,
+            |state| &state.circuit_state.circuits,
+            Box::new(move |circuit| {
+                predicates
+                    .iter()
+                    .all(|predicate| predicate.apply_to_circuit(circuit))
+            }),
+        )))
+    }
+
+    /// Adds a circuit to the underlying storage based on the proposal that is already in state..
+    /// Also includes the associated Services and Nodes. The associated circuit proposal for
+    /// the circuit ID is also removed
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_id` - The ID of the circuit proposal that should be converted to a circuit
+    fn upgrade_proposal_to_circuit(&self, circuit_id: &str) -> Result<(), AdminServiceStoreError> {
+        self.finalize_proposal(circuit_id).map(|_| ())
+    }
+
+    /// Fetches a node from the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `node_id` - The unique ID of the node to be returned
+    fn fetch_node(&self, node_id: &str) -> Result<Option<CircuitNode>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .circuit_state
+            .nodes
+            .get(node_id)
+            .cloned())
+    }
+
+    /// List all nodes from the underlying storage
+    fn list_nodes(
+        &self,
+    ) -> Result<Box<dyn ExactSizeIterator<Item = CircuitNode>>, AdminServiceStoreError> {
+        Ok(Box::new(SnapshotIter::new(
+            self.shared_state()?,
+            |state| &state.circuit_state.nodes,
+            Box::new(|_| true),
+        )))
+    }
+
+    /// Fetches a service from the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `service_id` - The `ServiceId` of a service made up of the circuit ID and service ID
+    fn fetch_service(
+        &self,
+        service_id: &ServiceId,
+    ) -> Result<Option<Service>, AdminServiceStoreError> {
+        Ok(self
+            .shared_state()?
+            .service_directory
+            .get(service_id)
+            .cloned())
+    }
+
+    /// List all services in a specific circuit from the underlying storage
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_id` - The unique ID of the circuit the services belong to
+    fn list_services(
+        &self,
+        circuit_id: &str,
+    ) -> Result<Box<dyn ExactSizeIterator<Item = Service>>, AdminServiceStoreError> {
+        let services: Vec<Service> = self
+            .shared_state()?
+            .circuit_state
+            .circuits
+            .get(circuit_id)
+            .ok_or(AdminServiceStoreError::OperationError {
+                context: format!("Circuit {} does not exist", circuit_id),
+                source: None,
+            })?
+            .roster
+            .clone();
+
+        Ok(Box::new(services.into_iter()))
+    }
+
+    /// List all circuits, proposals and nodes from the underlying storage
+    ///
+    /// The lists are read from a single load of the store's state, so they reflect the same
+    /// state.
+    fn list_all(&self) -> Result<AdminStateView, AdminServiceStoreError> {
+        let state = self.shared_state()?;
+
+        Ok(AdminStateView {
+            circuits: state.circuit_state.circuits.values().cloned().collect(),
+            proposals: state.proposal_state.proposals.values().cloned().collect(),
+            nodes: state.circuit_state.nodes.values().cloned().collect(),
+        })
+    }
+}
+
+/// YAML file specific circuit definition. This circuit definition in the 0.4v YAML stores service
+/// arguments in a map format, which differs from the definition defined in the AdminServiceStore.
+/// To handle this, circuit needs to be converted to the correct format during read/write
+/// operations.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct YamlCircuit {
+    id: String,
+    roster: Vec<YamlService>,
+    members: Vec<String>,
+    auth: AuthorizationType,
+    persistence: PersistenceType,
+    durability: DurabilityType,
+    routes: RouteType,
+    circuit_management_type: String,
+    #[serde(default, with = "optional_timestamp")]
+    created_at: Option<SystemTime>,
+    #[serde(default, with = "optional_timestamp")]
+    updated_at: Option<SystemTime>,
+    #[serde(default)]
+    circuit_status: CircuitStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+}
+
+impl YamlCircuit {
+    /// Converts the YAML circuit into a `Circuit`
+    ///
+    /// # Arguments
+    ///
+    ///  * `default_created_at` - The creation time used if the circuit does not have one, which
+    ///    is the case for circuits written before creation times were recorded. Circuits without
+    ///    an update time use their creation time.
+    fn into_circuit(self, default_created_at: SystemTime) -> Circuit {
+        let created_at = self.created_at.unwrap_or(default_created_at);
+
+        let circuit_id = self.id;
+        let roster = self
+            .roster
+            .into_iter()
+            .map(|service| {
+                let (service, report) = service.into_service_checked();
+                if !report.is_lossless() {
+                    warn!(
+                        "Dropping fields of service {} in circuit {} that cannot be \
+                         represented: {:?}",
+                        service.service_id, circuit_id, report.dropped_fields
+                    );
+                }
+                service
+            })
+            .collect();
+
+        Circuit {
+            id: circuit_id,
+            roster,
+            members: self.members,
+            auth: self.auth,
+            persistence: self.persistence,
+            durability: self.durability,
+            routes: self.routes,
+            circuit_management_type: self.circuit_management_type,
+            created_at,
+            updated_at: self.updated_at.unwrap_or(created_at),
+            circuit_status: self.circuit_status,
+            display_name: self.display_name,
+        }
+    }
+}
+
+impl From<Circuit> for YamlCircuit {
+    fn from(circuit: Circuit) -> Self {
+        YamlCircuit {
+            id: circuit.id,
+            roster: circuit.roster.into_iter().map(YamlService::from).collect(),
+            members: circuit.members,
+            auth: circuit.auth,
+            persistence: circuit.persistence,
+            durability: circuit.durability,
+            routes: circuit.routes,
+            circuit_management_type: circuit.circuit_management_type,
+            created_at: Some(circuit.created_at),
+            updated_at: Some(circuit.updated_at),
+            circuit_status: circuit.circuit_status,
+            display_name: circuit.display_name,
+        }
+    }
+}
+
+/// Serialization of an optional time as an RFC 3339 timestamp, used for fields that are missing
+/// from older state files. Times are also read from seconds or milliseconds since the Unix epoch,
+/// which a store writes in place of the timestamp if it has another `TimestampFormat`.
+mod optional_timestamp {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::EPOCH_MILLIS_THRESHOLD;
+
+    /// A time in any of the formats it may be written in
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timestamp {
+        Rfc3339(String),
+        Epoch(u64),
+    }
+
+    pub fn serialize<S>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match time {
+            Some(time) => serializer.serialize_str(&humantime::format_rfc3339(*time).to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<SystemTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<Timestamp>::deserialize(deserializer)?
+            .map(|timestamp| match timestamp {
+                Timestamp::Rfc3339(timestamp) => {
+                    humantime::parse_rfc3339_weak(&timestamp).map_err(serde::de::Error::custom)
+                }
+                Timestamp::Epoch(millis) if millis >= EPOCH_MILLIS_THRESHOLD => {
+                    Ok(UNIX_EPOCH + Duration::from_millis(millis))
+                }
+                Timestamp::Epoch(secs) => Ok(UNIX_EPOCH + Duration::from_secs(secs)),
+            })
+            .transpose()
+    }
+}
+
+/// YAML file specific service definition. This service definition in the 0.4v YAML stores
+/// arguments in a map format, which differs from the definition defined in the AdminServiceStore.
+/// To handle this, service needs to be converted to the correct format during read/write
+/// operations.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct YamlService {
+    service_id: String,
+    service_type: String,
+    allowed_nodes: Vec<String>,
+    #[serde(deserialize_with = "deserialize_arguments")]
+    arguments: BTreeMap<String, String>,
+    /// Fields in the file that are not part of the service definition. These cannot be
+    /// represented by a `Service` and are dropped when the service is converted.
+    #[serde(flatten)]
+    unknown_fields: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// Deserializes service arguments from either a map or a list of key-value pairs. Circuit state
+/// files store arguments as a map, but a service copied from a proposal state file will have its
+/// arguments as a list of pairs.
+fn deserialize_arguments<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Arguments {
+        Map(BTreeMap<String, String>),
+        Pairs(Vec<(String, String)>),
+    }
+
+    match serde::Deserialize::deserialize(deserializer)? {
+        Arguments::Map(arguments) => Ok(arguments),
+        Arguments::Pairs(arguments) => Ok(arguments.into_iter().collect()),
+    }
+}
+
+impl YamlService {
+    /// Converts the YAML service into a `Service`, reporting any data that could not be
+    /// represented by the `Service` and was dropped
+    fn into_service_checked(self) -> (Service, ConversionReport) {
+        let report = ConversionReport {
+            dropped_fields: self.unknown_fields.keys().cloned().collect(),
+        };
+
+        let service = Service {
+            service_id: self.service_id,
+            service_type: self.service_type,
+            allowed_nodes: self.allowed_nodes,
+            arguments: self
+                .arguments
+                .into_iter()
+                .map(|(key, value)| (key, value))
+                .collect(),
+        };
+
+        (service, report)
+    }
+}
+
+impl From<YamlService> for Service {
+    fn from(service: YamlService) -> Self {
+        service.into_service_checked().0
+    }
+}
+
+/// The data dropped when converting from a YAML file definition to a store definition
+#[derive(Debug, PartialEq, Eq)]
+struct ConversionReport {
+    dropped_fields: Vec<String>,
+}
+
+impl ConversionReport {
+    /// Returns true if no data was dropped during the conversion
+    fn is_lossless(&self) -> bool {
+        self.dropped_fields.is_empty()
+    }
+}
+
+impl From<Service> for YamlService {
+    fn from(service: Service) -> Self {
+        YamlService {
+            service_id: service.service_id,
+            service_type: service.service_type,
+            allowed_nodes: service.allowed_nodes,
+            arguments: service
+                .arguments
+                .into_iter()
+                .map(|(key, value)| (key, value))
+                .collect(),
+            unknown_fields: BTreeMap::new(),
+        }
+    }
+}
+
+/// YAML file specific state definition that can be read and written to the circuit YAML state file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+struct YamlCircuitState {
+    nodes: BTreeMap<String, CircuitNode>,
+    circuits: BTreeMap<String, YamlCircuit>,
+}
+
+impl YamlCircuitState {
+    /// Converts the YAML circuit state into the cached `CircuitState`
+    ///
+    /// # Arguments
+    ///
+    ///  * `default_created_at` - The creation time used for circuits that do not have one
+    fn into_circuit_state(self, default_created_at: SystemTime) -> CircuitState {
+        CircuitState {
+            nodes: self.nodes,
+            circuits: self
+                .circuits
+                .into_iter()
+                .map(|(id, circuit)| (id, circuit.into_circuit(default_created_at)))
+                .collect(),
+        }
+    }
+}
+
+impl From<CircuitState> for YamlCircuitState {
+    fn from(state: CircuitState) -> Self {
+        YamlCircuitState {
+            nodes: state.nodes,
+            circuits: state
+                .circuits
+                .into_iter()
+                .map(|(id, circuit)| (id, YamlCircuit::from(circuit)))
+                .collect(),
+        }
+    }
+}
+
+/// The circuit state that is cached by the YAML admin service store and used to respond to fetch
+/// requests
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct CircuitState {
+    nodes: BTreeMap<String, CircuitNode>,
+    circuits: BTreeMap<String, Circuit>,
+}
+
+impl CircuitState {
+    /// Returns the nodes, keyed by node ID
+    pub fn nodes(&self) -> &BTreeMap<String, CircuitNode> {
+        &self.nodes
+    }
+
+    /// Returns the circuits, keyed by circuit ID
+    pub fn circuits(&self) -> &BTreeMap<String, Circuit> {
+        &self.circuits
+    }
+}
+
+impl TryFrom<&[u8]> for CircuitState {
+    type Error = YamlAdminStoreError;
+
+    /// Parses the contents of a YAML circuit state file. Circuits without a creation time, which
+    /// is the case for circuits written before creation times were recorded, are given
+    /// `UNIX_EPOCH`.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        read_circuit_state_from(bytes, FileFormat::Yaml)
+    }
+}
+
+/// The proposal state that is cached by the YAML admin service store and used to respond to fetch
+/// requests
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ProposalState {
+    proposals: BTreeMap<String, CircuitProposal>,
+    /// The sequence number of each proposal, in the order they were submitted. Proposals read
+    /// from files written before submission order was recorded do not have one.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    submission_order: BTreeMap<String, u64>,
+}
+
+impl ProposalState {
+    /// Returns the proposals, keyed by circuit ID
+    pub fn proposals(&self) -> &BTreeMap<String, CircuitProposal> {
+        &self.proposals
+    }
+
+    /// Returns the proposals in the order they were submitted. Proposals without a sequence
+    /// number come first, in ID order.
+    pub fn proposals_by_submission_order(&self) -> Vec<&CircuitProposal> {
+        let mut proposals = self.proposals.values().collect::<Vec<_>>();
+        proposals.sort_by_key(|proposal| {
+            self.submission_order
+                .get(&proposal.circuit_id)
+                .copied()
+                .unwrap_or(0)
+        });
+        proposals
+    }
+
+    /// Inserts a proposal, giving it the next sequence number if it is not already in the state
+    fn insert_proposal(&mut self, proposal: CircuitProposal) {
+        if !self.proposals.contains_key(&proposal.circuit_id) {
+            let sequence = self
+                .submission_order
+                .values()
+                .max()
+                .map(|sequence| sequence + 1)
+                .unwrap_or(1);
+            self.submission_order
+                .insert(proposal.circuit_id.to_string(), sequence);
+        }
+
+        self.proposals
+            .insert(proposal.circuit_id.to_string(), proposal);
+    }
+
+    /// Inserts a proposal with the given sequence number, or without one if it is `None`,
+    /// replacing any sequence number it already had
+    fn insert_proposal_with_sequence(&mut self, proposal: CircuitProposal, sequence: Option<u64>) {
+        match sequence {
+            Some(sequence) => self
+                .submission_order
+                .insert(proposal.circuit_id.to_string(), sequence),
+            None => self.submission_order.remove(&proposal.circuit_id),
+        };
+
+        self.proposals
+            .insert(proposal.circuit_id.to_string(), proposal);
+    }
+
+    /// Removes a proposal and its sequence number
+    fn remove_proposal(&mut self, proposal_id: &str) -> Option<CircuitProposal> {
+        self.submission_order.remove(proposal_id);
+        self.proposals.remove(proposal_id)
+    }
+}
+
+impl TryFrom<&[u8]> for ProposalState {
+    type Error = YamlAdminStoreError;
+
+    /// Parses the contents of a YAML proposal state file
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        FileFormat::Yaml.read(bytes, "proposal state")
+    }
+}
+
+/// The proposal state file with each proposal left unparsed, used to load proposals one at a time
+#[derive(Deserialize)]
+struct RawProposalState {
+    proposals: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    submission_order: BTreeMap<String, u64>,
+}
+
+/// Reads proposal state in the given format, skipping any proposal that cannot be parsed
+///
+/// Returns the proposals that were parsed and the IDs of the proposals that were skipped. An
+/// error is only returned if the file itself is not a valid proposal state file.
+fn read_proposals_lenient<R: Read>(
+    reader: R,
+    file_format: FileFormat,
+) -> Result<(ProposalState, Vec<String>), YamlAdminStoreError> {
+    let raw_state: RawProposalState = file_format.read(reader, "proposal state file")?;
+
+    let mut proposal_state = ProposalState::default();
+    let mut skipped = Vec::new();
+    for (proposal_id, value) in raw_state.proposals {
+        match serde_yaml::from_value::<CircuitProposal>(value) {
+            Ok(proposal) => {
+                if let Some(sequence) = raw_state.submission_order.get(&proposal_id) {
+                    proposal_state
+                        .submission_order
+                        .insert(proposal_id.to_string(), *sequence);
+                }
+                proposal_state.proposals.insert(proposal_id, proposal);
+            }
+            Err(err) => {
+                warn!("Skipping unparseable proposal {}: {}", proposal_id, err);
+                skipped.push(proposal_id);
+            }
+        }
+    }
+
+    Ok((proposal_state, skipped))
+}
+
+/// Exclusive access to the state of a `YamlAdminServiceStore`, returned by `lock_state`
+///
+/// Changes made through the guard are only published to readers by `publish`, once they have been
+/// written, so readers never see a partly changed state or a change that failed to be written.
+/// Changes that have not been published when the guard is dropped are discarded.
+struct StateGuard<'a> {
+    _write_lock: MutexGuard<'a, ()>,
+    state: Arc<YamlState>,
+    /// The state when the guard was acquired or last published, which `roll_back` restores
+    original: Arc<YamlState>,
+    /// The entries changed since the guard was acquired or last published, which are appended to
+    /// the write-ahead log
+    changes: Vec<StateChange>,
+    published: &'a ArcSwap<YamlState>,
+}
+
+impl<'a> StateGuard<'a> {
+    /// Records that an entry of the locked state has been changed, so the change is appended to
+    /// the write-ahead log when it is written
+    fn record(&mut self, change: StateChange) {
+        self.changes.push(change);
+    }
+
+    /// Records that each of the given nodes may have been changed
+    fn record_nodes<'n>(&mut self, node_ids: impl IntoIterator<Item = &'n String>) {
+        self.changes
+            .extend(node_ids.into_iter().cloned().map(StateChange::Node));
+    }
+
+    /// Publishes the locked state to readers
+    fn publish(&mut self) {
+        if !Arc::ptr_eq(&self.state, &self.original) {
+            self.published.store(Arc::clone(&self.state));
+            self.original = Arc::clone(&self.state);
+        }
+        self.changes.clear();
+    }
+
+    /// Discards the changes made since the guard was acquired or last published
+    fn roll_back(&mut self) {
+        self.state = Arc::clone(&self.original);
+        self.changes.clear();
+    }
+}
+
+impl<'a> Deref for StateGuard<'a> {
+    type Target = Arc<YamlState>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl<'a> DerefMut for StateGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.state
+    }
+}
+
+/// The prefix added to the proposal state file's name to name the archived proposal file
+const ARCHIVED_PROPOSAL_FILE_PREFIX: &str = "archived_";
+
+/// The paths of the files the state of a `YamlAdminServiceStore` is written to
+#[derive(Debug, Clone, Default)]
+struct StatePaths {
+    circuit_file_path: String,
+    proposal_file_path: String,
+    archived_proposal_file_path: Option<String>,
+    circuit_cache_path: Option<String>,
+}
+
+impl StatePaths {
+    /// Creates the paths for the given circuit and proposal state files. The archived proposal
+    /// file is placed next to the proposal state file, named after it with an `archived_`
+    /// prefix, and the circuit cache next to the circuit state file.
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_file_path` - The path of the circuit state file
+    ///  * `proposal_file_path` - The path of the proposal state file
+    ///  * `archive_proposals` - Whether the store archives proposals
+    ///  * `circuit_cache` - Whether the store keeps a circuit cache
+    fn new(
+        circuit_file_path: String,
+        proposal_file_path: String,
+        archive_proposals: bool,
+        circuit_cache: bool,
+    ) -> Result<Self, YamlAdminStoreError> {
+        let archived_proposal_file_path = if archive_proposals {
+            let proposal_file_name = Path::new(&proposal_file_path)
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .ok_or_else(|| {
+                    YamlAdminStoreError::general_error(&format!(
+                        "Proposal file path '{}' does not have a valid file name",
+                        proposal_file_path
+                    ))
+                })?;
+            let path = PathBuf::from(&proposal_file_path).with_file_name(format!(
+                "{}{}",
+                ARCHIVED_PROPOSAL_FILE_PREFIX, proposal_file_name
+            ));
+            Some(
+                path.to_str()
+                    .ok_or_else(|| {
+                        YamlAdminStoreError::general_error(
+                            "Archived proposal file path is not valid UTF-8",
+                        )
+                    })?
+                    .to_string(),
+            )
+        } else {
+            None
+        };
+
+        let circuit_cache_path = if circuit_cache {
+            let path = PathBuf::from(&circuit_file_path).with_extension("cache");
+            Some(
+                path.to_str()
+                    .ok_or_else(|| {
+                        YamlAdminStoreError::general_error("Circuit cache path is not valid UTF-8")
+                    })?
+                    .to_string(),
+            )
+        } else {
+            None
+        };
+
+        Ok(StatePaths {
+            circuit_file_path,
+            proposal_file_path,
+            archived_proposal_file_path,
+            circuit_cache_path,
+        })
+    }
+
+    /// Returns the paths of all of the files the state is written to
+    fn files(&self) -> Vec<&str> {
+        let mut files = vec![
+            self.circuit_file_path.as_str(),
+            self.proposal_file_path.as_str(),
+        ];
+        files.extend(self.archived_proposal_file_path.as_deref());
+        files.extend(self.circuit_cache_path.as_deref());
+        files
+    }
+}
+
+/// The combination of circuit and circuit proposal state
+#[derive(Debug, Clone, Default)]
+struct YamlState {
+    circuit_state: CircuitState,
+    proposal_state: ProposalState,
+    archived_proposal_state: ProposalState,
+    service_directory: BTreeMap<ServiceId, Service>,
+    skipped_proposals: Vec<String>,
+}
+
+impl YamlState {
+    /// Adds a node if a node with the same ID does not already exist, returning whether the node
+    /// was added
+    fn insert_node_if_absent(&mut self, node: CircuitNode) -> bool {
+        if self.circuit_state.nodes.contains_key(&node.id) {
+            false
+        } else {
+            self.circuit_state.nodes.insert(node.id.to_string(), node);
+            true
+        }
+    }
+
+    /// Returns the IDs of the nodes that are not a member of any circuit, in node ID order
+    fn orphan_node_ids(&self) -> Vec<String> {
+        let members = self
+            .circuit_state
+            .circuits
+            .values()
+            .flat_map(|circuit| circuit.members.iter().map(String::as_str))
+            .collect::<BTreeSet<_>>();
+
+        self.circuit_state
+            .nodes
+            .keys()
+            .filter(|node_id| !members.contains(node_id.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the nodes that are not a member of any circuit, returning their IDs in node ID
+    /// order
+    fn remove_orphan_nodes(&mut self) -> Vec<String> {
+        let orphans = self.orphan_node_ids();
+        for node_id in orphans.iter() {
+            self.circuit_state.nodes.remove(node_id);
+        }
+
+        orphans
+    }
+
+    /// Inserts a circuit, replacing the services of any existing circuit with the same ID in the
+    /// service directory with the services in the new circuit's roster
+    fn insert_circuit(&mut self, circuit: Circuit) {
+        if let Some(existing) = self.circuit_state.circuits.get(&circuit.id) {
+            for service in existing.roster.iter() {
+                let service_id =
+                    ServiceId::new(service.service_id.to_string(), circuit.id.to_string());
+                self.service_directory.remove(&service_id);
+            }
+        }
+
+        for service in circuit.roster.iter() {
+            let service_id = ServiceId::new(service.service_id.to_string(), circuit.id.to_string());
+            self.service_directory.insert(service_id, service.clone());
+        }
+
+        self.circuit_state
+            .circuits
+            .insert(circuit.id.to_string(), circuit);
+    }
+
+    /// Replaces the circuit state, removing the services of the replaced circuits from the
+    /// service directory and adding the services of the new circuits
+    fn replace_circuit_state(&mut self, circuit_state: CircuitState) {
+        for (circuit_id, circuit) in self.circuit_state.circuits.iter() {
+            for service in circuit.roster.iter() {
+                let service_id =
+                    ServiceId::new(service.service_id.to_string(), circuit_id.to_string());
+
+                self.service_directory.remove(&service_id);
+            }
+        }
+
+        for (circuit_id, circuit) in circuit_state.circuits.iter() {
+            for service in circuit.roster.iter() {
+                let service_id =
+                    ServiceId::new(service.service_id.to_string(), circuit_id.to_string());
+
+                self.service_directory.insert(service_id, service.clone());
+            }
+        }
+
+        self.circuit_state = circuit_state;
+    }
+}
+
+/// Builds a service directory containing the services in the rosters of the given circuits
+fn build_service_directory(circuits: &BTreeMap<String, Circuit>) -> BTreeMap<ServiceId, Service> {
+    circuits
+        .iter()
+        .flat_map(|(circuit_id, circuit)| {
+            circuit.roster.iter().map(move |service| {
+                (
+                    ServiceId::new(service.service_id.to_string(), circuit_id.to_string()),
+                    service.clone(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Returns a hash of the contents of a state file, used to detect changes to the file
+fn contents_hash(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Checks, in debug builds only, that the service directory matches the circuit rosters
+fn debug_assert_service_directory(state: &YamlState) {
+    debug_assert!(
+        state.service_directory == build_service_directory(&state.circuit_state.circuits),
+        "Service directory does not match the circuit rosters"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use tempdir::TempDir;
+
+    use super::*;
+
+    use crate::admin::store::builders::{
+        CircuitBuilder, CircuitNodeBuilder, CircuitProposalBuilder, ProposedCircuitBuilder,
+        ProposedNodeBuilder, ProposedServiceBuilder, ServiceBuilder,
+    };
+    use crate::admin::store::{ProposalType, Vote, VoteRecord};
+    use crate::hex::parse_hex;
+
+    pub(super) const CIRCUIT_STATE: &[u8] = b"---
+nodes:
+    acme-node-000:
+        id: acme-node-000
+        endpoints:
+          - \"tcps://splinterd-node-acme:8044\"
+    bubba-node-000:
+        id: bubba-node-000
+        endpoints:
+          - \"tcps://splinterd-node-bubba:8044\"
+circuits:
+    WBKLF-AAAAA:
+        id: WBKLF-AAAAA
+        auth: Trust
+        members:
+          - bubba-node-000
+          - acme-node-000
+        roster:
+          - service_id: a000
+            service_type: scabbard
+            allowed_nodes:
+              - acme-node-000
+            arguments:
+              admin_keys: '[\"035724d11cae47c8907f8bfdf510488f49df8494ff81b63825bad923733c4ac550\"]'
+              peer_services: '[\"a001\"]'
+          - service_id: a001
+            service_type: scabbard
+            allowed_nodes:
+              - bubba-node-000
+            arguments:
+              admin_keys: '[\"035724d11cae47c8907f8bfdf510488f49df8494ff81b63825bad923733c4ac550\"]'
+              peer_services: '[\"a000\"]'
+        persistence: Any
+        durability: NoDurability
+        routes: Any
+        circuit_management_type: gameroom
+        created_at: \"2020-06-01T00:00:00Z\"";
+
+    pub(super) const PROPOSAL_STATE: &[u8] = b"---
+proposals:
+    WBKLF-BBBBB:
+        proposal_type: Create
+        circuit_id: WBKLF-BBBBB
+        circuit_hash: 7ddc426972710adc0b2ecd49e89a9dd805fb9206bf516079724c887bedbcdf1d
+        circuit:
+            circuit_id: WBKLF-BBBBB
             roster:
             - service_id: a000
               service_type: scabbard
@@ -1141,306 +4752,3978 @@ proposals:
         requester: 0283a14e0a17cb7f665311e9b5560f4cde2b502f17e2d03223e15d90d9318d7482
         requester_node_id: acme-node-000";
 
-    // Validate that if the YAML state files do not exist, the YamlAdminServiceStore will create
-    // the files with empty states.
+    // Validate that if the YAML state files do not exist, the YamlAdminServiceStore will create
+    // the files with empty states.
+    //
+    // 1. Creates a empty temp directory
+    // 2. Create a YAML admin service directory
+    // 3. Validate that the circuit and proposals YAMLfiles were created in the temp dir.
+    #[test]
+    fn test_write_new_files() {
+        let (_temp_dir, circuit_path, proposals_path) = state_file_paths("test_write_new_files");
+
+        // validate the files do not exist
+        assert!(!PathBuf::from(circuit_path.clone()).is_file());
+        assert!(!PathBuf::from(proposals_path.clone()).is_file());
+
+        // create YamlAdminServiceStore
+        let (_store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        // validate the files exist now
+        assert!(PathBuf::from(circuit_path.clone()).is_file());
+        assert!(PathBuf::from(proposals_path.clone()).is_file());
+    }
+
+    // Validate that the YAML admin service store can properly load circuit and proposals state
+    // from existing YAML files
+    //
+    // 1. Creates a temp directory with existing circuit and proposals yaml files
+    // 2. Create a YAML admin service directory
+    // 3. Validate that the circuit and proposals can be fetched from state
+    #[test]
+    fn test_read_existing_files() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_read_existing_files");
+
+        // create YamlAdminServiceStore
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        assert!(store
+            .fetch_proposal("WBKLF-BBBBB")
+            .expect("unable to fetch proposals")
+            .is_some());
+        assert!(store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("unable to fetch circuits")
+            .is_some());
+    }
+
+    // Test the proposal CRUD operations
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Fetch an existing proposal from state, validate proposal is returned
+    // 3. Fetch an non exisitng proposal from state, validate None
+    // 4. Update fetched proposal with a vote record and update, validate ok
+    // 5. Call update with new proposal, validate error is returned
+    // 6. Add new proposal, validate ok
+    // 7. List proposal, validate both the updated original proposal and new proposal is returned
+    // 8. Remove original proposal, validate okay
+    // 9. Validate the proposal state YAML in the temp dir matches the expected bytes and only
+    //    the new proposals
+    #[test]
+    fn test_proposals() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_proposals");
+
+        // create YamlAdminServiceStore
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        // fetch existing proposal from state
+        let mut proposal = store
+            .fetch_proposal("WBKLF-BBBBB")
+            .expect("unable to fetch proposals")
+            .expect("Expected proposal, got none");
+
+        assert_eq!(proposal, create_expected_proposal());
+
+        // fetch nonexisting proposal from state
+        assert!(store
+            .fetch_proposal("WBKLF-BADD")
+            .expect("unable to fetch proposals")
+            .is_none());
+
+        proposal.add_vote(VoteRecord {
+            public_key: parse_hex(
+                "035724d11cae47c8907f8bfdf510488f49df8494ff81b63825bad923733c4ac550",
+            )
+            .unwrap(),
+            vote: Vote::Accept,
+            voter_node_id: "bubba-node-000".into(),
+        });
+
+        store
+            .update_proposal(proposal.clone())
+            .expect("Unable to update proposal");
+
+        let new_proposal = new_proposal();
+
+        assert!(
+            store.update_proposal(new_proposal.clone()).is_err(),
+            "Updating new proposal should fail"
+        );
+
+        store
+            .add_proposal(new_proposal.clone())
+            .expect("Unable to add proposal");
+
+        assert_eq!(
+            store
+                .list_proposals(&vec![])
+                .expect("Unable to get list of proposals")
+                .collect::<Vec<CircuitProposal>>(),
+            vec![proposal, new_proposal.clone()]
+        );
+
+        store
+            .remove_proposal("WBKLF-BBBBB")
+            .expect("Unable to remove proposals");
+
+        let mut submission_order = BTreeMap::new();
+        submission_order.insert(new_proposal.circuit_id.to_string(), 1);
+        let mut yaml_state = BTreeMap::new();
+        yaml_state.insert(new_proposal.circuit_id.to_string(), new_proposal);
+        let mut yaml_state_vec = serde_yaml::to_vec(&ProposalState {
+            proposals: yaml_state,
+            submission_order,
+        })
+        .unwrap();
+
+        // Add new line because the file always ends with one
+        if !yaml_state_vec.ends_with(b"\n") {
+            yaml_state_vec.push(b'\n');
+        }
+
+        let mut contents = vec![];
+        File::open(proposals_path.clone())
+            .unwrap()
+            .read_to_end(&mut contents)
+            .expect("Unable to read proposals");
+
+        assert_eq!(yaml_state_vec, contents)
+    }
+
+    // Test the circuit CRUD operations
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Fetch an existing circuit from state, validate circuit is returned
+    // 3. Fetch an non exisitng circuit from state, validate None
+    // 4. Update fetched proposa with a vote record and update, validate ok
+    // 5. Call update with new circuit, validate error is returned
+    // 6. Add new circuit, validate ok
+    // 7. List circuit, validate both the updated original circuit and new circuit is returned
+    // 8. Remove original circuit, validate okay
+    // 9. Validate the circuit state YAML in the temp dir matches the expected bytes and contains
+    //    only the new circuit
+    #[test]
+    fn test_circuit() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_circuit");
+
+        // create YamlAdminServiceStore
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        // fetch existing circuit from state
+        let mut circuit = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("unable to fetch circuit")
+            .expect("Expected circuit, got none");
+
+        assert_eq!(circuit, create_expected_circuit());
+
+        // fetch nonexisting circuitfrom state
+        assert!(store
+            .fetch_circuit("WBKLF-BADD")
+            .expect("unable to fetch circuit")
+            .is_none());
+
+        circuit.circuit_management_type = "test".to_string();
+
+        store
+            .update_circuit(circuit.clone())
+            .expect("Unable to update circuit");
+
+        // the store records the time the circuit was updated
+        circuit.updated_at = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("unable to fetch circuit")
+            .expect("Expected circuit, got none")
+            .updated_at;
+        assert!(circuit.updated_at > circuit.created_at);
+
+        let (new_circuit, new_node) = new_circuit();
+
+        assert!(
+            store.update_circuit(new_circuit.clone()).is_err(),
+            "Updating new cirucit should fail"
+        );
+
+        store
+            .add_circuit(new_circuit.clone(), vec![new_node.clone()])
+            .expect("Unable to add cirucit");
+
+        // the store records the time the circuit was added
+        let mut new_circuit = new_circuit;
+        new_circuit.created_at = store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("unable to fetch circuit")
+            .expect("Expected circuit, got none")
+            .created_at;
+        new_circuit.updated_at = new_circuit.created_at;
+        assert!(new_circuit.created_at > UNIX_EPOCH);
+
+        assert_eq!(
+            store
+                .list_circuits(&vec![])
+                .expect("Unable to get list of circuits")
+                .collect::<Vec<Circuit>>(),
+            vec![circuit, new_circuit.clone()]
+        );
+
+        store
+            .remove_circuit("WBKLF-AAAAA")
+            .expect("Unable to remove circuit");
+
+        let mut yaml_circuits = BTreeMap::new();
+        let mut yaml_nodes = BTreeMap::new();
+        yaml_circuits.insert(new_circuit.id.to_string(), YamlCircuit::from(new_circuit));
+        yaml_nodes.insert(
+            "acme-node-000".to_string(),
+            CircuitNode {
+                id: "acme-node-000".to_string(),
+                endpoints: vec!["tcps://splinterd-node-acme:8044".into()],
+            },
+        );
+        yaml_nodes.insert(
+            "bubba-node-000".to_string(),
+            CircuitNode {
+                id: "bubba-node-000".to_string(),
+                endpoints: vec!["tcps://splinterd-node-bubba:8044".into()],
+            },
+        );
+        yaml_nodes.insert(new_node.id.to_string(), new_node);
+        let mut yaml_state_vec = serde_yaml::to_vec(&YamlCircuitState {
+            circuits: yaml_circuits,
+            nodes: yaml_nodes,
+        })
+        .unwrap();
+
+        // Add new line because the file always ends with one
+        if !yaml_state_vec.ends_with(b"\n") {
+            yaml_state_vec.push(b'\n');
+        }
+
+        let mut contents = vec![];
+        File::open(circuit_path.clone())
+            .unwrap()
+            .read_to_end(&mut contents)
+            .expect("Unable to read proposals");
+
+        assert_eq!(yaml_state_vec, contents)
+    }
+
+    // Test that circuit creation times are recorded, persisted and defaulted for legacy files
+    //
+    // 1. Setup the temp directory with circuit state that does not have creation times
+    // 2. Check that the existing circuit's creation time defaults to the file's modified time
+    // 3. Add a new circuit and check that its creation time is set when it is added
+    // 4. Update the new circuit and check that its creation time is preserved
+    // 5. Check that the circuits are listed in creation order by list_circuits_sorted
+    // 6. Reload the store and check that the creation times round-trip through the file
+    #[test]
+    fn test_circuit_created_at() {
+        let (_temp_dir, circuit_path, proposals_path) = state_file_paths("test_circuit_created_at");
+
+        // write yaml files to temp_dir, removing the creation time from the circuit state
+        let legacy_circuit_state = String::from_utf8(CIRCUIT_STATE.to_vec())
+            .expect("Circuit state is not valid UTF-8")
+            .replace("\n        created_at: \"2020-06-01T00:00:00Z\"", "");
+        assert!(!legacy_circuit_state.contains("created_at"));
+        write_file(legacy_circuit_state.as_bytes(), &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let modified = std::fs::metadata(&circuit_path)
+            .and_then(|metadata| metadata.modified())
+            .expect("Unable to get circuit file modified time");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let legacy_circuit = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+        assert_eq!(legacy_circuit.created_at(), modified);
+
+        let before_add = SystemTime::now();
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit.clone(), vec![node])
+            .expect("Unable to add circuit");
+
+        let created_at = store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none")
+            .created_at();
+        assert!(created_at >= before_add);
+
+        store
+            .update_circuit(circuit)
+            .expect("Unable to update circuit");
+        assert_eq!(
+            store
+                .fetch_circuit("WBKLF-DDDDD")
+                .expect("Unable to fetch circuit")
+                .expect("Expected circuit, got none")
+                .created_at(),
+            created_at
+        );
+
+        assert_eq!(
+            store
+                .list_circuits_sorted(&[], CircuitSortOrder::ByCreatedAt)
+                .expect("Unable to list circuits")
+                .map(|circuit| circuit.created_at())
+                .collect::<Vec<_>>(),
+            vec![modified, created_at]
+        );
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert_eq!(
+            store
+                .list_circuits_sorted(&[], CircuitSortOrder::ById)
+                .expect("Unable to list circuits")
+                .map(|circuit| (circuit.id.clone(), circuit.created_at()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("WBKLF-AAAAA".to_string(), modified),
+                ("WBKLF-DDDDD".to_string(), created_at)
+            ]
+        );
+    }
+
+    // Test that writing state through a symlinked state file preserves the symlink
+    //
+    // 1. Setup the temp directory with circuit state in a separate directory and a symlink to it
+    // 2. Add a circuit to the store
+    // 3. Check that the symlink still exists and the file it points to contains the new circuit
+    #[cfg(unix)]
+    #[test]
+    fn test_write_through_symlink() {
+        // create temp dir
+        let temp_dir =
+            TempDir::new("test_write_through_symlink").expect("Failed to create temp dir");
+        let shared_dir = temp_dir.path().join("shared");
+        std::fs::create_dir(&shared_dir).expect("Failed to create shared dir");
+
+        let real_circuit_path = shared_dir.join("circuits.yaml");
+        let circuit_path = temp_dir.path().join("circuits.yaml");
+        let proposals_path = temp_dir
+            .path()
+            .join("circuit_proposals.yaml")
+            .to_str()
+            .expect("Failed to get path")
+            .to_string();
+
+        // write yaml files to temp_dir, linking the circuit state file to the shared dir
+        write_file(
+            CIRCUIT_STATE,
+            real_circuit_path.to_str().expect("Failed to get path"),
+        );
+        write_file(PROPOSAL_STATE, &proposals_path);
+        std::os::unix::fs::symlink("shared/circuits.yaml", &circuit_path)
+            .expect("Failed to create symlink");
+
+        let (store, _) = YamlAdminServiceStore::new(
+            circuit_path
+                .to_str()
+                .expect("Failed to get path")
+                .to_string(),
+            proposals_path,
+        )
+        .expect("Unable to create yaml admin store");
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+
+        assert!(std::fs::symlink_metadata(&circuit_path)
+            .expect("Unable to get symlink metadata")
+            .file_type()
+            .is_symlink());
+
+        let mut contents = String::new();
+        File::open(&real_circuit_path)
+            .expect("Unable to open circuit file")
+            .read_to_string(&mut contents)
+            .expect("Unable to read circuit file");
+        assert!(contents.contains("WBKLF-AAAAA"));
+        assert!(contents.contains("WBKLF-DDDDD"));
+    }
+
+    // Test that take_proposal returns the removed proposal
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Take the existing proposal and check the returned proposal matches it
+    // 3. Check that taking the proposal again returns a not found error
+    #[test]
+    fn test_take_proposal_returns_proposal() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_take_proposal_returns_proposal");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let existing = store
+            .fetch_proposal("WBKLF-BBBBB")
+            .expect("Unable to fetch proposal")
+            .expect("Expected proposal, got none");
+
+        let removed = store
+            .take_proposal("WBKLF-BBBBB")
+            .expect("Unable to remove proposal");
+
+        assert_eq!(removed, existing);
+        assert_eq!(removed, create_expected_proposal());
+        match store.take_proposal("WBKLF-BBBBB") {
+            Err(AdminServiceStoreError::NotFoundError(_)) => (),
+            res => panic!("Expected a not found error, got {:?}", res),
+        }
+    }
+
+    // Test that circuits and proposals that use challenge authorization round-trip through the
+    // state files
+    //
+    // 1. Setup the temp directory with circuit and proposal state that use `auth: Challenge`
+    // 2. Check that the circuit and proposal are loaded with challenge authorization
+    // 3. Write the state back out, reload the store and check the authorization is unchanged
+    #[test]
+    fn test_challenge_authorization() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            state_file_paths("test_challenge_authorization");
+
+        // write yaml files to temp_dir, using challenge authorization
+        let circuit_state = String::from_utf8(CIRCUIT_STATE.to_vec())
+            .expect("Circuit state is not valid UTF-8")
+            .replace("auth: Trust", "auth: Challenge");
+        let proposal_state = String::from_utf8(PROPOSAL_STATE.to_vec())
+            .expect("Proposal state is not valid UTF-8")
+            .replace("authorization_type: Trust", "authorization_type: Challenge");
+        write_file(circuit_state.as_bytes(), &circuit_path);
+        write_file(proposal_state.as_bytes(), &proposals_path);
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let circuit = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+        assert_eq!(circuit.auth, AuthorizationType::Challenge);
+
+        let proposal = store
+            .fetch_proposal("WBKLF-BBBBB")
+            .expect("Unable to fetch proposal")
+            .expect("Expected proposal, got none");
+        assert_eq!(
+            proposal.circuit.authorization_type,
+            AuthorizationType::Challenge
+        );
+
+        // rewrite both state files and reload them
+        store
+            .update_circuit(circuit)
+            .expect("Unable to update circuit");
+        store
+            .update_proposal(proposal.clone())
+            .expect("Unable to update proposal");
+
+        // the store records the time the circuit was updated
+        let circuit = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(store.fetch_circuit("WBKLF-AAAAA").unwrap(), Some(circuit));
+        assert_eq!(store.fetch_proposal("WBKLF-BBBBB").unwrap(), Some(proposal));
+    }
+
+    // Test that service_exists reports whether a service is in the store
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Check that an existing service exists
+    // 3. Check that unknown services do not exist
+    #[test]
+    fn test_service_exists() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_service_exists");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert!(store
+            .service_exists(&ServiceId::new(
+                "a000".to_string(),
+                "WBKLF-AAAAA".to_string()
+            ))
+            .expect("Unable to check service"));
+        assert!(!store
+            .service_exists(&ServiceId::new(
+                "zzzz".to_string(),
+                "WBKLF-AAAAA".to_string()
+            ))
+            .expect("Unable to check service"));
+        assert!(!store
+            .service_exists(&ServiceId::new(
+                "a000".to_string(),
+                "WBKLF-ZZZZZ".to_string()
+            ))
+            .expect("Unable to check service"));
+    }
+
+    // Test that rebuild_service_directory and reload restore a service directory that no longer
+    // matches the circuit rosters
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Corrupt the service directory by removing a service and adding an unknown service
+    // 3. Rebuild the service directory and check that it matches the circuit roster
+    // 4. Create a store that rebuilds the directory on reload, corrupt it again and reload
+    // 5. Check that the service directory matches the circuit roster
+    #[test]
+    fn test_rebuild_service_directory() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_rebuild_service_directory");
+
+        let existing_id = ServiceId::new("a000".to_string(), "WBKLF-AAAAA".to_string());
+        let unknown_id = ServiceId::new("zzzz".to_string(), "WBKLF-AAAAA".to_string());
+
+        let corrupt = |store: &YamlAdminServiceStore| {
+            let mut guard = store.lock_state().expect("Unable to lock state");
+            let state = Arc::make_mut(&mut guard);
+            let service = state
+                .service_directory
+                .remove(&existing_id)
+                .expect("Service is missing from the directory");
+            state.service_directory.insert(unknown_id.clone(), service);
+            guard.publish();
+        };
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        corrupt(&store);
+        assert!(!store.service_exists(&existing_id).unwrap());
+        assert!(store.service_exists(&unknown_id).unwrap());
+
+        store
+            .rebuild_service_directory()
+            .expect("Unable to rebuild service directory");
+
+        assert!(!store.service_exists(&unknown_id).unwrap());
+        assert_eq!(
+            store.fetch_service(&existing_id).unwrap(),
+            Some(create_expected_circuit().roster[0].clone())
+        );
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_rebuild_on_reload(true)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        corrupt(&store);
+        store.reload().expect("Unable to reload store");
+
+        assert!(!store.service_exists(&unknown_id).unwrap());
+        assert_eq!(
+            store.fetch_service(&existing_id).unwrap(),
+            Some(create_expected_circuit().roster[0].clone())
+        );
+    }
+
+    // Test that state files end with exactly one newline and that writing the same state again
+    // produces identical bytes
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Update a proposal without changing it and read the proposal state file
+    // 3. Check that the file ends with exactly one newline
+    // 4. Update the proposal again and check that the file is unchanged
+    #[test]
+    fn test_write_single_trailing_newline() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_write_single_trailing_newline");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        store
+            .update_proposal(create_expected_proposal())
+            .expect("Unable to update proposal");
+        let first_write = fs::read(&proposals_path).expect("Unable to read proposal file");
+
+        assert!(first_write.ends_with(b"\n"));
+        assert!(!first_write.ends_with(b"\n\n"));
+
+        store
+            .update_proposal(create_expected_proposal())
+            .expect("Unable to update proposal");
+        let second_write = fs::read(&proposals_path).expect("Unable to read proposal file");
+
+        assert_eq!(first_write, second_write);
+    }
+
+    // Test that the state files end with a newline only if their format has a trailing newline
+    //
+    // 1. For each file format, create a store in an empty temp directory
+    // 2. Add a proposal so the proposal state file is written
+    // 3. Check that YAML files end with exactly one newline and JSON files end with the closing
+    //    brace of the document
+    #[test]
+    fn test_trailing_newline_by_file_format() {
+        for file_format in [FileFormat::Yaml, FileFormat::Json].iter() {
+            // create temp dir
+            let temp_dir = TempDir::new("test_trailing_newline_by_file_format")
+                .expect("Failed to create temp dir");
+            let circuit_path = temp_dir
+                .path()
+                .join("circuits")
+                .to_str()
+                .expect("Failed to get path")
+                .to_string();
+
+            let proposals_path = temp_dir
+                .path()
+                .join("circuit_proposals")
+                .to_str()
+                .expect("Failed to get path")
+                .to_string();
+
+            let store = store_builder(&circuit_path, &proposals_path)
+                .with_file_format(*file_format)
+                .build()
+                .expect("Unable to create yaml admin store");
+
+            store
+                .add_proposal(new_proposal())
+                .expect("Unable to add proposal");
+
+            let circuit_contents = fs::read(&circuit_path).expect("Unable to read circuit file");
+            let proposal_contents =
+                fs::read(&proposals_path).expect("Unable to read proposal file");
+
+            for contents in [circuit_contents, proposal_contents].iter() {
+                if file_format.trailing_newline() {
+                    assert!(contents.ends_with(b"\n"));
+                    assert!(!contents.ends_with(b"\n\n"));
+                } else {
+                    assert!(contents.ends_with(b"}"));
+                }
+            }
+        }
+    }
+
+    // Test that ensure_node only adds a node that does not already exist and only writes the
+    // circuit state file when it does
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Ensure a new node exists and check that it was added and written to the file
+    // 3. Replace the circuit state file contents
+    // 4. Ensure the same node exists and check that it was not added and that the file was not
+    //    written
+    #[test]
+    fn test_ensure_node() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_ensure_node");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let (_, node) = new_circuit();
+
+        assert!(store
+            .ensure_node(node.clone())
+            .expect("Unable to ensure node"));
+        assert_eq!(store.fetch_node(&node.id).unwrap(), Some(node.clone()));
+        assert!(fs::read_to_string(&circuit_path)
+            .expect("Unable to read circuit file")
+            .contains(&node.id));
+
+        write_file(b"not written", &circuit_path);
+
+        assert!(!store.ensure_node(node).expect("Unable to ensure node"));
+        assert_eq!(
+            fs::read(&circuit_path).expect("Unable to read circuit file"),
+            b"not written"
+        );
+    }
+
+    // Test the circuit, proposal, node and service operations with YAML state files
+    #[test]
+    fn test_crud_yaml_file_format() {
+        check_file_format_crud(FileFormat::Yaml, "test_crud_yaml_file_format");
+    }
+
+    // Test the circuit, proposal, node and service operations with JSON state files
+    #[test]
+    fn test_crud_json_file_format() {
+        check_file_format_crud(FileFormat::Json, "test_crud_json_file_format");
+    }
+
+    // Runs the circuit, proposal, node and service operations against a store using the given
+    // file format
+    //
+    // 1. Create a store with the file format in an empty temp directory
+    // 2. Add a circuit and a proposal, update them, and check they can be fetched
+    // 3. Check that the state files are written in the file format
+    // 4. Create a new store from the state files and check that it has the same state
+    // 5. Remove the circuit and proposal and check that a new store from the state files is empty
+    fn check_file_format_crud(file_format: FileFormat, test_name: &str) {
+        // create temp dir
+        let temp_dir = TempDir::new(test_name).expect("Failed to create temp dir");
+        let circuit_path = temp_dir
+            .path()
+            .join("circuits")
+            .to_str()
+            .expect("Failed to get path")
+            .to_string();
+
+        let proposals_path = temp_dir
+            .path()
+            .join("circuit_proposals")
+            .to_str()
+            .expect("Failed to get path")
+            .to_string();
+
+        let build_store = || {
+            store_builder(&circuit_path, &proposals_path)
+                .with_file_format(file_format)
+                .build()
+                .expect("Unable to create yaml admin store")
+        };
+
+        let store = build_store();
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit.clone(), vec![node.clone()])
+            .expect("Unable to add circuit");
+
+        let mut circuit = store
+            .fetch_circuit(&circuit.id)
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+        circuit.circuit_management_type = "test".to_string();
+        store
+            .update_circuit(circuit.clone())
+            .expect("Unable to update circuit");
+        circuit.updated_at = store
+            .fetch_circuit(&circuit.id)
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none")
+            .updated_at;
+
+        let mut proposal = new_proposal();
+        store
+            .add_proposal(proposal.clone())
+            .expect("Unable to add proposal");
+        proposal.circuit.comments = "updated".to_string();
+        store
+            .update_proposal(proposal.clone())
+            .expect("Unable to update proposal");
+
+        assert_eq!(
+            store.fetch_circuit(&circuit.id).unwrap(),
+            Some(circuit.clone())
+        );
+        assert_eq!(
+            store.fetch_proposal(&proposal.circuit_id).unwrap(),
+            Some(proposal.clone())
+        );
+        assert_eq!(store.fetch_node(&node.id).unwrap(), Some(node.clone()));
+        assert_eq!(
+            store
+                .fetch_service(&ServiceId::new(
+                    circuit.roster[0].service_id.to_string(),
+                    circuit.id.to_string()
+                ))
+                .unwrap(),
+            Some(circuit.roster[0].clone())
+        );
+
+        let circuit_contents = fs::read(&circuit_path).expect("Unable to read circuit file");
+        let proposal_contents = fs::read(&proposals_path).expect("Unable to read proposal file");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&circuit_contents).is_ok(),
+            file_format == FileFormat::Json
+        );
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&proposal_contents).is_ok(),
+            file_format == FileFormat::Json
+        );
+
+        // service arguments are stored as a map, so they are read back sorted by key
+        for service in circuit.roster.iter_mut() {
+            service.arguments.sort();
+        }
+
+        let store = build_store();
+
+        assert_eq!(
+            store.list_circuits(&[]).unwrap().collect::<Vec<_>>(),
+            vec![circuit.clone()]
+        );
+        assert_eq!(
+            store.list_proposals(&[]).unwrap().collect::<Vec<_>>(),
+            vec![proposal.clone()]
+        );
+        assert_eq!(store.list_nodes().unwrap().collect::<Vec<_>>(), vec![node]);
+
+        store
+            .remove_circuit(&circuit.id)
+            .expect("Unable to remove circuit");
+        store
+            .remove_proposal(&proposal.circuit_id)
+            .expect("Unable to remove proposal");
+
+        let store = build_store();
+
+        assert_eq!(store.list_circuits(&[]).unwrap().len(), 0);
+        assert_eq!(store.list_proposals(&[]).unwrap().len(), 0);
+    }
+
+    // Test that upgrading a proposal to a circuit that already exists is rejected
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Add a circuit with the same ID as the existing proposal
+    // 3. Check that upgrading the proposal returns an error
+    // 4. Check that the proposal and the existing circuit are unchanged, in the store and in the
+    //    state files
+    #[test]
+    fn test_upgrade_existing_circuit() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_upgrade_existing_circuit");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let mut circuit = create_expected_circuit();
+        circuit.id = "WBKLF-BBBBB".to_string();
+        store
+            .add_circuit(circuit, vec![])
+            .expect("Unable to add circuit");
+        let circuit = store
+            .fetch_circuit("WBKLF-BBBBB")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+
+        match store.upgrade_proposal_to_circuit("WBKLF-BBBBB") {
+            Err(AdminServiceStoreError::OperationError { .. }) => (),
+            res => panic!("Expected OperationError, got {:?}", res),
+        }
+
+        assert_eq!(
+            store.fetch_proposal("WBKLF-BBBBB").unwrap(),
+            Some(create_expected_proposal())
+        );
+        assert_eq!(
+            store.fetch_circuit("WBKLF-BBBBB").unwrap(),
+            Some(circuit.clone())
+        );
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store.fetch_proposal("WBKLF-BBBBB").unwrap(),
+            Some(create_expected_proposal())
+        );
+        assert_eq!(store.fetch_circuit("WBKLF-BBBBB").unwrap(), Some(circuit));
+    }
+
+    // Test that rename_circuit moves a circuit and its services to the new ID
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Check that renaming to an existing ID or from a missing ID fails
+    // 3. Rename the existing circuit
+    // 4. Check that the circuit and its services are only found under the new ID, in the store
+    //    and in the state file
+    #[test]
+    fn test_rename_circuit() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_rename_circuit");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let (new_circuit, new_node) = new_circuit();
+        store
+            .add_circuit(new_circuit, vec![new_node])
+            .expect("Unable to add circuit");
+
+        assert!(store.rename_circuit("WBKLF-AAAAA", "WBKLF-DDDDD").is_err());
+        assert!(store.rename_circuit("WBKLF-ZZZZZ", "WBKLF-YYYYY").is_err());
+
+        store
+            .rename_circuit("WBKLF-AAAAA", "WBKLF-RENAMED")
+            .expect("Unable to rename circuit");
+
+        let mut expected_circuit = create_expected_circuit();
+        expected_circuit.id = "WBKLF-RENAMED".to_string();
+        expected_circuit.updated_at = store
+            .fetch_circuit("WBKLF-RENAMED")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none")
+            .updated_at;
+        assert!(expected_circuit.updated_at > expected_circuit.created_at);
+        let expected_service = expected_circuit.roster[0].clone();
+
+        let check_store = |store: &YamlAdminServiceStore| {
+            assert_eq!(store.fetch_circuit("WBKLF-AAAAA").unwrap(), None);
+            assert_eq!(
+                store.fetch_circuit("WBKLF-RENAMED").unwrap(),
+                Some(expected_circuit.clone())
+            );
+            assert_eq!(
+                store
+                    .fetch_service(&ServiceId::new(
+                        "a000".to_string(),
+                        "WBKLF-AAAAA".to_string()
+                    ))
+                    .unwrap(),
+                None
+            );
+            assert_eq!(
+                store
+                    .fetch_service(&ServiceId::new(
+                        "a000".to_string(),
+                        "WBKLF-RENAMED".to_string()
+                    ))
+                    .unwrap(),
+                Some(expected_service.clone())
+            );
+        };
+
+        check_store(&store);
+        check_store(
+            &YamlAdminServiceStore::new(circuit_path, proposals_path)
+                .expect("Unable to create yaml admin store")
+                .0,
+        );
+    }
+
+    // Test that creating a store reports which state files existed
+    //
+    // 1. Create a store in an empty temp directory and check that it was created empty
+    // 2. Create a store from the files written in step 1 and check that they were loaded
+    // 3. Remove the proposal file, create a store and check that it was partially loaded
+    #[test]
+    fn test_init_outcome() {
+        let (_temp_dir, circuit_path, proposals_path) = state_file_paths("test_init_outcome");
+
+        let (_, init_outcome) =
+            YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+                .expect("Unable to create yaml admin store");
+        assert_eq!(init_outcome, InitOutcome::CreatedEmpty);
+
+        let (store, init_outcome) =
+            YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+                .expect("Unable to create yaml admin store");
+        assert_eq!(init_outcome, InitOutcome::LoadedExisting);
+        assert_eq!(store.init_outcome(), InitOutcome::LoadedExisting);
+
+        fs::remove_file(&proposals_path).expect("Unable to remove proposal file");
+
+        let (_, init_outcome) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert_eq!(init_outcome, InitOutcome::PartiallyLoaded);
+    }
+
+    // Test that proposal application metadata that is not valid UTF-8 is written and read back
+    // without changes
+    //
+    // 1. Setup the temp directory with existing state, which has empty application metadata
+    // 2. Add a proposal with binary application metadata
+    // 3. Check that the metadata is written to the proposal file as hex
+    // 4. Create a new store from the state files and check that both proposals are unchanged
+    #[test]
+    fn test_binary_application_metadata() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_binary_application_metadata");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let mut proposal = new_proposal();
+        proposal.circuit.application_metadata = vec![0x00, 0xff, 0xfe, 0x80, 0xc3];
+        assert!(String::from_utf8(proposal.circuit.application_metadata.clone()).is_err());
+
+        store
+            .add_proposal(proposal.clone())
+            .expect("Unable to add proposal");
+
+        assert!(fs::read_to_string(&proposals_path)
+            .expect("Unable to read proposal file")
+            .contains("application_metadata: 00fffe80c3"));
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store.fetch_proposal("WBKLF-BBBBB").unwrap(),
+            Some(create_expected_proposal())
+        );
+        assert_eq!(store.fetch_proposal("WBKLF-CCCCC").unwrap(), Some(proposal));
+    }
+
+    // Test that list_circuits_modified_since only returns circuits updated after the given time
+    //
+    // 1. Setup the temp directory with existing state and add a second circuit
+    // 2. Record the current time and update the second circuit
+    // 3. Check that only the updated circuit is returned for the recorded time
+    // 4. Check that both circuits are returned for an earlier time
+    #[test]
+    fn test_list_circuits_modified_since() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_list_circuits_modified_since");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let (new_circuit, new_node) = new_circuit();
+        store
+            .add_circuit(new_circuit, vec![new_node])
+            .expect("Unable to add circuit");
+        let mut new_circuit = store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+
+        let since = SystemTime::now();
+        std::thread::sleep(Duration::from_millis(10));
+
+        new_circuit.circuit_management_type = "updated".to_string();
+        store
+            .update_circuit(new_circuit)
+            .expect("Unable to update circuit");
+
+        let modified = store
+            .list_circuits_modified_since(since)
+            .expect("Unable to list circuits");
+        assert_eq!(
+            modified
+                .iter()
+                .map(|circuit| circuit.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["WBKLF-DDDDD"]
+        );
+        assert_eq!(modified[0].circuit_management_type, "updated");
+
+        assert_eq!(
+            store
+                .list_circuits_modified_since(UNIX_EPOCH)
+                .expect("Unable to list circuits")
+                .len(),
+            2
+        );
+    }
+
+    // Test that add_proposal applies the store's proposal conflict policy when a proposal with the
+    // same ID exists
+    //
+    // 1. For each policy, setup a temp directory with existing state
+    // 2. Add a proposal identical to the existing proposal and check the result
+    // 3. Add a proposal that differs from the existing proposal and check the result
+    // 4. Check the proposal in the store and in the proposal state file
+    #[test]
+    fn test_proposal_conflict_policy() {
+        let mut differing_proposal = create_expected_proposal();
+        differing_proposal.circuit.comments = "differing".to_string();
+
+        for (policy, identical_ok, differing_ok) in &[
+            (ProposalConflictPolicy::Strict, false, false),
+            (ProposalConflictPolicy::UpsertIfIdentical, true, false),
+            (ProposalConflictPolicy::Overwrite, true, true),
+        ] {
+            let (_temp_dir, circuit_path, proposals_path) =
+                setup_state_files("test_proposal_conflict_policy");
+
+            let store = store_builder(&circuit_path, &proposals_path)
+                .with_proposal_conflict_policy(*policy)
+                .build()
+                .expect("Unable to create yaml admin store");
+
+            assert_eq!(
+                store.add_proposal(create_expected_proposal()).is_ok(),
+                *identical_ok,
+                "Unexpected result adding an identical proposal with {:?}",
+                policy
+            );
+            assert_eq!(
+                store.add_proposal(differing_proposal.clone()).is_ok(),
+                *differing_ok,
+                "Unexpected result adding a differing proposal with {:?}",
+                policy
+            );
+
+            let expected_proposal = if *differing_ok {
+                differing_proposal.clone()
+            } else {
+                create_expected_proposal()
+            };
+
+            assert_eq!(
+                store.fetch_proposal("WBKLF-BBBBB").unwrap(),
+                Some(expected_proposal.clone())
+            );
+
+            let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+                .expect("Unable to create yaml admin store");
+            assert_eq!(
+                store.fetch_proposal("WBKLF-BBBBB").unwrap(),
+                Some(expected_proposal)
+            );
+        }
+    }
+
+    // Test that with_state gives access to the cached circuit and proposal state
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Count the services across all circuits and proposals with a single call to with_state
+    // 3. Check the count matches the sample state
+    #[test]
+    fn test_with_state() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_with_state");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let (node_count, service_count) = store
+            .with_state(|circuit_state, proposal_state| {
+                let circuit_services: usize = circuit_state
+                    .circuits()
+                    .values()
+                    .map(|circuit| circuit.roster.len())
+                    .sum();
+                let proposal_services: usize = proposal_state
+                    .proposals()
+                    .values()
+                    .map(|proposal| proposal.circuit.roster.len())
+                    .sum();
+                (
+                    circuit_state.nodes().len(),
+                    circuit_services + proposal_services,
+                )
+            })
+            .expect("Unable to read state");
+
+        assert_eq!(node_count, 2);
+        assert_eq!(service_count, 4);
+    }
+
+    // Test that add_circuit applies the store's node conflict policy when a node already exists
+    // with different endpoints
+    //
+    // 1. For each policy, setup a temp directory with existing state
+    // 2. Add a circuit with an existing node ID and changed endpoints
+    // 3. With the warn policy, check that the circuit was added and the existing node is unchanged
+    // 4. With the error policy, check that an error is returned and the circuit was not added
+    #[test]
+    fn test_node_conflict_policy() {
+        for policy in &[NodeConflictPolicy::Warn, NodeConflictPolicy::Error] {
+            let (_temp_dir, circuit_path, proposals_path) =
+                setup_state_files("test_node_conflict_policy");
+
+            let store = store_builder(&circuit_path, &proposals_path)
+                .with_node_conflict_policy(*policy)
+                .build()
+                .expect("Unable to create yaml admin store");
+
+            let existing_node = store
+                .fetch_node("acme-node-000")
+                .expect("Unable to fetch node")
+                .expect("Expected node, got none");
+
+            let (circuit, _) = new_circuit();
+            let changed_node = CircuitNode {
+                id: "acme-node-000".to_string(),
+                endpoints: vec!["tcps://splinterd-node-acme-new:8044".into()],
+            };
+
+            let result = store.add_circuit(circuit, vec![changed_node]);
+
+            match policy {
+                NodeConflictPolicy::Warn => {
+                    result.expect("Unable to add circuit");
+                    assert!(store.fetch_circuit("WBKLF-DDDDD").unwrap().is_some());
+                }
+                NodeConflictPolicy::Error => {
+                    assert!(result.is_err());
+                    assert!(store.fetch_circuit("WBKLF-DDDDD").unwrap().is_none());
+                }
+            }
+
+            assert_eq!(
+                store.fetch_node("acme-node-000").unwrap(),
+                Some(existing_node)
+            );
+        }
+    }
+
+    // Test that update_circuit applies the store's membership policy when a member that a service
+    // is still allowed on is removed
+    //
+    // 1. For each policy, setup a temp directory with existing state
+    // 2. Update the existing circuit to remove bubba-node-000, which service a001 is allowed on
+    // 3. With the warn policy, check that the circuit was updated
+    // 4. With the error policy, check that an error is returned and the circuit is unchanged
+    #[test]
+    fn test_membership_policy() {
+        for policy in &[MembershipPolicy::Warn, MembershipPolicy::Error] {
+            let (_temp_dir, circuit_path, proposals_path) =
+                setup_state_files("test_membership_policy");
+
+            let store = store_builder(&circuit_path, &proposals_path)
+                .with_membership_policy(*policy)
+                .build()
+                .expect("Unable to create yaml admin store");
+
+            let mut circuit = store
+                .fetch_circuit("WBKLF-AAAAA")
+                .expect("Unable to fetch circuit")
+                .expect("Expected circuit, got none");
+            circuit.members = vec!["acme-node-000".to_string()];
+
+            let result = store.update_circuit(circuit);
+
+            let members = store
+                .fetch_circuit("WBKLF-AAAAA")
+                .expect("Unable to fetch circuit")
+                .expect("Expected circuit, got none")
+                .members;
+
+            match policy {
+                MembershipPolicy::Warn => {
+                    result.expect("Unable to update circuit");
+                    assert_eq!(members, vec!["acme-node-000".to_string()]);
+                }
+                MembershipPolicy::Error => {
+                    match result {
+                        Err(AdminServiceStoreError::OperationError { context, .. }) => {
+                            assert!(context.contains("bubba-node-000"))
+                        }
+                        res => panic!("Expected an operation error, got {:?}", res),
+                    }
+                    assert_eq!(
+                        members,
+                        vec!["bubba-node-000".to_string(), "acme-node-000".to_string()]
+                    );
+                }
+            }
+        }
+    }
+
+    // Test that a circuit rejected by a circuit validator is not written
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Create a store with a validator that only allows circuits with two members
+    // 3. Add a circuit with three members and check that it is rejected with the reason
+    // 4. Update the existing circuit, which has two members, and check that it is accepted
+    #[test]
+    fn test_circuit_validator() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_circuit_validator");
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_circuit_validator(Box::new(|circuit: &Circuit| {
+                if circuit.members.len() == 2 {
+                    Ok(())
+                } else {
+                    Err("circuits must have exactly two members".to_string())
+                }
+            }))
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        let (circuit, node) = new_circuit();
+        match store.add_circuit(circuit, vec![node]) {
+            Err(AdminServiceStoreError::OperationError { context, .. }) => {
+                assert!(context.contains("circuits must have exactly two members"))
+            }
+            res => panic!("Expected an operation error, got {:?}", res),
+        }
+        assert!(store.fetch_circuit("WBKLF-DDDDD").unwrap().is_none());
+
+        let mut circuit = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+        circuit.circuit_management_type = "updated".to_string();
+        store
+            .update_circuit(circuit)
+            .expect("Unable to update circuit");
+    }
+
+    // Test that upsert_circuit applies the circuit validators to both new and existing circuits,
+    // leaving the store unchanged when a circuit is rejected
+    //
+    // 1. Create a store with existing circuit state and a validator that only allows circuits
+    //    with two members
+    // 2. Upsert a new circuit with three members and check that it is rejected with the reason
+    // 3. Upsert the existing circuit with a third member and check that it is rejected
+    // 4. Verify the store's state and the circuit state file are unchanged
+    #[test]
+    fn test_upsert_circuit_validator() {
+        let file_store = Arc::new(MemFileStore::new());
+        file_store
+            .write(Path::new("/state/circuits.yaml"), CIRCUIT_STATE)
+            .expect("Unable to write circuit state");
+
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/state/circuits.yaml")
+            .with_proposal_file_path("/state/circuit_proposals.yaml")
+            .with_file_store(file_store.clone())
+            .with_circuit_validator(Box::new(|circuit: &Circuit| {
+                if circuit.members.len() == 2 {
+                    Ok(())
+                } else {
+                    Err("circuits must have exactly two members".to_string())
+                }
+            }))
+            .build()
+            .expect("Unable to create yaml admin store");
+        let original = store.shared_state().expect("Unable to get state");
+
+        let (circuit, node) = new_circuit();
+        match store.upsert_circuit(circuit, vec![node.clone()]) {
+            Err(AdminServiceStoreError::OperationError { context, .. }) => {
+                assert!(context.contains("circuits must have exactly two members"))
+            }
+            res => panic!("Expected an operation error, got {:?}", res),
+        }
+
+        let mut existing = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+        existing.members.push(node.id.to_string());
+        assert!(store.upsert_circuit(existing, vec![node]).is_err());
+
+        assert!(Arc::ptr_eq(
+            &store.shared_state().expect("Unable to get state"),
+            &original
+        ));
+        assert_eq!(
+            file_store
+                .read(Path::new("/state/circuits.yaml"))
+                .expect("Unable to read circuit state"),
+            CIRCUIT_STATE
+        );
+    }
+
+    // Test that list_circuits_with_pending_proposals pairs each circuit with the proposal with
+    // the same ID
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Add a circuit with the same ID as the existing proposal
+    // 3. Check that the existing circuit has no proposal and the new circuit has the proposal
+    #[test]
+    fn test_list_circuits_with_pending_proposals() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_list_circuits_with_pending_proposals");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let mut circuit = create_expected_circuit();
+        circuit.id = "WBKLF-BBBBB".to_string();
+        store
+            .add_circuit(circuit, vec![])
+            .expect("Unable to add circuit");
+
+        let pairs = store
+            .list_circuits_with_pending_proposals()
+            .expect("Unable to list circuits");
+
+        assert_eq!(
+            pairs
+                .iter()
+                .map(|(circuit, proposal)| (circuit.id.as_str(), proposal.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("WBKLF-AAAAA", None),
+                ("WBKLF-BBBBB", Some(create_expected_proposal())),
+            ]
+        );
+    }
+
+    // Test that a store created from bytes has the parsed state and does not write files
+    //
+    // 1. Create a store from the sample circuit and proposal state
+    // 2. Check that the existing circuit, proposal, node and service can be fetched
+    // 3. Add a circuit and check that it can be fetched
+    // 4. Check that the store cannot be reloaded
+    #[test]
+    fn test_from_bytes() {
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store.fetch_circuit("WBKLF-AAAAA").unwrap(),
+            Some(create_expected_circuit())
+        );
+        assert_eq!(
+            store.fetch_proposal("WBKLF-BBBBB").unwrap(),
+            Some(create_expected_proposal())
+        );
+        assert!(store.fetch_node("acme-node-000").unwrap().is_some());
+        assert_eq!(
+            store
+                .fetch_service(&ServiceId::new(
+                    "a000".to_string(),
+                    "WBKLF-AAAAA".to_string()
+                ))
+                .unwrap(),
+            Some(create_expected_circuit().roster[0].clone())
+        );
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+        assert!(store.fetch_circuit("WBKLF-DDDDD").unwrap().is_some());
+
+        assert!(store.reload().is_err());
+        assert!(YamlAdminServiceStore::from_bytes(b"not: [valid", PROPOSAL_STATE).is_err());
+    }
+
+    // Test that reset removes all state from the store and the state files
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Reset the store
+    // 3. Check that there are no circuits, proposals, nodes or services
+    // 4. Check that the state files contain empty maps
+    #[test]
+    fn test_reset() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_reset");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        store.reset().expect("Unable to reset store");
+
+        assert_eq!(store.list_circuits(&[]).unwrap().len(), 0);
+        assert_eq!(store.list_proposals(&[]).unwrap().len(), 0);
+        assert_eq!(store.list_nodes().unwrap().len(), 0);
+        assert!(!store
+            .service_exists(&ServiceId::new(
+                "a000".to_string(),
+                "WBKLF-AAAAA".to_string()
+            ))
+            .unwrap());
+
+        let read_yaml = |path: &str| -> serde_yaml::Value {
+            serde_yaml::from_slice(&fs::read(path).expect("Unable to read state file"))
+                .expect("Unable to parse state file")
+        };
+        let empty_map = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+
+        let circuit_yaml = read_yaml(&circuit_path);
+        assert_eq!(circuit_yaml["circuits"], empty_map);
+        assert_eq!(circuit_yaml["nodes"], empty_map);
+        assert_eq!(read_yaml(&proposals_path)["proposals"], empty_map);
+    }
+
+    // Test that swapping the circuit and proposal file paths returns an error suggesting the paths
+    // may be swapped
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Create a store with the proposal file as the circuit file and vice versa
+    // 3. Check that the error names the circuit file and suggests the paths are swapped
+    // 4. Create a store with the circuit file as both paths
+    // 5. Check that the error names the proposal file and suggests the paths are swapped
+    #[test]
+    fn test_swapped_paths() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_swapped_paths");
+
+        let err = match YamlAdminServiceStore::new(proposals_path.clone(), circuit_path.clone()) {
+            Ok(_) => panic!("Expected an error creating the store"),
+            Err(err) => err.to_string(),
+        };
+        assert!(err.contains(&format!("circuit state file '{}'", proposals_path)));
+        assert!(err.contains("may be swapped"));
+
+        let err = match YamlAdminServiceStore::new(circuit_path.clone(), circuit_path.clone()) {
+            Ok(_) => panic!("Expected an error creating the store"),
+            Err(err) => err.to_string(),
+        };
+        assert!(err.contains(&format!("proposal state file '{}'", circuit_path)));
+        assert!(err.contains("may be swapped"));
+    }
+
+    // Test that a circuit can be disbanded
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Add a second circuit and disband the first
+    // 3. Verify the disbanded circuit can still be fetched and has the disbanded status
+    // 4. Verify listing with a status predicate excludes the disbanded circuit
+    // 5. Verify the status is persisted by reloading the store from the files
+    // 6. Verify disbanding a circuit that does not exist returns an error
+    #[test]
+    fn test_disband_circuit() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_disband_circuit");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let (new_circuit, new_node) = new_circuit();
+        store
+            .add_circuit(new_circuit, vec![new_node])
+            .expect("Unable to add circuit");
+
+        store
+            .disband_circuit("WBKLF-AAAAA")
+            .expect("Unable to disband circuit");
+
+        let disbanded = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+        assert_eq!(disbanded.circuit_status(), &CircuitStatus::Disbanded);
+        assert!(disbanded.updated_at() > disbanded.created_at());
+
+        let active: Vec<String> = store
+            .list_circuits(&[CircuitPredicate::Status(CircuitStatus::Active)])
+            .expect("Unable to list circuits")
+            .map(|circuit| circuit.id)
+            .collect();
+        assert_eq!(active, vec!["WBKLF-DDDDD".to_string()]);
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert_eq!(
+            store
+                .fetch_circuit("WBKLF-AAAAA")
+                .expect("Unable to fetch circuit"),
+            Some(disbanded)
+        );
+
+        assert!(store.disband_circuit("WBKLF-ZZZZZ").is_err());
+    }
+
+    // Test that circuits and proposals can be listed with a status predicate
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Add a second circuit and a proposal, then disband the first circuit
+    // 3. Verify listing active circuits returns only the new circuit
+    // 4. Verify listing disbanded circuits returns only the disbanded circuit
+    // 5. Verify a status predicate does not filter out any proposals
+    #[test]
+    fn test_list_circuits_by_status() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_list_circuits_by_status");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let (new_circuit, new_node) = new_circuit();
+        store
+            .add_circuit(new_circuit, vec![new_node])
+            .expect("Unable to add circuit");
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+        store
+            .disband_circuit("WBKLF-AAAAA")
+            .expect("Unable to disband circuit");
+
+        let list_ids = |status: CircuitStatus| -> Vec<String> {
+            store
+                .list_circuits(&[CircuitPredicate::Status(status)])
+                .expect("Unable to list circuits")
+                .map(|circuit| circuit.id)
+                .collect()
+        };
+
+        assert_eq!(
+            list_ids(CircuitStatus::Active),
+            vec!["WBKLF-DDDDD".to_string()]
+        );
+        assert_eq!(
+            list_ids(CircuitStatus::Disbanded),
+            vec!["WBKLF-AAAAA".to_string()]
+        );
+
+        let all_proposals: Vec<CircuitProposal> = store
+            .list_proposals(&[])
+            .expect("Unable to list proposals")
+            .collect();
+        assert_eq!(all_proposals.len(), 2);
+
+        for status in &[CircuitStatus::Active, CircuitStatus::Disbanded] {
+            let proposals: Vec<CircuitProposal> = store
+                .list_proposals(&[CircuitPredicate::Status(status.clone())])
+                .expect("Unable to list proposals")
+                .collect();
+            assert_eq!(proposals, all_proposals);
+        }
+    }
+
+    // Test that converting a YAML service reports fields that cannot be represented
+    //
+    // 1. Parse a YAML service with an extra `priority` field
+    // 2. Verify the conversion report flags the `priority` field as dropped
+    // 3. Verify a service without extra fields converts without loss
+    // 4. Verify circuit state with an extra service field loads, dropping the field
+    #[test]
+    fn test_service_conversion_report() {
+        let yaml_service: YamlService = serde_yaml::from_str(
+            "service_id: a000\n\
+             service_type: scabbard\n\
+             allowed_nodes:\n  - acme-node-000\n\
+             arguments:\n  peer_services: '[\"a001\"]'\n\
+             priority: high\n",
+        )
+        .expect("Unable to parse service");
+
+        let (service, report) = yaml_service.into_service_checked();
+        assert_eq!(service.service_id, "a000");
+        assert!(!report.is_lossless());
+        assert_eq!(report.dropped_fields, vec!["priority".to_string()]);
+
+        let (_, report) = YamlService::from(service).into_service_checked();
+        assert!(report.is_lossless());
+
+        let circuit_state = String::from_utf8(CIRCUIT_STATE.to_vec())
+            .expect("Circuit state is not valid UTF-8")
+            .replacen(
+                "            service_type: scabbard\n",
+                "            service_type: scabbard\n            priority: high\n",
+                1,
+            );
+        let circuit_state = CircuitState::try_from(circuit_state.as_bytes())
+            .expect("Unable to parse circuit state");
+        assert_eq!(
+            circuit_state.circuits["WBKLF-AAAAA"].roster[0].service_id,
+            "a000"
+        );
+    }
+
+    // Test that writes failing with a transient error are retried
+    //
+    // 1. Create a writer that fails twice with a transient error before succeeding
+    // 2. Verify the write succeeds when retried up to three times, after three attempts
+    // 3. Verify the write fails when it is only retried once
+    // 4. Verify a non-transient error is not retried
+    #[test]
+    fn test_retry_transient_write_errors() {
+        struct FlakyWriter {
+            failures: usize,
+            attempts: usize,
+            kind: io::ErrorKind,
+            written: Vec<u8>,
+        }
+
+        impl Write for FlakyWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.attempts += 1;
+                if self.attempts <= self.failures {
+                    return Err(io::Error::new(self.kind, "flaky write"));
+                }
+                self.written.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let flaky_writer = |kind| FlakyWriter {
+            failures: 2,
+            attempts: 0,
+            kind,
+            written: vec![],
+        };
+        let retry = |max_retries| WriteRetry {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+        };
+
+        let mut writer = flaky_writer(io::ErrorKind::WouldBlock);
+        retry_io(retry(3), || writer.write_all(b"state")).expect("Write was not retried");
+        assert_eq!(writer.attempts, 3);
+        assert_eq!(writer.written, b"state".to_vec());
+
+        let mut writer = flaky_writer(io::ErrorKind::TimedOut);
+        assert!(retry_io(retry(1), || writer.write_all(b"state")).is_err());
+        assert_eq!(writer.attempts, 2);
+
+        let mut writer = flaky_writer(io::ErrorKind::PermissionDenied);
+        assert!(retry_io(retry(3), || writer.write_all(b"state")).is_err());
+        assert_eq!(writer.attempts, 1);
+    }
+
+    // Test that the length of listed circuits matches the number of circuits yielded
+    //
+    // 1. Setup the temp directory with existing state and add a second circuit
+    // 2. For several sets of predicates, verify the iterator's `len` matches the number of
+    //    circuits it yields and that the `CircuitList` has the same length
+    // 3. Verify the `CircuitList` can be indexed in ID order
+    #[test]
+    fn test_list_circuits_len() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_list_circuits_len");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let (new_circuit, new_node) = new_circuit();
+        store
+            .add_circuit(new_circuit, vec![new_node])
+            .expect("Unable to add circuit");
+
+        let predicate_sets = vec![
+            (vec![], 2),
+            (
+                vec![CircuitPredicate::ManagmentTypeEq("gameroom".to_string())],
+                1,
+            ),
+            (
+                vec![CircuitPredicate::MembersInclude(vec![
+                    "new-node-000".to_string()
+                ])],
+                1,
+            ),
+            (
+                vec![CircuitPredicate::ManagmentTypeEq("unknown".to_string())],
+                0,
+            ),
+        ];
+
+        for (predicates, expected_len) in predicate_sets {
+            let circuits = store
+                .list_circuits(&predicates)
+                .expect("Unable to list circuits");
+            assert_eq!(circuits.len(), expected_len);
+            assert_eq!(circuits.count(), expected_len);
+
+            let circuit_list = store
+                .list_circuits_indexed(&predicates)
+                .expect("Unable to list circuits");
+            assert_eq!(circuit_list.len(), expected_len);
+            assert_eq!(circuit_list.is_empty(), expected_len == 0);
+            assert_eq!(circuit_list.iter().count(), expected_len);
+        }
+
+        let circuit_list = store
+            .list_circuits_indexed(&[])
+            .expect("Unable to list circuits");
+        assert_eq!(circuit_list[0].id, "WBKLF-AAAAA");
+        assert_eq!(circuit_list[1].id, "WBKLF-DDDDD");
+        assert_eq!(circuit_list.get(2), None);
+    }
+
+    // Test that upsert_circuit adds a missing circuit and updates an existing one
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Upsert a new circuit and verify it was inserted with its services
+    // 3. Upsert the circuit again with a changed roster and verify it was updated, keeping its
+    //    creation time
+    // 4. Verify the service directory contains the new roster and not the removed service
+    // 5. Verify the changes were written by reloading the store from the files
+    #[test]
+    fn test_upsert_circuit() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_upsert_circuit");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let (circuit, new_node) = new_circuit();
+        assert!(store
+            .upsert_circuit(circuit.clone(), vec![new_node.clone()])
+            .expect("Unable to upsert circuit"));
+        assert_eq!(
+            store
+                .fetch_node("new-node-000")
+                .expect("Unable to fetch node"),
+            Some(new_node.clone())
+        );
+        assert!(store
+            .fetch_service(&ServiceId::new(
+                "a001".to_string(),
+                "WBKLF-DDDDD".to_string()
+            ))
+            .expect("Unable to fetch service")
+            .is_some());
+        let created_at = store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none")
+            .created_at;
+
+        let mut updated_circuit = circuit;
+        updated_circuit.roster.truncate(1);
+        updated_circuit.roster[0].service_type = "updated".to_string();
+        assert!(!store
+            .upsert_circuit(updated_circuit.clone(), vec![new_node])
+            .expect("Unable to upsert circuit"));
+
+        let fetched = store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .expect("Expected circuit, got none");
+        assert_eq!(fetched.roster, updated_circuit.roster);
+        assert_eq!(fetched.created_at, created_at);
+
+        let check_services = |store: &YamlAdminServiceStore| {
+            assert_eq!(
+                store
+                    .fetch_service(&ServiceId::new(
+                        "a000".to_string(),
+                        "WBKLF-DDDDD".to_string()
+                    ))
+                    .expect("Unable to fetch service")
+                    .map(|service| service.service_type),
+                Some("updated".to_string())
+            );
+            assert_eq!(
+                store
+                    .fetch_service(&ServiceId::new(
+                        "a001".to_string(),
+                        "WBKLF-DDDDD".to_string()
+                    ))
+                    .expect("Unable to fetch service"),
+                None
+            );
+        };
+        check_services(&store);
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        check_services(&store);
+    }
+
+    // Test that circuits and proposals can be listed with predicates from any iterator
+    //
+    // 1. Setup the temp directory with existing state and add a second circuit and proposal
+    // 2. Verify listing circuits with a single predicate and with an iterator of predicates
+    //    returns the same circuits as the slice form
+    // 3. Verify the same for proposals
+    #[test]
+    fn test_list_matching_predicates() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_list_matching_predicates");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let (new_circuit, new_node) = new_circuit();
+        store
+            .add_circuit(new_circuit, vec![new_node])
+            .expect("Unable to add circuit");
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+
+        let management_type = CircuitPredicate::ManagmentTypeEq("gameroom".to_string());
+        let members = vec![
+            CircuitPredicate::MembersInclude(vec!["acme-node-000".to_string()]),
+            CircuitPredicate::ManagmentTypeEq("test".to_string()),
+        ];
+
+        let expected: Vec<Circuit> = store
+            .list_circuits(std::slice::from_ref(&management_type))
+            .expect("Unable to list circuits")
+            .collect();
+        assert_eq!(expected.len(), 1);
+        let circuits: Vec<Circuit> = store
+            .list_circuits_matching(Some(&management_type))
+            .expect("Unable to list circuits")
+            .collect();
+        assert_eq!(circuits, expected);
+
+        let expected: Vec<Circuit> = store
+            .list_circuits(&members)
+            .expect("Unable to list circuits")
+            .collect();
+        assert_eq!(expected.len(), 1);
+        let circuits: Vec<Circuit> = store
+            .list_circuits_matching(members.iter().filter(|_| true))
+            .expect("Unable to list circuits")
+            .collect();
+        assert_eq!(circuits, expected);
+
+        let expected: Vec<CircuitProposal> = store
+            .list_proposals(std::slice::from_ref(&management_type))
+            .expect("Unable to list proposals")
+            .collect();
+        assert_eq!(expected.len(), 1);
+        let proposals: Vec<CircuitProposal> = store
+            .list_proposals_matching(Some(&management_type))
+            .expect("Unable to list proposals")
+            .collect();
+        assert_eq!(proposals, expected);
+
+        let expected: Vec<CircuitProposal> = store
+            .list_proposals(&members)
+            .expect("Unable to list proposals")
+            .collect();
+        let proposals: Vec<CircuitProposal> = store
+            .list_proposals_matching(members.iter())
+            .expect("Unable to list proposals")
+            .collect();
+        assert_eq!(proposals, expected);
+    }
+
+    // Test that a proposal's circuit hash can be verified against its proposed circuit
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Add a proposal with the hash of its proposed circuit and verify the hash matches
+    // 3. Verify the existing proposal, whose hash was not computed from its circuit, does not
+    //    match
+    // 4. Verify a proposal that does not exist returns a not found error
+    // 5. Verify a proposal with challenge authorization returns an error
+    // 6. Verify the store can be loaded with hash verification enabled
+    #[test]
+    fn test_verify_proposal_hash() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_verify_proposal_hash");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let mut proposal = new_proposal();
+        proposal.circuit_hash =
+            proposed_circuit_hash(&proposal.circuit).expect("Unable to compute circuit hash");
+        assert_eq!(proposal.circuit_hash.len(), 64);
+        store
+            .add_proposal(proposal)
+            .expect("Unable to add proposal");
+
+        assert!(store
+            .verify_proposal_hash("WBKLF-CCCCC")
+            .expect("Unable to verify proposal hash"));
+        assert!(!store
+            .verify_proposal_hash("WBKLF-BBBBB")
+            .expect("Unable to verify proposal hash"));
+        assert!(matches!(
+            store.verify_proposal_hash("WBKLF-ZZZZZ"),
+            Err(AdminServiceStoreError::NotFoundError(_))
+        ));
+
+        let mut challenge_proposal = new_proposal();
+        challenge_proposal.circuit_id = "WBKLF-DDDDD".to_string();
+        challenge_proposal.circuit.circuit_id = "WBKLF-DDDDD".to_string();
+        challenge_proposal.circuit.authorization_type = AuthorizationType::Challenge;
+        store
+            .add_proposal(challenge_proposal)
+            .expect("Unable to add proposal");
+        assert!(matches!(
+            store.verify_proposal_hash("WBKLF-DDDDD"),
+            Err(AdminServiceStoreError::OperationError { .. })
+        ));
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_proposal_hash_verification(true)
+            .build()
+            .expect("Unable to create yaml admin store");
+        assert!(store
+            .verify_proposal_hash("WBKLF-CCCCC")
+            .expect("Unable to verify proposal hash"));
+    }
+
+    // Test that only proposals accepted by all of their members are upgradeable
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Add a proposal accepted by all of its members, a proposal missing only the requester's
+    //    vote, a proposal with no votes, and a proposal with a rejecting vote
+    // 3. Verify only the proposal accepted by all of its members is listed as upgradeable
+    // 4. Verify the progress of the proposal missing the requester's vote agrees that it is not
+    //    upgradeable
+    #[test]
+    fn test_list_upgradeable_proposals() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_list_upgradeable_proposals");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let vote = |vote, voter_node_id: &str| VoteRecord {
+            public_key: vec![1, 2, 3],
+            vote,
+            voter_node_id: voter_node_id.to_string(),
+        };
+
+        let mut accepted = new_proposal();
+        accepted.add_vote(vote(Vote::Accept, "bubba-node-000"));
+        accepted.add_vote(vote(Vote::Accept, "acme-node-000"));
+        store
+            .add_proposal(accepted.clone())
+            .expect("Unable to add proposal");
+
+        let mut missing_requester_vote = new_proposal();
+        missing_requester_vote.circuit_id = "WBKLF-GGGGG".to_string();
+        missing_requester_vote.add_vote(vote(Vote::Accept, "bubba-node-000"));
+        store
+            .add_proposal(missing_requester_vote)
+            .expect("Unable to add proposal");
+
+        let mut rejected = new_proposal();
+        rejected.circuit_id = "WBKLF-EEEEE".to_string();
+        rejected.add_vote(vote(Vote::Accept, "bubba-node-000"));
+        rejected.add_vote(vote(Vote::Reject, "acme-node-000"));
+        store
+            .add_proposal(rejected)
+            .expect("Unable to add proposal");
+
+        let mut unvoted = new_proposal();
+        unvoted.circuit_id = "WBKLF-FFFFF".to_string();
+        store.add_proposal(unvoted).expect("Unable to add proposal");
+
+        assert_eq!(
+            store
+                .list_upgradeable_proposals()
+                .expect("Unable to list upgradeable proposals"),
+            vec![accepted]
+        );
+        assert!(!store
+            .fetch_proposal_with_progress("WBKLF-GGGGG")
+            .expect("Unable to fetch proposal")
+            .expect("Proposal not found")
+            .progress()
+            .is_upgradeable());
+    }
+
+    // Test that service arguments can be read as a list of pairs
+    //
+    // 1. Write a circuit state file with one service whose arguments are a list of key-value
+    //    pairs, as in a proposal state file, and one whose arguments are a map
+    // 2. Create the store and verify both services have the expected arguments
+    #[test]
+    fn test_read_service_arguments_as_pairs() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            state_file_paths("test_read_service_arguments_as_pairs");
+
+        let circuit_state = String::from_utf8(CIRCUIT_STATE.to_vec())
+            .expect("Circuit state is not valid UTF-8")
+            .replace(
+                "            arguments:
+              admin_keys: '[\"035724d11cae47c8907f8bfdf510488f49df8494ff81b63825bad923733c4ac550\"]'
+              peer_services: '[\"a001\"]'",
+                "            arguments:
+              - - peer_services
+                - '[\"a001\"]'
+              - - admin_keys
+                - '[\"035724d11cae47c8907f8bfdf510488f49df8494ff81b63825bad923733c4ac550\"]'",
+            );
+        assert!(circuit_state.contains("- - peer_services"));
+
+        // write yaml files to temp_dir
+        write_file(circuit_state.as_bytes(), &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store
+                .fetch_circuit("WBKLF-AAAAA")
+                .expect("Unable to fetch circuit"),
+            Some(create_expected_circuit())
+        );
+    }
+
+    // Test that circuit members without a node entry are reported as missing
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Verify no member nodes are missing
+    // 3. Add a circuit without providing its new member's node
+    // 4. Verify the new member is reported as missing for the new circuit only
+    #[test]
+    fn test_missing_member_nodes() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_missing_member_nodes");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert!(store
+            .missing_member_nodes()
+            .expect("Unable to list missing member nodes")
+            .is_empty());
+
+        let (circuit, _) = new_circuit();
+        store
+            .add_circuit(circuit, vec![])
+            .expect("Unable to add circuit");
+
+        assert_eq!(
+            store
+                .missing_member_nodes()
+                .expect("Unable to list missing member nodes"),
+            vec![("WBKLF-DDDDD".to_string(), vec!["new-node-000".to_string()])]
+        );
+    }
+
+    // Test that the enum values of circuits and proposals are written in lowercase with the
+    // lowercase enum case, and that the files can be read back
+    //
+    // 1. Setup the temp directory with existing state, which has capitalized enum values
+    // 2. Create a store with the lowercase enum case and verify the existing state was loaded
+    // 3. Add a circuit and a proposal so the state files are written
+    // 4. Verify the state files have lowercase enum values
+    // 5. Create a new store with the default enum case from the files and verify it has the same
+    //    circuits and proposals
+    #[test]
+    fn test_lowercase_enum_case() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_lowercase_enum_case");
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_enum_case(EnumCase::Lowercase)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store
+                .fetch_circuit("WBKLF-AAAAA")
+                .expect("Unable to fetch circuit")
+                .expect("Circuit not found")
+                .auth,
+            AuthorizationType::Trust
+        );
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+
+        let circuit_file =
+            fs::read_to_string(&circuit_path).expect("Unable to read circuit state file");
+        assert!(circuit_file.contains("auth: trust"));
+        assert!(circuit_file.contains("durability: nodurability"));
+        assert!(!circuit_file.contains("Trust"));
+
+        let proposal_file =
+            fs::read_to_string(&proposals_path).expect("Unable to read proposal state file");
+        assert!(proposal_file.contains("authorization_type: trust"));
+        assert!(proposal_file.contains("routes: any"));
+        assert!(!proposal_file.contains("Trust"));
+
+        let (reloaded_store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        // creation times are written to the second, so compare the circuits' enum values
+        assert_eq!(
+            reloaded_store
+                .list_circuits(&[])
+                .expect("Unable to list circuits")
+                .map(|circuit| (circuit.id, circuit.auth, circuit.durability))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "WBKLF-AAAAA".to_string(),
+                    AuthorizationType::Trust,
+                    DurabilityType::NoDurability
+                ),
+                (
+                    "WBKLF-DDDDD".to_string(),
+                    AuthorizationType::Trust,
+                    DurabilityType::NoDurability
+                ),
+            ]
+        );
+        assert_eq!(
+            reloaded_store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .collect::<Vec<_>>(),
+            store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .collect::<Vec<_>>()
+        );
+    }
+
+    // Test that empty state files are read as empty state
+    //
+    // 1. Setup the temp directory with an empty circuit state file and existing proposal state
+    // 2. Create a store and verify it has no circuits or nodes, and still has the proposals
+    // 3. Empty the proposal state file and reload the store
+    // 4. Verify the store has no proposals
+    #[test]
+    fn test_zero_byte_state_file() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            state_file_paths("test_zero_byte_state_file");
+
+        // write yaml files to temp_dir
+        write_file(b"", &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store
+                .list_circuits(&[])
+                .expect("Unable to list circuits")
+                .count(),
+            0
+        );
+        assert_eq!(store.list_nodes().expect("Unable to list nodes").count(), 0);
+        assert_eq!(
+            store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .count(),
+            1
+        );
+
+        write_file(b"", &proposals_path);
+        store.reload().expect("Unable to reload store");
+
+        assert_eq!(
+            store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .count(),
+            0
+        );
+    }
+
+    // Test that paging through the nodes returns each node exactly once, in node ID order
+    //
+    // 1. Setup the temp directory with existing state and add five more nodes
+    // 2. Page through the nodes three at a time, checking the total on each page
+    // 3. Verify the pages together list every node once, in order
+    // 4. Verify a page past the end is empty
+    #[test]
+    fn test_list_nodes_paged() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_list_nodes_paged");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        for i in 0..5 {
+            store
+                .ensure_node(CircuitNode {
+                    id: format!("node-{:03}", i),
+                    endpoints: vec![format!("tcps://node-{:03}:8044", i)],
+                })
+                .expect("Unable to add node");
+        }
+
+        let mut paged_ids = vec![];
+        let mut offset = 0;
+        loop {
+            let (page, total) = store
+                .list_nodes_paged(offset, 3)
+                .expect("Unable to list nodes");
+            assert_eq!(total, 7);
+            if page.is_empty() {
+                break;
+            }
+
+            assert!(page.len() <= 3);
+            offset += page.len();
+            paged_ids.extend(page.into_iter().map(|node| node.id));
+        }
+
+        let expected_ids: Vec<String> = store
+            .list_nodes()
+            .expect("Unable to list nodes")
+            .map(|node| node.id)
+            .collect();
+        assert_eq!(expected_ids.len(), 7);
+        assert_eq!(paged_ids, expected_ids);
+
+        let (page, total) = store.list_nodes_paged(10, 3).expect("Unable to list nodes");
+        assert!(page.is_empty());
+        assert_eq!(total, 7);
+    }
+
+    // Test that a node is in use only if it is a member of a circuit
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Verify a member of the existing circuit is in use and an unknown node is not
+    // 3. Remove the circuit and verify its member is no longer in use
+    #[test]
+    fn test_node_in_use() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_node_in_use");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert!(store
+            .node_in_use("acme-node-000")
+            .expect("Unable to check node"));
+        assert!(!store
+            .node_in_use("unknown-node-000")
+            .expect("Unable to check node"));
+
+        store
+            .remove_circuit("WBKLF-AAAAA")
+            .expect("Unable to remove circuit");
+
+        assert!(!store
+            .node_in_use("acme-node-000")
+            .expect("Unable to check node"));
+    }
+
+    // Test that only the circuits hosting a service are listed for its service ID
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Verify the existing circuit is listed for its service a000
+    // 3. Verify no circuits are listed for an unknown service ID
+    #[test]
+    fn test_list_circuits_with_service() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_list_circuits_with_service");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store
+                .list_circuits_with_service("a000")
+                .expect("Unable to list circuits")
+                .into_iter()
+                .map(|circuit| circuit.id)
+                .collect::<Vec<_>>(),
+            vec!["WBKLF-AAAAA".to_string()]
+        );
+        assert!(store
+            .list_circuits_with_service("unknown")
+            .expect("Unable to list circuits")
+            .is_empty());
+    }
+
+    // Test that proposals are listed in the order they were submitted, and that the order is
+    // preserved across writes
+    //
+    // 1. Setup the temp directory with existing state, which has a proposal without a submission
+    //    sequence number
+    // 2. Add proposals out of ID order, then update the first one added
+    // 3. Verify the proposals are listed in ID order by list_proposals and in submission order by
+    //    list_proposals_by_submission_order, with the existing proposal first
+    // 4. Create a new store from the files and verify the submission order is the same
+    #[test]
+    fn test_list_proposals_by_submission_order() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_list_proposals_by_submission_order");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        for circuit_id in ["WBKLF-FFFFF", "WBKLF-CCCCC", "WBKLF-EEEEE"].iter() {
+            let mut proposal = new_proposal();
+            proposal.circuit_id = circuit_id.to_string();
+            store
+                .add_proposal(proposal)
+                .expect("Unable to add proposal");
+        }
+
+        let mut updated = new_proposal();
+        updated.circuit_id = "WBKLF-FFFFF".to_string();
+        updated.add_vote(VoteRecord {
+            public_key: vec![1, 2, 3],
+            vote: Vote::Accept,
+            voter_node_id: "bubba-node-000".to_string(),
+        });
+        store
+            .update_proposal(updated)
+            .expect("Unable to update proposal");
+
+        let ids = |proposals: Vec<CircuitProposal>| {
+            proposals
+                .into_iter()
+                .map(|proposal| proposal.circuit_id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ids(store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .collect()),
+            vec!["WBKLF-BBBBB", "WBKLF-CCCCC", "WBKLF-EEEEE", "WBKLF-FFFFF"]
+        );
+        assert_eq!(
+            ids(store
+                .list_proposals_by_submission_order()
+                .expect("Unable to list proposals")),
+            vec!["WBKLF-BBBBB", "WBKLF-FFFFF", "WBKLF-CCCCC", "WBKLF-EEEEE"]
+        );
+
+        let (reloaded_store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert_eq!(
+            ids(reloaded_store
+                .list_proposals_by_submission_order()
+                .expect("Unable to list proposals")),
+            vec!["WBKLF-BBBBB", "WBKLF-FFFFF", "WBKLF-CCCCC", "WBKLF-EEEEE"]
+        );
+    }
+
+    // Test that an error reading a state file carries the underlying error as its source, and can
+    // be sent between threads as required by error reporting libraries
+    //
+    // 1. Setup the temp directory with a circuit state file that is not valid YAML
+    // 2. Create a store and check that an error is returned
+    // 3. Walk the error's source chain and check that it includes the YAML parse error
+    #[test]
+    fn test_error_source_chain() {
+        fn assert_send_sync<T: Error + Send + Sync + 'static>(_: &T) {}
+
+        let (_temp_dir, circuit_path, proposals_path) = state_file_paths("test_error_source_chain");
+
+        // write yaml files to temp_dir
+        write_file(b"circuits: [", &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let err = match YamlAdminServiceStore::new(circuit_path, proposals_path) {
+            Err(err) => err,
+            Ok(_) => panic!("Expected an error creating the store"),
+        };
+        assert_send_sync(&err);
+
+        let mut chain = vec![];
+        let mut cause: &(dyn Error + 'static) = &err;
+        while let Some(source) = cause.source() {
+            chain.push(source);
+            cause = source;
+        }
+
+        let yaml_error = chain
+            .iter()
+            .find_map(|source| source.downcast_ref::<serde_yaml::Error>())
+            .expect("Expected a YAML error in the source chain");
+        assert!(err.to_string().contains(&yaml_error.to_string()));
+    }
+
+    // Test that circuit state is read from a reader without touching the filesystem
+    //
+    // 1. Create an in-memory store
+    // 2. Read the sample circuit state through the reader-based method
+    // 3. Verify the circuit and nodes are parsed, and the store's state is unchanged
+    #[test]
+    fn test_read_circuit_state_from() {
+        let store = YamlAdminServiceStore::from_bytes(b"nodes: {}\ncircuits: {}", b"proposals: {}")
+            .expect("Unable to create store");
+
+        let circuit_state = store
+            .read_circuit_state_from(CIRCUIT_STATE)
+            .expect("Unable to read circuit state");
+
+        assert_eq!(
+            circuit_state.circuits().get("WBKLF-AAAAA"),
+            Some(&create_expected_circuit())
+        );
+        assert_eq!(
+            circuit_state.nodes().keys().collect::<Vec<_>>(),
+            vec!["acme-node-000", "bubba-node-000"]
+        );
+        assert_eq!(store.list_circuits(&[]).unwrap().count(), 0);
+    }
+
+    // Test that proposal state is read from a reader without touching the filesystem
+    //
+    // 1. Create an in-memory store
+    // 2. Read the sample proposal state through the reader-based method
+    // 3. Verify the proposal is parsed and none were skipped
+    // 4. Verify reading invalid proposal state returns an error
+    #[test]
+    fn test_read_proposal_state_from() {
+        let store = YamlAdminServiceStore::from_bytes(b"nodes: {}\ncircuits: {}", b"proposals: {}")
+            .expect("Unable to create store");
+
+        let (proposal_state, skipped_proposals) = store
+            .read_proposal_state_from(PROPOSAL_STATE)
+            .expect("Unable to read proposal state");
+
+        assert_eq!(
+            proposal_state.proposals().get("WBKLF-BBBBB"),
+            Some(&create_expected_proposal())
+        );
+        assert!(skipped_proposals.is_empty());
+
+        assert!(store
+            .read_proposal_state_from(&b"proposals: ["[..])
+            .is_err());
+    }
+
+    // Test that relocating a store writes its state to the new files and that later changes are
+    // only written to the new files
+    //
+    // 1. Setup the temp directory with existing state, once keeping and once removing the old
+    //    files
+    // 2. Relocate the store to new paths in a subdirectory
+    // 3. Verify the new files contain the existing state
+    // 4. Add a circuit and verify it is written to the new circuit file
+    // 5. Verify the old files are unchanged, or removed if requested
+    #[test]
+    fn test_relocate() {
+        for remove_old_files in &[false, true] {
+            let (temp_dir, circuit_path, proposals_path) = setup_state_files("test_relocate");
+
+            let new_dir = temp_dir.path().join("new");
+            fs::create_dir(&new_dir).expect("Failed to create new dir");
+            let new_circuit_path = new_dir
+                .join("circuits.yaml")
+                .to_str()
+                .expect("Failed to get path")
+                .to_string();
+            let new_proposals_path = new_dir
+                .join("circuit_proposals.yaml")
+                .to_str()
+                .expect("Failed to get path")
+                .to_string();
+
+            let (store, _) =
+                YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+                    .expect("Unable to create yaml admin store");
+            let old_circuit_contents =
+                fs::read(&circuit_path).expect("Unable to read circuit file");
+            let old_proposal_contents =
+                fs::read(&proposals_path).expect("Unable to read proposal file");
+
+            store
+                .relocate(
+                    new_circuit_path.clone(),
+                    new_proposals_path.clone(),
+                    *remove_old_files,
+                )
+                .expect("Unable to relocate store");
+
+            let (relocated, _) =
+                YamlAdminServiceStore::new(new_circuit_path.clone(), new_proposals_path.clone())
+                    .expect("Unable to create yaml admin store from new files");
+            assert!(relocated
+                .fetch_circuit("WBKLF-AAAAA")
+                .expect("Unable to fetch circuit")
+                .is_some());
+            assert!(relocated
+                .fetch_proposal("WBKLF-BBBBB")
+                .expect("Unable to fetch proposal")
+                .is_some());
+
+            let (circuit, node) = new_circuit();
+            store
+                .add_circuit(circuit, vec![node])
+                .expect("Unable to add circuit");
+
+            let (relocated, _) =
+                YamlAdminServiceStore::new(new_circuit_path.clone(), new_proposals_path.clone())
+                    .expect("Unable to create yaml admin store from new files");
+            assert!(relocated
+                .fetch_circuit("WBKLF-DDDDD")
+                .expect("Unable to fetch circuit")
+                .is_some());
+
+            if *remove_old_files {
+                assert!(!PathBuf::from(&circuit_path).exists());
+                assert!(!PathBuf::from(&proposals_path).exists());
+            } else {
+                assert_eq!(
+                    fs::read(&circuit_path).expect("Unable to read circuit file"),
+                    old_circuit_contents
+                );
+                assert_eq!(
+                    fs::read(&proposals_path).expect("Unable to read proposal file"),
+                    old_proposal_contents
+                );
+            }
+        }
+    }
+
+    // Test that a circuit's display name is written to and read from the circuit state file
+    //
+    // 1. Setup the temp directory with existing state, which has no display names
+    // 2. Verify the existing circuit loads without a display name
+    // 3. Add a circuit with a display name and set a display name on the existing circuit
+    // 4. Reload the store from the files and verify both display names are read back
+    // 5. Clear the display name of the existing circuit and verify it is no longer written to
+    //    the file
+    #[test]
+    fn test_circuit_display_name() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_circuit_display_name");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+
+        let circuit = store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .expect("Circuit does not exist");
+        assert_eq!(circuit.display_name(), None);
+
+        let (circuit, node) = new_circuit();
+        let circuit = CircuitBuilder::default()
+            .with_circuit_id(&circuit.id)
+            .with_roster(&circuit.roster)
+            .with_members(&circuit.members)
+            .with_circuit_management_type(&circuit.circuit_management_type)
+            .with_display_name("Test Circuit")
+            .build()
+            .expect("Unable to build circuit");
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+        store
+            .set_circuit_display_name("WBKLF-AAAAA", Some("Gameroom".to_string()))
+            .expect("Unable to set display name");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path)
+            .expect("Unable to create yaml admin store");
+        let fetch_display_name = |circuit_id: &str| {
+            store
+                .fetch_circuit(circuit_id)
+                .expect("Unable to fetch circuit")
+                .expect("Circuit does not exist")
+                .display_name()
+                .map(str::to_string)
+        };
+        assert_eq!(
+            fetch_display_name("WBKLF-DDDDD").as_deref(),
+            Some("Test Circuit")
+        );
+        assert_eq!(
+            fetch_display_name("WBKLF-AAAAA").as_deref(),
+            Some("Gameroom")
+        );
+
+        store
+            .set_circuit_display_name("WBKLF-AAAAA", None)
+            .expect("Unable to clear display name");
+        assert_eq!(fetch_display_name("WBKLF-AAAAA"), None);
+
+        let circuit_contents =
+            fs::read_to_string(&circuit_path).expect("Unable to read circuit file");
+        assert_eq!(circuit_contents.matches("display_name").count(), 1);
+
+        assert!(store.set_circuit_display_name("WBKLF-ZZZZZ", None).is_err());
+    }
+
+    // Test that readers do not wait for writers and never observe a partly applied change
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Start reader threads that repeatedly check that every circuit member is a known node
+    // 3. Add circuits, each with a new member node, while the readers run
+    // 4. Hold the write lock and verify a circuit can still be fetched
+    // 5. Stop the readers and verify each of them completed reads
+    #[test]
+    fn test_concurrent_readers() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_concurrent_readers");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        let store = Arc::new(store);
+
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let readers = (0..8)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                let done = Arc::clone(&done);
+                std::thread::spawn(move || {
+                    let mut reads = 0;
+                    loop {
+                        store
+                            .with_state(|circuit_state, _| {
+                                for circuit in circuit_state.circuits().values() {
+                                    for member in circuit.members.iter() {
+                                        assert!(
+                                            circuit_state.nodes().contains_key(member),
+                                            "Circuit {} has unknown member {}",
+                                            circuit.id,
+                                            member
+                                        );
+                                    }
+                                }
+                            })
+                            .expect("Unable to read state");
+                        reads += 1;
+
+                        if done.load(std::sync::atomic::Ordering::SeqCst) {
+                            return reads;
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for i in 0..20 {
+            let (mut circuit, mut node) = new_circuit();
+            circuit.id = format!("WBKLF-C{:04}", i);
+            node.id = format!("new-node-{:03}", i);
+            circuit.members[2] = node.id.to_string();
+            store
+                .add_circuit(circuit, vec![node])
+                .expect("Unable to add circuit");
+        }
+
+        let guard = store.lock_state().expect("Unable to lock state");
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let reader_store = Arc::clone(&store);
+        std::thread::spawn(move || {
+            let fetched = reader_store
+                .fetch_circuit("WBKLF-C0019")
+                .expect("Unable to fetch circuit");
+            sender
+                .send(fetched.is_some())
+                .expect("Unable to send result");
+        });
+        assert!(receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Fetch was blocked by the write lock"));
+        drop(guard);
+
+        done.store(true, std::sync::atomic::Ordering::SeqCst);
+        for reader in readers {
+            assert!(reader.join().expect("Reader thread panicked") > 0);
+        }
+    }
+
+    // Test that the get methods return the node, circuit or proposal if it exists, and a
+    // NotFoundError if it does not
     //
-    // 1. Creates a empty temp directory
-    // 2. Create a YAML admin service directory
-    // 3. Validate that the circuit and proposals YAMLfiles were created in the temp dir.
+    // 1. Create a store from the existing state
+    // 2. Verify the existing node, circuit and proposal are returned
+    // 3. Verify each get method returns a NotFoundError for a missing ID
     #[test]
-    fn test_write_new_files() {
-        let temp_dir = TempDir::new("test_write_new_files").expect("Failed to create temp dir");
-        let circuit_path = temp_dir
-            .path()
-            .join("circuits.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+    fn test_get_or_not_found() {
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
+            .expect("Unable to create yaml admin store");
 
-        let proposals_path = temp_dir
-            .path()
-            .join("circuit_proposals.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+        assert_eq!(
+            store
+                .get_node("acme-node-000")
+                .expect("Unable to get node")
+                .id,
+            "acme-node-000"
+        );
+        assert_eq!(
+            store
+                .get_circuit("WBKLF-AAAAA")
+                .expect("Unable to get circuit"),
+            create_expected_circuit()
+        );
+        assert_eq!(
+            store
+                .get_proposal("WBKLF-BBBBB")
+                .expect("Unable to get proposal"),
+            create_expected_proposal()
+        );
 
-        // validate the files do not exist
-        assert!(!PathBuf::from(circuit_path.clone()).is_file());
-        assert!(!PathBuf::from(proposals_path.clone()).is_file());
+        match store.get_node("missing-node-000") {
+            Err(AdminServiceStoreError::NotFoundError(_)) => (),
+            res => panic!("Expected NotFoundError, got {:?}", res),
+        }
+        match store.get_circuit("WBKLF-ZZZZZ") {
+            Err(AdminServiceStoreError::NotFoundError(_)) => (),
+            res => panic!("Expected NotFoundError, got {:?}", res),
+        }
+        match store.get_proposal("WBKLF-ZZZZZ") {
+            Err(AdminServiceStoreError::NotFoundError(_)) => (),
+            res => panic!("Expected NotFoundError, got {:?}", res),
+        }
+    }
 
-        // create YamlAdminServiceStore
-        let _store = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+    // Test that removing a circuit or proposal that is present returns true, and removing it
+    // again returns false without writing the state files
+    //
+    // 1. Create a store on a file store that counts writes, with the existing state
+    // 2. Remove the circuit twice, verifying true then false and a single write
+    // 3. Remove the proposal twice, verifying true then false and a single write
+    #[test]
+    fn test_remove_if_present() {
+        struct CountingFileStore {
+            files: MemFileStore,
+            writes: Mutex<usize>,
+        }
+
+        impl CountingFileStore {
+            fn writes(&self) -> usize {
+                *self.writes.lock().expect("lock poisoned")
+            }
+        }
+
+        impl FileStore for CountingFileStore {
+            fn exists(&self, path: &Path) -> bool {
+                self.files.exists(path)
+            }
+
+            fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+                self.files.read(path)
+            }
+
+            fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+                *self.writes.lock().expect("lock poisoned") += 1;
+                self.files.write(path, contents)
+            }
+
+            fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+                self.files.modified(path)
+            }
+
+            fn remove(&self, path: &Path) -> io::Result<()> {
+                self.files.remove(path)
+            }
+        }
+
+        let file_store = Arc::new(CountingFileStore {
+            files: MemFileStore::new(),
+            writes: Mutex::new(0),
+        });
+        file_store
+            .files
+            .write(Path::new("/state/circuits.yaml"), CIRCUIT_STATE)
+            .expect("Unable to write circuit state");
+        file_store
+            .files
+            .write(Path::new("/state/circuit_proposals.yaml"), PROPOSAL_STATE)
+            .expect("Unable to write proposal state");
+
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/state/circuits.yaml")
+            .with_proposal_file_path("/state/circuit_proposals.yaml")
+            .with_file_store(file_store.clone())
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        let writes = file_store.writes();
+        assert!(store
+            .remove_circuit_if_present("WBKLF-AAAAA")
+            .expect("Unable to remove circuit"));
+        assert!(!store
+            .remove_circuit_if_present("WBKLF-AAAAA")
+            .expect("Unable to remove circuit"));
+        assert_eq!(file_store.writes(), writes + 1);
+        assert!(store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .is_none());
+
+        let writes = file_store.writes();
+        assert!(store
+            .remove_proposal_if_present("WBKLF-BBBBB")
+            .expect("Unable to remove proposal"));
+        assert!(!store
+            .remove_proposal_if_present("WBKLF-BBBBB")
+            .expect("Unable to remove proposal"));
+        assert_eq!(file_store.writes(), writes + 1);
+        assert!(store
+            .fetch_proposal("WBKLF-BBBBB")
+            .expect("Unable to fetch proposal")
+            .is_none());
+    }
+
+    // Test that circuit and proposal state can be parsed from bytes
+    //
+    // 1. Parse the sample circuit and proposal state
+    // 2. Verify the parsed state contains the expected circuit, node and proposal
+    // 3. Verify bytes that are not valid state are rejected
+    #[test]
+    fn test_state_try_from_bytes() {
+        let circuit_state =
+            CircuitState::try_from(CIRCUIT_STATE).expect("Unable to parse circuit state");
+        assert_eq!(
+            circuit_state.circuits().get("WBKLF-AAAAA"),
+            Some(&create_expected_circuit())
+        );
+        assert!(circuit_state.nodes().contains_key("acme-node-000"));
+        assert!(circuit_state.nodes().contains_key("bubba-node-000"));
+
+        let proposal_state =
+            ProposalState::try_from(PROPOSAL_STATE).expect("Unable to parse proposal state");
+        assert_eq!(
+            proposal_state.proposals().get("WBKLF-BBBBB"),
+            Some(&create_expected_proposal())
+        );
+
+        assert!(CircuitState::try_from(&b"not: [valid"[..]).is_err());
+        assert!(ProposalState::try_from(&b"not: [valid"[..]).is_err());
+    }
+
+    // Test that a change that cannot be written because the filesystem is read-only is rolled
+    // back, leaving the cached state the same as the state files
+    //
+    // 1. Create a store with a file store whose writes can be made to fail as read-only
+    // 2. Add a circuit, then make writes fail and add a proposal
+    // 3. Check that an error is returned and the proposal is not in the store
+    // 4. Make writes succeed again and check that the circuit is still in the store and that a
+    //    store reloaded from the files has the same circuits and proposals
+    #[test]
+    #[cfg(unix)]
+    fn test_read_only_filesystem_rollback() {
+        let file_store = Arc::new(FailingFileStore::new());
+
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/nonexistent/circuits.yaml")
+            .with_proposal_file_path("/nonexistent/circuit_proposals.yaml")
+            .with_file_store(file_store.clone())
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+
+        file_store.fail_writes(Some(|| io::Error::from_raw_os_error(libc::EROFS)));
+
+        match store.add_proposal(new_proposal()) {
+            Err(AdminServiceStoreError::StorageError {
+                source: Some(source),
+                ..
+            }) => assert!(source.downcast_ref::<YamlAdminStoreError>().is_some()),
+            res => panic!("Expected a storage error, got {:?}", res),
+        }
+        assert_eq!(
+            store
+                .fetch_proposal("WBKLF-CCCCC")
+                .expect("Unable to fetch proposal"),
+            None
+        );
+
+        file_store.fail_writes(None);
+
+        assert!(store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .is_some());
+
+        let reloaded_store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/nonexistent/circuits.yaml")
+            .with_proposal_file_path("/nonexistent/circuit_proposals.yaml")
+            .with_file_store(file_store.clone())
+            .build()
+            .expect("Unable to reload yaml admin store");
+        assert_eq!(
+            reloaded_store
+                .list_circuits(&[])
+                .expect("Unable to list circuits")
+                .map(|circuit| circuit.id)
+                .collect::<Vec<_>>(),
+            vec!["WBKLF-DDDDD".to_string()]
+        );
+        assert_eq!(
+            reloaded_store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .count(),
+            0
+        );
+    }
+
+    // Test that a change that cannot be written for any reason is rolled back, and is never seen
+    // by readers
+    //
+    // 1. Create a store with a file store whose writes can be made to fail, and add a circuit
+    // 2. Make writes fail, then update the circuit, remove it and add a proposal, checking that
+    //    each returns an error and that the store still has the original circuit and no proposal
+    // 3. Make writes succeed again and check that a store reloaded from the files has the same
+    //    circuit
+    #[test]
+    fn test_write_failure_rollback() {
+        let file_store = Arc::new(FailingFileStore::new());
+
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/state/circuits.yaml")
+            .with_proposal_file_path("/state/circuit_proposals.yaml")
+            .with_file_store(file_store.clone())
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit.clone(), vec![node])
+            .expect("Unable to add circuit");
+        let added = store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit");
+
+        file_store.fail_writes(Some(|| {
+            io::Error::new(io::ErrorKind::PermissionDenied, "permission denied")
+        }));
+
+        let mut updated = circuit;
+        updated.display_name = Some("updated".to_string());
+        assert!(store.update_circuit(updated).is_err());
+        assert!(store.remove_circuit("WBKLF-DDDDD").is_err());
+        assert!(store.add_proposal(new_proposal()).is_err());
+
+        assert_eq!(
+            store
+                .fetch_circuit("WBKLF-DDDDD")
+                .expect("Unable to fetch circuit"),
+            added
+        );
+        assert_eq!(
+            store
+                .fetch_proposal("WBKLF-CCCCC")
+                .expect("Unable to fetch proposal"),
+            None
+        );
+
+        file_store.fail_writes(None);
+
+        let reloaded_store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/state/circuits.yaml")
+            .with_proposal_file_path("/state/circuit_proposals.yaml")
+            .with_file_store(file_store)
+            .build()
+            .expect("Unable to reload yaml admin store");
+        let reloaded = reloaded_store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .expect("Circuit is missing from the reloaded store");
+        assert_eq!(reloaded.display_name, None);
+        assert_eq!(
+            Some(reloaded.updated_at),
+            added.map(|circuit| circuit.updated_at)
+        );
+    }
+
+    // Test that services can be added to and removed from a circuit's roster, keeping the
+    // service directory in sync
+    //
+    // 1. Setup the temp directory with existing state and create a store that rejects services
+    //    allowed on non-members
+    // 2. Add a service and verify it is in the roster, the service directory and the file
+    // 3. Verify adding a duplicate service, a service on a non-member or a service to a missing
+    //    circuit returns an error
+    // 4. Remove the service and verify it is no longer in the roster or the service directory
+    // 5. Verify removing a missing service or from a missing circuit returns an error
+    #[test]
+    fn test_add_and_remove_service() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_add_and_remove_service");
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_membership_policy(MembershipPolicy::Error)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        let service = ServiceBuilder::default()
+            .with_service_id("a002")
+            .with_service_type("scabbard")
+            .with_allowed_nodes(&["acme-node-000".to_string()])
+            .build()
+            .expect("Unable to build service");
+        let service_id = ServiceId::new("a002".to_string(), "WBKLF-AAAAA".to_string());
+
+        store
+            .add_service_to_circuit("WBKLF-AAAAA", service.clone())
+            .expect("Unable to add service");
+        assert_eq!(
+            store
+                .fetch_service(&service_id)
+                .expect("Unable to fetch service"),
+            Some(service.clone())
+        );
+        assert!(store
+            .list_services("WBKLF-AAAAA")
+            .expect("Unable to list services")
+            .any(|listed| listed == service));
+
+        let (reloaded_store, _) =
+            YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+                .expect("Unable to create yaml admin store");
+        assert_eq!(
+            reloaded_store
+                .fetch_service(&service_id)
+                .expect("Unable to fetch service"),
+            Some(service.clone())
+        );
+
+        assert!(store
+            .add_service_to_circuit("WBKLF-AAAAA", service.clone())
+            .is_err());
+        let non_member_service = ServiceBuilder::default()
+            .with_service_id("a003")
+            .with_service_type("scabbard")
+            .with_allowed_nodes(&["new-node-000".to_string()])
+            .build()
+            .expect("Unable to build service");
+        assert!(store
+            .add_service_to_circuit("WBKLF-AAAAA", non_member_service)
+            .is_err());
+        assert!(store
+            .add_service_to_circuit("WBKLF-ZZZZZ", service.clone())
+            .is_err());
+
+        store
+            .remove_service_from_circuit("WBKLF-AAAAA", "a002")
+            .expect("Unable to remove service");
+        assert_eq!(
+            store
+                .fetch_service(&service_id)
+                .expect("Unable to fetch service"),
+            None
+        );
+        assert_eq!(
+            store
+                .list_services("WBKLF-AAAAA")
+                .expect("Unable to list services")
+                .map(|listed| listed.service_id)
+                .collect::<Vec<_>>(),
+            vec!["a000".to_string(), "a001".to_string()]
+        );
+
+        assert!(store
+            .remove_service_from_circuit("WBKLF-AAAAA", "a002")
+            .is_err());
+        assert!(store
+            .remove_service_from_circuit("WBKLF-ZZZZZ", "a000")
+            .is_err());
+    }
+
+    // Test that a store is only empty if it has no circuits, proposals or nodes
+    //
+    // 1. Create a store in a temp directory without existing state and verify it is empty
+    // 2. Add a node and verify the store is no longer empty
+    // 3. Create a store from the existing state and verify it is not empty
+    #[test]
+    fn test_is_empty() {
+        let (_temp_dir, circuit_path, proposals_path) = state_file_paths("test_is_empty");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert!(store.is_empty().expect("Unable to check if store is empty"));
+
+        store
+            .ensure_node(CircuitNode {
+                id: "new-node-000".to_string(),
+                endpoints: vec!["tcps://splinterd-node-new:8044".to_string()],
+            })
+            .expect("Unable to add node");
+        assert!(!store.is_empty().expect("Unable to check if store is empty"));
+
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
+            .expect("Unable to create yaml admin store");
+        assert!(!store.is_empty().expect("Unable to check if store is empty"));
+    }
+
+    // Test that finalizing a proposal returns the consumed proposal and the created circuit
+    //
+    // 1. Create a store from the existing state and add a proposal whose proposed circuit ID does
+    //    not match its ID
+    // 2. Finalize the existing proposal and verify the returned proposal and circuit
+    // 3. Verify the proposal is removed and the circuit and its services are in the store
+    // 4. Verify finalizing the mismatched proposal returns an error and leaves it in the store
+    // 5. Verify finalizing the removed proposal returns a not found error
+    #[test]
+    fn test_finalize_proposal() {
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
+            .expect("Unable to create yaml admin store");
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+
+        let expected_proposal = create_expected_proposal();
+        let (proposal, circuit) = store
+            .finalize_proposal("WBKLF-BBBBB")
+            .expect("Unable to finalize proposal");
+
+        assert_eq!(proposal, expected_proposal);
+        assert_eq!(circuit.id, expected_proposal.circuit.circuit_id);
+        assert_eq!(
+            circuit.roster,
+            expected_proposal
+                .circuit
+                .roster
+                .iter()
+                .cloned()
+                .map(Service::from)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            circuit.members,
+            expected_proposal
+                .circuit
+                .members
+                .iter()
+                .map(|node| node.node_id.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            store
+                .fetch_proposal("WBKLF-BBBBB")
+                .expect("Unable to fetch proposal"),
+            None
+        );
+        assert_eq!(
+            store
+                .fetch_circuit(&circuit.id)
+                .expect("Unable to fetch circuit"),
+            Some(circuit.clone())
+        );
+        for service in circuit.roster.iter() {
+            assert!(store
+                .service_exists(&ServiceId::new(
+                    service.service_id.to_string(),
+                    circuit.id.to_string()
+                ))
+                .expect("Unable to check service"));
+        }
+
+        assert!(store.finalize_proposal("WBKLF-CCCCC").is_err());
+        assert!(store
+            .fetch_proposal("WBKLF-CCCCC")
+            .expect("Unable to fetch proposal")
+            .is_some());
+        assert!(matches!(
+            store.finalize_proposal("WBKLF-BBBBB"),
+            Err(AdminServiceStoreError::NotFoundError(_))
+        ));
+    }
+
+    // Test that votes stored as a map keyed by voter node ID are read as vote records, and that
+    // the votes are still written as a list
+    //
+    // 1. Replace the votes of the sample proposal with votes in map form and parse the state
+    // 2. Verify the votes are read as vote records in voter node ID order
+    // 3. Serialize the proposal state, parse it again and verify the votes are unchanged
+    #[test]
+    fn test_votes_map_form() {
+        let proposal_yaml = String::from_utf8(PROPOSAL_STATE.to_vec())
+            .expect("Proposal state is not UTF-8")
+            .replace(
+                "        votes: []\n",
+                "        votes:\n            \
+                             bubba-node-000:\n                \
+                                 public_key: [2, 131]\n                \
+                                 vote: Accept\n            \
+                             acme-node-000:\n                \
+                                 public_key: [3, 14]\n                \
+                                 vote: Reject\n",
+            );
+
+        let proposal_state = ProposalState::try_from(proposal_yaml.as_bytes())
+            .expect("Unable to parse proposal state");
+        let expected_votes = vec![
+            VoteRecord {
+                public_key: vec![3, 14],
+                vote: Vote::Reject,
+                voter_node_id: "acme-node-000".to_string(),
+            },
+            VoteRecord {
+                public_key: vec![2, 131],
+                vote: Vote::Accept,
+                voter_node_id: "bubba-node-000".to_string(),
+            },
+        ];
+        assert_eq!(
+            proposal_state.proposals()["WBKLF-BBBBB"].votes,
+            expected_votes
+        );
+
+        let written = serde_yaml::to_vec(&proposal_state).expect("Unable to write proposal state");
+        let value: serde_yaml::Value =
+            serde_yaml::from_slice(&written).expect("Unable to parse written proposal state");
+        assert!(value["proposals"]["WBKLF-BBBBB"]["votes"].is_sequence());
+        let proposal_state =
+            ProposalState::try_from(written.as_slice()).expect("Unable to parse proposal state");
+        assert_eq!(
+            proposal_state.proposals()["WBKLF-BBBBB"].votes,
+            expected_votes
+        );
+    }
+
+    // Test that the IDs of all services in the store are listed
+    //
+    // 1. Create a store from the existing state
+    // 2. Verify the IDs of both services of the existing circuit are listed
+    #[test]
+    fn test_list_service_ids() {
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
+            .expect("Unable to create yaml admin store");
+
+        let service_ids = store
+            .list_service_ids()
+            .expect("Unable to list service IDs");
+        assert_eq!(
+            service_ids,
+            vec![
+                ServiceId::new("a000".to_string(), "WBKLF-AAAAA".to_string()),
+                ServiceId::new("a001".to_string(), "WBKLF-AAAAA".to_string()),
+            ]
+        );
+    }
+
+    // Test that the circuits each node is a member of are listed by node
+    //
+    // 1. Create a store from the existing state
+    // 2. Verify both members of the existing circuit map to it
+    // 3. Add a circuit that shares both members and has a new one, and verify the shared members
+    //    map to both circuits and the new member only to the added circuit
+    #[test]
+    fn test_circuits_by_node() {
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
             .expect("Unable to create yaml admin store");
 
-        // validate the files exist now
-        assert!(PathBuf::from(circuit_path.clone()).is_file());
-        assert!(PathBuf::from(proposals_path.clone()).is_file());
+        let circuits_by_node = store
+            .circuits_by_node()
+            .expect("Unable to get circuits by node");
+        assert_eq!(circuits_by_node.len(), 2);
+        assert_eq!(
+            circuits_by_node["acme-node-000"],
+            vec!["WBKLF-AAAAA".to_string()]
+        );
+        assert_eq!(
+            circuits_by_node["bubba-node-000"],
+            vec!["WBKLF-AAAAA".to_string()]
+        );
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+
+        let circuits_by_node = store
+            .circuits_by_node()
+            .expect("Unable to get circuits by node");
+        assert_eq!(circuits_by_node.len(), 3);
+        for node_id in &["acme-node-000", "bubba-node-000"] {
+            assert_eq!(
+                circuits_by_node[*node_id],
+                vec!["WBKLF-AAAAA".to_string(), "WBKLF-DDDDD".to_string()]
+            );
+        }
+        assert_eq!(
+            circuits_by_node["new-node-000"],
+            vec!["WBKLF-DDDDD".to_string()]
+        );
+    }
+
+    // Test that add_circuit only rejects a circuit with a member that has no node definition when
+    // the store requires known members
+    //
+    // 1. For each setting, create a store from the existing state in a memory file store
+    // 2. Add a circuit with a member that is neither in the store nor added with the circuit
+    // 3. Without the requirement, check that the circuit was added
+    // 4. With the requirement, check that an error naming the member is returned and the circuit
+    //    was not added
+    #[test]
+    fn test_require_known_members() {
+        for require_known_members in &[false, true] {
+            let file_store = Arc::new(MemFileStore::new());
+            file_store
+                .write(Path::new("/state/circuits.yaml"), CIRCUIT_STATE)
+                .expect("Unable to write circuit state");
+            file_store
+                .write(Path::new("/state/circuit_proposals.yaml"), PROPOSAL_STATE)
+                .expect("Unable to write proposal state");
+
+            let store = YamlAdminServiceStoreBuilder::new()
+                .with_circuit_file_path("/state/circuits.yaml")
+                .with_proposal_file_path("/state/circuit_proposals.yaml")
+                .with_file_store(file_store)
+                .with_require_known_members(*require_known_members)
+                .build()
+                .expect("Unable to create yaml admin store");
+
+            let (mut circuit, node) = new_circuit();
+            circuit.members.push("unknown-node-000".to_string());
+
+            let result = store.add_circuit(circuit, vec![node]);
+            let added = store
+                .fetch_circuit("WBKLF-DDDDD")
+                .expect("Unable to fetch circuit")
+                .is_some();
+
+            if *require_known_members {
+                match result {
+                    Err(AdminServiceStoreError::OperationError { context, .. }) => {
+                        assert!(context.contains("unknown-node-000"))
+                    }
+                    res => panic!("Expected an operation error, got {:?}", res),
+                }
+                assert!(!added);
+            } else {
+                result.expect("Unable to add circuit");
+                assert!(added);
+            }
+        }
+    }
+
+    // Test that circuit times are written in each timestamp format and read back by a store with
+    // the same format
+    //
+    // 1. For each format, create a store with a fixed clock in a memory file store
+    // 2. Add a circuit and verify the circuit state file has its times in the format
+    // 3. Create a new store with the same format from the file and verify the circuit has the
+    //    same times
+    #[test]
+    fn test_timestamp_format_round_trip() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_590_969_600);
+        let formats = [
+            (
+                TimestampFormat::Rfc3339,
+                "created_at: \"2020-06-01T00:00:00Z\"",
+            ),
+            (TimestampFormat::EpochSeconds, "created_at: 1590969600\n"),
+            (TimestampFormat::EpochMillis, "created_at: 1590969600000\n"),
+        ];
+
+        for (timestamp_format, expected) in formats.iter() {
+            let file_store = Arc::new(MemFileStore::new());
+            let build_store = || {
+                YamlAdminServiceStoreBuilder::new()
+                    .with_circuit_file_path("/state/circuits.yaml")
+                    .with_proposal_file_path("/state/circuit_proposals.yaml")
+                    .with_file_store(file_store.clone())
+                    .with_clock(Arc::new(FixedClock::new(now)))
+                    .with_timestamp_format(*timestamp_format)
+                    .build()
+                    .expect("Unable to create yaml admin store")
+            };
+
+            let (circuit, node) = new_circuit();
+            build_store()
+                .add_circuit(circuit, vec![node])
+                .expect("Unable to add circuit");
+
+            let circuit_file = String::from_utf8(
+                file_store
+                    .read(Path::new("/state/circuits.yaml"))
+                    .expect("Unable to read circuit state file"),
+            )
+            .expect("Circuit state file is not UTF-8");
+            assert!(
+                circuit_file.contains(expected),
+                "Expected {:?} in circuit state file:\n{}",
+                expected,
+                circuit_file
+            );
+
+            let circuit = build_store()
+                .fetch_circuit("WBKLF-DDDDD")
+                .expect("Unable to fetch circuit")
+                .expect("Circuit not found");
+            assert_eq!(circuit.created_at(), now);
+            assert_eq!(circuit.updated_at(), now);
+        }
+    }
+
+    // Test that circuit times written as epoch times are read by a store with the default RFC 3339
+    // timestamp format
+    //
+    // 1. For each epoch format, write a circuit with a store using the format
+    // 2. Create a store with the default format from the file and verify the circuit has the
+    //    same times
+    #[test]
+    fn test_timestamp_format_cross_read() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_590_969_600);
+
+        for timestamp_format in &[TimestampFormat::EpochSeconds, TimestampFormat::EpochMillis] {
+            let file_store = Arc::new(MemFileStore::new());
+
+            let (circuit, node) = new_circuit();
+            YamlAdminServiceStoreBuilder::new()
+                .with_circuit_file_path("/state/circuits.yaml")
+                .with_proposal_file_path("/state/circuit_proposals.yaml")
+                .with_file_store(file_store.clone())
+                .with_clock(Arc::new(FixedClock::new(now)))
+                .with_timestamp_format(*timestamp_format)
+                .build()
+                .expect("Unable to create yaml admin store")
+                .add_circuit(circuit, vec![node])
+                .expect("Unable to add circuit");
+
+            let circuit = YamlAdminServiceStoreBuilder::new()
+                .with_circuit_file_path("/state/circuits.yaml")
+                .with_proposal_file_path("/state/circuit_proposals.yaml")
+                .with_file_store(file_store)
+                .build()
+                .expect("Unable to create yaml admin store")
+                .fetch_circuit("WBKLF-DDDDD")
+                .expect("Unable to fetch circuit")
+                .expect("Circuit not found");
+            assert_eq!(circuit.created_at(), now);
+            assert_eq!(circuit.updated_at(), now);
+        }
     }
 
-    // Validate that the YAML admin service store can properly load circuit and proposals state
-    // from existing YAML files
+    // Test that state files missing required top-level keys fail to load with a strict schema,
+    // and are read as empty state without one
     //
-    // 1. Creates a temp directory with existing circuit and proposals yaml files
-    // 2. Create a YAML admin service directory
-    // 3. Validate that the circuit and proposals can be fetched from state
+    // 1. Write a circuit state file with only a `nodes` key and a proposal state file containing
+    //    an empty mapping
+    // 2. Verify the store fails to load by default, with an error naming the missing key
+    // 3. Verify the store loads with the strict schema disabled, with the nodes from the file and
+    //    no circuits or proposals
     #[test]
-    fn test_read_existing_files() {
-        // create temp dir
-        let temp_dir = TempDir::new("test_read_existing_files").expect("Failed to create temp dir");
-        let circuit_path = temp_dir
-            .path()
-            .join("circuits.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+    fn test_strict_schema() {
+        let (_temp_dir, circuit_path, proposals_path) = state_file_paths("test_strict_schema");
 
-        let proposals_path = temp_dir
-            .path()
-            .join("circuit_proposals.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+        write_file(
+            b"---\nnodes:\n  acme-node-000:\n    id: acme-node-000\n    endpoints:\n      \
+              - \"tcps://splinterd-node-acme:8044\"\n",
+            &circuit_path,
+        );
+        write_file(b"--- {}\n", &proposals_path);
 
-        // write yaml files to temp_dir
-        write_file(CIRCUIT_STATE, &circuit_path);
-        write_file(PROPOSAL_STATE, &proposals_path);
+        match store_builder(&circuit_path, &proposals_path).build() {
+            Err(err) => assert!(err
+                .to_string()
+                .contains("missing the top-level key(s) circuits")),
+            Ok(_) => panic!("Expected the store to fail to load"),
+        }
 
-        // create YamlAdminServiceStore
-        let store = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_strict_schema(false)
+            .build()
             .expect("Unable to create yaml admin store");
 
         assert!(store
-            .fetch_proposal("WBKLF-BBBBB")
-            .expect("unable to fetch proposals")
-            .is_some());
-        assert!(store
-            .fetch_circuit("WBKLF-AAAAA")
-            .expect("unable to fetch circuits")
+            .fetch_node("acme-node-000")
+            .expect("Unable to fetch node")
             .is_some());
+        assert_eq!(
+            store
+                .list_circuits(&[])
+                .expect("Unable to list circuits")
+                .count(),
+            0
+        );
+        assert_eq!(
+            store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .count(),
+            0
+        );
     }
 
-    // Test the proposal CRUD operations
+    // Test that stores loaded from identical files are in sync, and stores loaded from
+    // different files are not
     //
-    // 1. Setup the temp directory with existing state
-    // 2. Fetch an existing proposal from state, validate proposal is returned
-    // 3. Fetch an non exisitng proposal from state, validate None
-    // 4. Update fetched proposal with a vote record and update, validate ok
-    // 5. Call update with new proposal, validate error is returned
-    // 6. Add new proposal, validate ok
-    // 7. List proposal, validate both the updated original proposal and new proposal is returned
-    // 8. Remove original proposal, validate okay
-    // 9. Validate the proposal state YAML in the temp dir matches the expected bytes and only
-    //    the new proposals
+    // 1. Write the existing state to two temp directories and load a store from each
+    // 2. Verify the stores are in sync
+    // 3. Add a proposal to one store and verify the stores are no longer in sync
     #[test]
-    fn test_proposals() {
-        // create temp dir
-        let temp_dir = TempDir::new("test_proposals").expect("Failed to create temp dir");
-        let circuit_path = temp_dir
-            .path()
-            .join("circuits.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+    fn test_stores_in_sync() {
+        let temp_dir = TempDir::new("test_stores_in_sync").expect("Failed to create temp dir");
+
+        let create_store = |name: &str| {
+            let circuit_path = temp_dir
+                .path()
+                .join(format!("{}_circuits.yaml", name))
+                .to_str()
+                .expect("Failed to get path")
+                .to_string();
+
+            let proposals_path = temp_dir
+                .path()
+                .join(format!("{}_circuit_proposals.yaml", name))
+                .to_str()
+                .expect("Failed to get path")
+                .to_string();
+
+            // write yaml files to temp_dir
+            write_file(CIRCUIT_STATE, &circuit_path);
+            write_file(PROPOSAL_STATE, &proposals_path);
+
+            let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+                .expect("Unable to create yaml admin store");
+            store
+        };
 
-        let proposals_path = temp_dir
-            .path()
-            .join("circuit_proposals.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+        let primary = create_store("primary");
+        let replica = create_store("replica");
 
-        // write yaml files to temp_dir
-        write_file(CIRCUIT_STATE, &circuit_path);
-        write_file(PROPOSAL_STATE, &proposals_path);
+        assert!(stores_in_sync(&primary, &replica).expect("Unable to compare stores"));
 
-        // create YamlAdminServiceStore
-        let store = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
-            .expect("Unable to create yaml admin store");
+        primary
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
 
-        // fetch existing proposal from state
-        let mut proposal = store
-            .fetch_proposal("WBKLF-BBBBB")
-            .expect("unable to fetch proposals")
-            .expect("Expected proposal, got none");
+        assert!(!stores_in_sync(&primary, &replica).expect("Unable to compare stores"));
+    }
 
-        assert_eq!(proposal, create_expected_proposal());
+    // Test that the requester of a proposal and the public key of a vote are returned as hex
+    //
+    // 1. Parse the sample proposal state and verify the requester of the proposal is returned as
+    //    the hex in the file
+    // 2. Create a vote record with the same key and verify its public key is returned as hex
+    #[test]
+    fn test_requester_and_public_key_hex() {
+        let requester_hex = "0283a14e0a17cb7f665311e9b5560f4cde2b502f17e2d03223e15d90d9318d7482";
 
-        // fetch nonexisting proposal from state
-        assert!(store
-            .fetch_proposal("WBKLF-BADD")
-            .expect("unable to fetch proposals")
-            .is_none());
+        let proposal_state =
+            ProposalState::try_from(PROPOSAL_STATE).expect("Unable to parse proposal state");
+        assert_eq!(
+            proposal_state.proposals()["WBKLF-BBBBB"].requester_hex(),
+            requester_hex
+        );
 
-        proposal.add_vote(VoteRecord {
-            public_key: parse_hex(
-                "035724d11cae47c8907f8bfdf510488f49df8494ff81b63825bad923733c4ac550",
-            )
-            .unwrap(),
+        let vote_record = VoteRecord {
+            public_key: parse_hex(requester_hex).expect("Unable to parse hex"),
             vote: Vote::Accept,
-            voter_node_id: "bubba-node-000".into(),
-        });
+            voter_node_id: "acme-node-000".to_string(),
+        };
+        assert_eq!(vote_record.public_key_hex(), requester_hex);
+    }
+
+    // Test that circuit state can be read from a reader and written to a writer without a store
+    //
+    // 1. Read the sample circuit state from an in-memory reader
+    // 2. Write the state to an in-memory writer
+    // 3. Read the written output and verify it matches the state that was read
+    // 4. Verify the same round trip works in the JSON format
+    #[test]
+    fn test_circuit_state_reader_writer() {
+        let circuit_state = read_circuit_state_from(CIRCUIT_STATE, FileFormat::Yaml)
+            .expect("Unable to read circuit state");
+        assert!(circuit_state.circuits().contains_key("WBKLF-AAAAA"));
+
+        let mut output = vec![];
+        write_circuit_state_to(&mut output, &circuit_state, FileFormat::Yaml)
+            .expect("Unable to write circuit state");
+        assert!(output.starts_with(b"---"));
+        assert_eq!(
+            read_circuit_state_from(output.as_slice(), FileFormat::Yaml)
+                .expect("Unable to read written circuit state"),
+            circuit_state
+        );
+
+        let mut output = vec![];
+        write_circuit_state_to(&mut output, &circuit_state, FileFormat::Json)
+            .expect("Unable to write circuit state");
+        assert_eq!(
+            read_circuit_state_from(output.as_slice(), FileFormat::Json)
+                .expect("Unable to read written circuit state"),
+            circuit_state
+        );
+    }
 
+    // Test that nodes no longer a member of any circuit are pruned, either explicitly or when a
+    // circuit is removed from a store built to prune them
+    //
+    // 1. Create a store from the existing state and add a circuit with a new node
+    // 2. Verify pruning removes nothing while both circuits exist
+    // 3. Remove the new circuit and verify its node is kept until the nodes are pruned
+    // 4. Create a store that prunes nodes on removal and verify removing the new circuit removes
+    //    its node, but not the nodes of the remaining circuit
+    #[test]
+    fn test_prune_orphan_nodes() {
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
+            .expect("Unable to create yaml admin store");
+        let (circuit, node) = new_circuit();
         store
-            .update_proposal(proposal.clone())
-            .expect("Unable to update proposal");
+            .add_circuit(circuit.clone(), vec![node.clone()])
+            .expect("Unable to add circuit");
 
-        let new_proposal = new_proposal();
+        assert!(store
+            .prune_orphan_nodes()
+            .expect("Unable to prune nodes")
+            .is_empty());
 
-        assert!(
-            store.update_proposal(new_proposal.clone()).is_err(),
-            "Updating new proposal should fail"
+        store
+            .remove_circuit(&circuit.id)
+            .expect("Unable to remove circuit");
+        assert!(store
+            .fetch_node("new-node-000")
+            .expect("Unable to fetch node")
+            .is_some());
+
+        assert_eq!(
+            store.prune_orphan_nodes().expect("Unable to prune nodes"),
+            vec!["new-node-000".to_string()]
         );
+        assert!(store
+            .fetch_node("new-node-000")
+            .expect("Unable to fetch node")
+            .is_none());
+        assert_eq!(store.list_nodes().expect("Unable to list nodes").count(), 2);
+
+        let file_store = Arc::new(MemFileStore::new());
+        file_store
+            .write(Path::new("/state/circuits.yaml"), CIRCUIT_STATE)
+            .expect("Unable to write circuit state");
+        file_store
+            .write(Path::new("/state/circuit_proposals.yaml"), PROPOSAL_STATE)
+            .expect("Unable to write proposal state");
+        let store = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/state/circuits.yaml")
+            .with_proposal_file_path("/state/circuit_proposals.yaml")
+            .with_file_store(file_store)
+            .with_prune_nodes_on_remove(true)
+            .build()
+            .expect("Unable to create yaml admin store");
+        store
+            .add_circuit(circuit.clone(), vec![node])
+            .expect("Unable to add circuit");
 
         store
-            .add_proposal(new_proposal.clone())
+            .remove_circuit(&circuit.id)
+            .expect("Unable to remove circuit");
+        assert!(store
+            .fetch_node("new-node-000")
+            .expect("Unable to fetch node")
+            .is_none());
+        assert_eq!(store.list_nodes().expect("Unable to list nodes").count(), 2);
+    }
+
+    // Test that the reported sizes of the circuit and proposal state match the sizes of the
+    // written state files
+    //
+    // 1. Setup the temp directory with existing state and add a circuit and a proposal, so both
+    //    files are written by the store
+    // 2. Verify the reported sizes match the sizes of the files
+    #[test]
+    fn test_state_size_bytes() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_state_size_bytes");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+        store
+            .add_proposal(new_proposal())
             .expect("Unable to add proposal");
 
+        let file_size = |path: &str| {
+            std::fs::metadata(path)
+                .expect("Unable to read file metadata")
+                .len() as usize
+        };
         assert_eq!(
             store
-                .list_proposals(&vec![])
-                .expect("Unable to get list of proposals")
-                .collect::<Vec<CircuitProposal>>(),
-            vec![proposal, new_proposal.clone()]
+                .circuit_state_size_bytes()
+                .expect("Unable to get circuit state size"),
+            file_size(&circuit_path)
         );
+        assert_eq!(
+            store
+                .proposal_state_size_bytes()
+                .expect("Unable to get proposal state size"),
+            file_size(&proposals_path)
+        );
+    }
 
-        store
-            .remove_proposal("WBKLF-BBBBB")
-            .expect("Unable to remove proposals");
-
-        let mut yaml_state = BTreeMap::new();
-        yaml_state.insert(new_proposal.circuit_id.to_string(), new_proposal);
-        let mut yaml_state_vec = serde_yaml::to_vec(&ProposalState {
-            proposals: yaml_state,
-        })
-        .unwrap();
+    // Test that a store built to memory-map the circuit state file loads the same state as one
+    // that reads the file
+    //
+    // 1. Write the sample state files and load one store that reads the circuit state file and
+    //    one that maps it
+    // 2. Verify both stores have the same circuits, nodes and proposals
+    // 3. Verify a change written by the mapping store is loaded by the mapping store on reload
+    #[cfg(feature = "admin-store-mmap")]
+    #[test]
+    fn test_mmap_circuit_file() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_mmap_circuit_file");
 
-        // Add new line because the file has a new added to it
-        yaml_state_vec.append(&mut "\n".as_bytes().to_vec());
+        let build_store = |mmap_circuit_file: bool| {
+            store_builder(&circuit_path, &proposals_path)
+                .with_mmap_circuit_file(mmap_circuit_file)
+                .build()
+                .expect("Unable to create yaml admin store")
+        };
+        let buffered_store = build_store(false);
+        let mapped_store = build_store(true);
 
-        let mut contents = vec![];
-        File::open(proposals_path.clone())
-            .unwrap()
-            .read_to_end(&mut contents)
-            .expect("Unable to read proposals");
+        assert_eq!(
+            mapped_store
+                .list_circuits(&[])
+                .expect("Unable to list circuits")
+                .collect::<Vec<_>>(),
+            buffered_store
+                .list_circuits(&[])
+                .expect("Unable to list circuits")
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            mapped_store
+                .list_nodes()
+                .expect("Unable to list nodes")
+                .collect::<Vec<_>>(),
+            buffered_store
+                .list_nodes()
+                .expect("Unable to list nodes")
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            mapped_store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .collect::<Vec<_>>(),
+            buffered_store
+                .list_proposals(&[])
+                .expect("Unable to list proposals")
+                .collect::<Vec<_>>()
+        );
 
-        assert_eq!(yaml_state_vec, contents)
+        let (circuit, node) = new_circuit();
+        mapped_store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+        mapped_store.reload().expect("Unable to reload store");
+        mapped_store
+            .get_circuit("WBKLF-DDDDD")
+            .expect("Unable to get circuit");
     }
 
-    // Test the circuit CRUD operations
+    // Test that removing a circuit with the default policy leaves a proposal with the same ID in
+    // place
     //
     // 1. Setup the temp directory with existing state
-    // 2. Fetch an existing circuit from state, validate circuit is returned
-    // 3. Fetch an non exisitng circuit from state, validate None
-    // 4. Update fetched proposa with a vote record and update, validate ok
-    // 5. Call update with new circuit, validate error is returned
-    // 6. Add new circuit, validate ok
-    // 7. List circuit, validate both the updated original circuit and new circuit is returned
-    // 8. Remove original circuit, validate okay
-    // 9. Validate the circuit state YAML in the temp dir matches the expected bytes and contains
-    //    only the new circuit
+    // 2. Add a proposal with the same ID as the existing circuit
+    // 3. Remove the circuit and check the proposal still exists, both in the store and on disk
     #[test]
-    fn test_circuit() {
-        // create temp dir
-        let temp_dir = TempDir::new("test_circuit").expect("Failed to create temp dir");
-        let circuit_path = temp_dir
-            .path()
-            .join("circuits.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+    fn test_remove_circuit_warn_proposals() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_remove_circuit_warn_proposals");
 
-        let proposals_path = temp_dir
-            .path()
-            .join("circuit_proposals.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
 
-        // write yaml files to temp_dir
-        write_file(CIRCUIT_STATE, &circuit_path);
-        write_file(PROPOSAL_STATE, &proposals_path);
+        let mut proposal = new_proposal();
+        proposal.circuit_id = "WBKLF-AAAAA".to_string();
+        proposal.circuit.circuit_id = "WBKLF-AAAAA".to_string();
+        store
+            .add_proposal(proposal.clone())
+            .expect("Unable to add proposal");
 
-        // create YamlAdminServiceStore
-        let store = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
-            .expect("Unable to create yaml admin store");
+        store
+            .remove_circuit("WBKLF-AAAAA")
+            .expect("Unable to remove circuit");
 
-        // fetch existing circuit from state
-        let mut circuit = store
-            .fetch_circuit("WBKLF-AAAAA")
-            .expect("unable to fetch circuit")
-            .expect("Expected circuit, got none");
+        assert_eq!(store.fetch_circuit("WBKLF-AAAAA").unwrap(), None);
+        assert_eq!(
+            store.fetch_proposal("WBKLF-AAAAA").unwrap(),
+            Some(proposal.clone())
+        );
 
-        assert_eq!(circuit, create_expected_circuit());
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert_eq!(store.fetch_proposal("WBKLF-AAAAA").unwrap(), Some(proposal));
+    }
 
-        // fetch nonexisting circuitfrom state
-        assert!(store
-            .fetch_circuit("WBKLF-BADD")
-            .expect("unable to fetch circuit")
-            .is_none());
+    // Test that removing a circuit with the cascade-remove policy also removes a proposal with
+    // the same ID
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Add a proposal with the same ID as the existing circuit
+    // 3. Remove the circuit and check the proposal was removed, both in the store and on disk
+    // 4. Check that unrelated proposals are not removed
+    #[test]
+    fn test_remove_circuit_cascade_proposals() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_remove_circuit_cascade_proposals");
 
-        circuit.circuit_management_type = "test".to_string();
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_cascade_proposals(CascadeProposals::Remove)
+            .build()
+            .expect("Unable to create yaml admin store");
 
+        let mut proposal = new_proposal();
+        proposal.circuit_id = "WBKLF-AAAAA".to_string();
+        proposal.circuit.circuit_id = "WBKLF-AAAAA".to_string();
         store
-            .update_circuit(circuit.clone())
-            .expect("Unable to update circuit");
+            .add_proposal(proposal)
+            .expect("Unable to add proposal");
 
-        let (new_circuit, new_node) = new_circuit();
+        store
+            .remove_circuit("WBKLF-AAAAA")
+            .expect("Unable to remove circuit");
 
-        assert!(
-            store.update_circuit(new_circuit.clone()).is_err(),
-            "Updating new cirucit should fail"
+        assert_eq!(store.fetch_circuit("WBKLF-AAAAA").unwrap(), None);
+        assert_eq!(store.fetch_proposal("WBKLF-AAAAA").unwrap(), None);
+        assert_eq!(
+            store.fetch_proposal("WBKLF-BBBBB").unwrap(),
+            Some(create_expected_proposal())
+        );
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert_eq!(store.fetch_proposal("WBKLF-AAAAA").unwrap(), None);
+        assert_eq!(
+            store.fetch_proposal("WBKLF-BBBBB").unwrap(),
+            Some(create_expected_proposal())
         );
+    }
 
-        store
-            .add_circuit(new_circuit.clone(), vec![new_node.clone()])
-            .expect("Unable to add cirucit");
+    // Test that unparseable proposals are skipped when lenient proposal parsing is enabled
+    //
+    // 1. Setup the temp directory with a proposal file that has two valid proposals and one
+    //    invalid proposal
+    // 2. Check that loading the file without lenient parsing fails
+    // 3. Check that loading the file with lenient parsing loads the two valid proposals and
+    //    reports the invalid proposal as skipped
+    #[test]
+    fn test_lenient_proposals() {
+        let (_temp_dir, circuit_path, proposals_path) = state_file_paths("test_lenient_proposals");
 
-        assert_eq!(
-            store
-                .list_circuits(&vec![])
-                .expect("Unable to get list of circuits")
-                .collect::<Vec<Circuit>>(),
-            vec![circuit, new_circuit.clone()]
+        let mut proposal_state = ProposalState::default();
+        for proposal in [create_expected_proposal(), new_proposal()].iter().cloned() {
+            proposal_state
+                .proposals
+                .insert(proposal.circuit_id.clone(), proposal);
+        }
+        let mut proposal_yaml =
+            serde_yaml::to_value(&proposal_state).expect("Unable to serialize proposals");
+        if let serde_yaml::Value::Mapping(proposals) = &mut proposal_yaml["proposals"] {
+            let mut invalid = serde_yaml::Mapping::new();
+            invalid.insert("proposal_type".into(), "NotAProposalType".into());
+            proposals.insert("WBKLF-EEEEE".into(), invalid.into());
+        } else {
+            panic!("Serialized proposals are not a mapping");
+        }
+
+        // write yaml files to temp_dir
+        write_file(CIRCUIT_STATE, &circuit_path);
+        write_file(
+            serde_yaml::to_string(&proposal_yaml)
+                .expect("Unable to serialize proposals")
+                .as_bytes(),
+            &proposals_path,
         );
 
-        store
-            .remove_circuit("WBKLF-AAAAA")
-            .expect("Unable to remove circuit");
+        assert!(YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone()).is_err());
 
-        let mut yaml_circuits = BTreeMap::new();
-        let mut yaml_nodes = BTreeMap::new();
-        yaml_circuits.insert(new_circuit.id.to_string(), YamlCircuit::from(new_circuit));
-        yaml_nodes.insert(
-            "acme-node-000".to_string(),
-            CircuitNode {
-                id: "acme-node-000".to_string(),
-                endpoints: vec!["tcps://splinterd-node-acme:8044".into()],
-            },
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_lenient_proposals(true)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store.skipped_proposals().unwrap(),
+            vec!["WBKLF-EEEEE".to_string()]
         );
-        yaml_nodes.insert(
-            "bubba-node-000".to_string(),
-            CircuitNode {
-                id: "bubba-node-000".to_string(),
-                endpoints: vec!["tcps://splinterd-node-bubba:8044".into()],
-            },
+        assert_eq!(
+            store.list_proposals(&[]).unwrap().collect::<Vec<_>>(),
+            vec![create_expected_proposal(), new_proposal()]
         );
-        yaml_nodes.insert(new_node.id.to_string(), new_node);
-        let mut yaml_state_vec = serde_yaml::to_vec(&YamlCircuitState {
-            circuits: yaml_circuits,
-            nodes: yaml_nodes,
-        })
-        .unwrap();
+    }
 
-        // Add new line because the file has a new added to it
-        yaml_state_vec.append(&mut "\n".as_bytes().to_vec());
+    // Test that list_all returns the same circuits, proposals and nodes as the individual list
+    // operations
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Call list_all and then each of the individual list operations
+    // 3. Check that the combined view matches the individual lists
+    #[test]
+    fn test_list_all() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_list_all");
 
-        let mut contents = vec![];
-        File::open(circuit_path.clone())
-            .unwrap()
-            .read_to_end(&mut contents)
-            .expect("Unable to read proposals");
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
 
-        assert_eq!(yaml_state_vec, contents)
+        let view = store.list_all().expect("Unable to list all state");
+
+        assert_eq!(
+            view.circuits,
+            store.list_circuits(&[]).unwrap().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            view.proposals,
+            store.list_proposals(&[]).unwrap().collect::<Vec<_>>()
+        );
+        assert_eq!(view.nodes, store.list_nodes().unwrap().collect::<Vec<_>>());
+        assert_eq!(view.circuits, vec![create_expected_circuit()]);
+        assert_eq!(view.proposals, vec![create_expected_proposal()]);
+        assert_eq!(view.nodes.len(), 2);
     }
 
     // Test the node CRUD operations
@@ -1450,28 +8733,10 @@ proposals:
     // 3. Check that the expected nodes are returned when list_nodes is called
     #[test]
     fn test_node() {
-        // create temp dir
-        let temp_dir = TempDir::new("test_node").expect("Failed to create temp dir");
-        let circuit_path = temp_dir
-            .path()
-            .join("circuits.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
-
-        let proposals_path = temp_dir
-            .path()
-            .join("circuit_proposals.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
-
-        // write yaml files to temp_dir
-        write_file(CIRCUIT_STATE, &circuit_path);
-        write_file(PROPOSAL_STATE, &proposals_path);
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_node");
 
         // create YamlAdminServiceStore
-        let store = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
             .expect("Unable to create yaml admin store");
 
         let node = store
@@ -1502,6 +8767,58 @@ proposals:
         );
     }
 
+    // Test that a node endpoint written as a single string is read as a one-element list
+    //
+    // 1. Setup the temp directory with circuit state whose node endpoint is a scalar
+    // 2. Check that the fetched node has a single endpoint
+    // 3. Check that the endpoints are written back to the file as a list
+    #[test]
+    fn test_node_scalar_endpoint() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            state_file_paths("test_node_scalar_endpoint");
+
+        // write yaml files to temp_dir
+        write_file(
+            b"---
+nodes:
+    acme-node-000:
+        id: acme-node-000
+        endpoints: \"tcps://splinterd-node-acme:8044\"
+circuits: {}",
+            &circuit_path,
+        );
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let node = store
+            .fetch_node("acme-node-000")
+            .expect("Unable to fetch node")
+            .expect("expected node, got none");
+
+        assert_eq!(
+            node,
+            CircuitNode {
+                id: "acme-node-000".to_string(),
+                endpoints: vec!["tcps://splinterd-node-acme:8044".into()],
+            }
+        );
+
+        // trigger a write of the circuit state and check the endpoints are written as a list
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+
+        let mut yaml_state = String::new();
+        std::fs::File::open(&circuit_path)
+            .expect("Unable to open circuit file")
+            .read_to_string(&mut yaml_state)
+            .expect("Unable to read circuit file");
+        assert!(yaml_state.contains("- \"tcps://splinterd-node-acme:8044\""));
+    }
+
     // Test the service CRUD operations
     //
     // 1. Setup the temp directory with existing state
@@ -1509,30 +8826,12 @@ proposals:
     // 3. Check that the expected services are returned when list_services is called
     #[test]
     fn test_service() {
-        // create temp dir
-        let temp_dir = TempDir::new("test_service").expect("Failed to create temp dir");
-        let circuit_path = temp_dir
-            .path()
-            .join("circuits.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
-
-        let proposals_path = temp_dir
-            .path()
-            .join("circuit_proposals.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
-
-        // write yaml files to temp_dir
-        write_file(CIRCUIT_STATE, &circuit_path);
-        write_file(PROPOSAL_STATE, &proposals_path);
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_service");
 
         let service_id = ServiceId::new("a000".to_string(), "WBKLF-AAAAA".to_string());
 
         // create YamlAdminServiceStore
-        let store = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
             .expect("Unable to create yaml admin store");
 
         let service = store
@@ -1600,28 +8899,14 @@ proposals:
     // 4. Check that the circuit, nodes and services have been set
     #[test]
     fn test_upgrading_proposals_to_circuit() {
-        // create temp dir
-        let temp_dir =
-            TempDir::new("est_upgrading_proposals_to_circuit").expect("Failed to create temp dir");
-        let circuit_path = temp_dir
-            .path()
-            .join("circuits.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
-
-        let proposals_path = temp_dir
-            .path()
-            .join("circuit_proposals.yaml")
-            .to_str()
-            .expect("Failed to get path")
-            .to_string();
+        let (_temp_dir, circuit_path, proposals_path) =
+            state_file_paths("est_upgrading_proposals_to_circuit");
 
         // write proposal to state
         write_file(PROPOSAL_STATE, &proposals_path);
 
         // create YamlAdminServiceStore
-        let store = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
             .expect("Unable to create yaml admin store");
 
         let service_id = ServiceId::new("a000".to_string(), "WBKLF-BBBBB".to_string());
@@ -1640,13 +8925,302 @@ proposals:
         assert!(store.fetch_service(&service_id).unwrap().is_some());
     }
 
-    fn write_file(data: &[u8], file_path: &str) {
+    // Test that an upgraded proposal is archived when the store is built with proposal archiving
+    // enabled.
+    //
+    // 1. Setup the temp directory with existing proposal state
+    // 2. Build a store with proposal archiving enabled
+    // 3. Upgrade proposal to circuit, validate ok
+    // 4. Check that the proposal is no longer listed but can be fetched from the archive
+    // 5. Check that the archive is reloaded by a new store using the same files
+    #[test]
+    fn test_archive_upgraded_proposal() {
+        let (temp_dir, circuit_path, proposals_path) =
+            state_file_paths("test_archive_upgraded_proposal");
+
+        // write proposal to state
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_archived_proposals(true)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        assert!(temp_dir
+            .path()
+            .join("archived_circuit_proposals.yaml")
+            .is_file());
+        assert_eq!(store.fetch_archived_proposal("WBKLF-BBBBB").unwrap(), None);
+
+        store
+            .upgrade_proposal_to_circuit("WBKLF-BBBBB")
+            .expect("Unable to upgrade proposal to circuit");
+
+        assert_eq!(store.list_proposals(&[]).unwrap().next(), None);
+        assert_eq!(
+            store.fetch_archived_proposal("WBKLF-BBBBB").unwrap(),
+            Some(create_expected_proposal())
+        );
+
+        let reloaded_store = store_builder(&circuit_path, &proposals_path)
+            .with_archived_proposals(true)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            reloaded_store
+                .fetch_archived_proposal("WBKLF-BBBBB")
+                .unwrap(),
+            Some(create_expected_proposal())
+        );
+    }
+
+    // Test that archiving an upgraded proposal is written with the rest of the state, so a failed
+    // write rolls back the archived proposal along with the upgrade
+    //
+    // 1. Create a store with proposal archiving and a file store whose writes can be made to fail,
+    //    loading existing proposal state
+    // 2. Make writes fail and upgrade the proposal, checking that an error is returned and that
+    //    the proposal has not been removed, archived or upgraded to a circuit
+    // 3. Make writes succeed, upgrade the proposal and check that the archived proposal is in a
+    //    reloaded store
+    #[test]
+    fn test_archive_write_failure_rollback() {
+        let file_store = Arc::new(FailingFileStore::new());
+        file_store
+            .write(Path::new("/state/circuit_proposals.yaml"), PROPOSAL_STATE)
+            .expect("Unable to write proposal state");
+
+        let builder = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path("/state/circuits.yaml")
+            .with_proposal_file_path("/state/circuit_proposals.yaml")
+            .with_archived_proposals(true)
+            .with_file_store(file_store.clone());
+        let store = builder
+            .clone()
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        file_store.fail_writes(Some(|| {
+            io::Error::new(io::ErrorKind::PermissionDenied, "permission denied")
+        }));
+
+        assert!(store.upgrade_proposal_to_circuit("WBKLF-BBBBB").is_err());
+        assert!(store.fetch_proposal("WBKLF-BBBBB").unwrap().is_some());
+        assert_eq!(store.fetch_archived_proposal("WBKLF-BBBBB").unwrap(), None);
+        assert_eq!(store.fetch_circuit("WBKLF-BBBBB").unwrap(), None);
+
+        file_store.fail_writes(None);
+
+        store
+            .upgrade_proposal_to_circuit("WBKLF-BBBBB")
+            .expect("Unable to upgrade proposal to circuit");
+
+        let reloaded_store = builder.build().expect("Unable to reload yaml admin store");
+        assert_eq!(
+            reloaded_store
+                .fetch_archived_proposal("WBKLF-BBBBB")
+                .unwrap(),
+            Some(create_expected_proposal())
+        );
+    }
+
+    // Test that the store's state can be exported to a tar archive and imported into a new store
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Export the state to an in-memory tar archive
+    // 3. Import the archive into a store created in a separate empty directory
+    // 4. Validate the circuits, proposals, nodes and services match the original store
+    // 5. Validate importing again without overwrite fails and with overwrite succeeds
+    #[cfg(feature = "admin-store-tar")]
+    #[test]
+    fn test_export_import_tar() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_export_import_tar");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let mut archive = vec![];
+        store
+            .export_tar(&mut archive)
+            .expect("Unable to export tar archive");
+
+        let import_dir =
+            TempDir::new("test_export_import_tar_import").expect("Failed to create temp dir");
+        let (import_store, _) = YamlAdminServiceStore::new(
+            import_dir
+                .path()
+                .join("circuits.yaml")
+                .to_str()
+                .expect("Failed to get path")
+                .to_string(),
+            import_dir
+                .path()
+                .join("circuit_proposals.yaml")
+                .to_str()
+                .expect("Failed to get path")
+                .to_string(),
+        )
+        .expect("Unable to create yaml admin store");
+
+        import_store
+            .import_tar(archive.as_slice(), false)
+            .expect("Unable to import tar archive");
+
+        assert_eq!(
+            import_store
+                .list_circuits(&[])
+                .unwrap()
+                .collect::<Vec<Circuit>>(),
+            vec![create_expected_circuit()]
+        );
+        assert_eq!(
+            import_store
+                .list_proposals(&[])
+                .unwrap()
+                .collect::<Vec<CircuitProposal>>(),
+            vec![create_expected_proposal()]
+        );
+        assert_eq!(
+            import_store.list_nodes().unwrap().collect::<Vec<_>>(),
+            store.list_nodes().unwrap().collect::<Vec<_>>()
+        );
+        assert!(import_store
+            .fetch_service(&ServiceId::new(
+                "a000".to_string(),
+                "WBKLF-AAAAA".to_string()
+            ))
+            .unwrap()
+            .is_some());
+
+        assert!(import_store.import_tar(archive.as_slice(), false).is_err());
+        import_store
+            .import_tar(archive.as_slice(), true)
+            .expect("Unable to import tar archive with overwrite");
+        assert_eq!(import_store.list_circuits(&[]).unwrap().len(), 1);
+    }
+
+    // Test that a tar archive is written in the store's format and that circuits imported from an
+    // archive without creation times are given the archived file's modified time
+    //
+    // 1. Setup the temp directory with existing state and create a store with lowercase enums
+    // 2. Export the state and check the archived circuit state uses lowercase enum values
+    // 3. Build an archive whose circuit state has no creation times and a known modified time
+    // 4. Import the archive and check the circuit's creation time is the modified time
+    #[cfg(feature = "admin-store-tar")]
+    #[test]
+    fn test_export_import_tar_store_format() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_export_import_tar_store_format");
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_enum_case(EnumCase::Lowercase)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        let mut archive = vec![];
+        store
+            .export_tar(&mut archive)
+            .expect("Unable to export tar archive");
+
+        let mut circuit_file = String::new();
+        for entry in tar::Archive::new(archive.as_slice())
+            .entries()
+            .expect("Unable to read tar archive")
+        {
+            let mut entry = entry.expect("Unable to read tar archive entry");
+            if entry.path().expect("Unable to read entry path") == Path::new(CIRCUITS_TAR_ENTRY) {
+                entry
+                    .read_to_string(&mut circuit_file)
+                    .expect("Unable to read archived circuit state");
+            }
+        }
+        assert!(circuit_file.contains("auth: trust"));
+        assert!(!circuit_file.contains("Trust"));
+
+        let legacy_circuit_state = String::from_utf8(CIRCUIT_STATE.to_vec())
+            .expect("Circuit state is not valid UTF-8")
+            .replace("\n        created_at: \"2020-06-01T00:00:00Z\"", "");
+        assert!(!legacy_circuit_state.contains("created_at"));
+        let modified = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+
+        let mut legacy_archive = tar::Builder::new(vec![]);
+        append_tar_entry(
+            &mut legacy_archive,
+            CIRCUITS_TAR_ENTRY,
+            legacy_circuit_state.as_bytes(),
+            modified,
+        )
+        .expect("Unable to append circuit state");
+        append_tar_entry(
+            &mut legacy_archive,
+            PROPOSALS_TAR_ENTRY,
+            PROPOSAL_STATE,
+            modified,
+        )
+        .expect("Unable to append proposal state");
+        let legacy_archive = legacy_archive
+            .into_inner()
+            .expect("Unable to finish tar archive");
+
+        store
+            .import_tar(legacy_archive.as_slice(), true)
+            .expect("Unable to import tar archive");
+        assert_eq!(
+            store
+                .fetch_circuit("WBKLF-AAAAA")
+                .expect("Unable to fetch circuit")
+                .expect("Circuit not found")
+                .created_at(),
+            modified
+        );
+    }
+
+    /// Creates a temp directory with the given name and returns it with the paths of the circuit
+    /// and proposal state files in it. The files are not created.
+    pub(super) fn state_file_paths(name: &str) -> (TempDir, String, String) {
+        let temp_dir = TempDir::new(name).expect("Failed to create temp dir");
+        let path = |file_name: &str| {
+            temp_dir
+                .path()
+                .join(file_name)
+                .to_str()
+                .expect("Failed to get path")
+                .to_string()
+        };
+        let circuit_path = path("circuits.yaml");
+        let proposals_path = path("circuit_proposals.yaml");
+
+        (temp_dir, circuit_path, proposals_path)
+    }
+
+    /// Creates a temp directory with the given name containing the sample circuit and proposal
+    /// state files, and returns it with the paths of the files
+    pub(super) fn setup_state_files(name: &str) -> (TempDir, String, String) {
+        let (temp_dir, circuit_path, proposals_path) = state_file_paths(name);
+        write_file(CIRCUIT_STATE, &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        (temp_dir, circuit_path, proposals_path)
+    }
+
+    /// Returns a store builder for the given circuit and proposal state files
+    pub(super) fn store_builder(
+        circuit_path: &str,
+        proposals_path: &str,
+    ) -> YamlAdminServiceStoreBuilder {
+        YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path(circuit_path)
+            .with_proposal_file_path(proposals_path)
+    }
+
+    pub(super) fn write_file(data: &[u8], file_path: &str) {
         let mut file = File::create(file_path).expect("Error creating test yaml file.");
         file.write_all(data)
             .expect("unable to write test file to temp dir")
     }
 
-    fn create_expected_proposal() -> CircuitProposal {
+    pub(super) fn create_expected_proposal() -> CircuitProposal {
         CircuitProposalBuilder::default()
             .with_proposal_type(&ProposalType::Create)
             .with_circuit_id("WBKLF-BBBBB")
@@ -1700,7 +9274,7 @@ proposals:
             .build().expect("Unable to build proposals")
     }
 
-    fn create_expected_circuit() -> Circuit {
+    pub(super) fn create_expected_circuit() -> Circuit {
         CircuitBuilder::default()
             .with_circuit_id("WBKLF-AAAAA")
             .with_roster(&vec![
@@ -1732,11 +9306,14 @@ proposals:
             ])
             .with_members(&vec!["bubba-node-000".into(), "acme-node-000".into()])
             .with_circuit_management_type("gameroom")
+            .with_created_at(
+                humantime::parse_rfc3339("2020-06-01T00:00:00Z").expect("Invalid timestamp"),
+            )
             .build()
             .expect("Unable to build circuit")
     }
 
-    fn new_proposal() -> CircuitProposal {
+    pub(super) fn new_proposal() -> CircuitProposal {
         CircuitProposalBuilder::default()
             .with_proposal_type(&ProposalType::Create)
             .with_circuit_id("WBKLF-CCCCC")
@@ -1790,7 +9367,7 @@ proposals:
             .build().expect("Unable to build proposals")
     }
 
-    fn new_circuit() -> (Circuit, CircuitNode) {
+    pub(super) fn new_circuit() -> (Circuit, CircuitNode) {
         (CircuitBuilder::default()
             .with_circuit_id("WBKLF-DDDDD")
             .with_roster(&vec![
@@ -1829,4 +9406,49 @@ proposals:
             .with_endpoints(&vec!["tcps://splinterd-node-new:8044".into()])
             .build().expect("Unable to build node"))
     }
+
+    /// A file store whose writes fail with the error made by the given function, until it is
+    /// cleared with `fail_writes(None)`
+    pub(super) struct FailingFileStore {
+        files: MemFileStore,
+        write_error: Mutex<Option<fn() -> io::Error>>,
+    }
+
+    impl FailingFileStore {
+        pub(super) fn new() -> Self {
+            FailingFileStore {
+                files: MemFileStore::new(),
+                write_error: Mutex::new(None),
+            }
+        }
+
+        pub(super) fn fail_writes(&self, write_error: Option<fn() -> io::Error>) {
+            *self.write_error.lock().expect("lock poisoned") = write_error;
+        }
+    }
+
+    impl FileStore for FailingFileStore {
+        fn exists(&self, path: &Path) -> bool {
+            self.files.exists(path)
+        }
+
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.files.read(path)
+        }
+
+        fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            if let Some(write_error) = *self.write_error.lock().expect("lock poisoned") {
+                return Err(write_error());
+            }
+            self.files.write(path, contents)
+        }
+
+        fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+            self.files.modified(path)
+        }
+
+        fn remove(&self, path: &Path) -> io::Result<()> {
+            self.files.remove(path)
+        }
+    }
 }
@@ -0,0 +1,142 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Searching the circuits of a `YamlAdminServiceStore`
+
+use super::CircuitState;
+
+/// A field of a circuit that matched a search query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchHit {
+    /// The ID of the circuit matched
+    CircuitId { circuit_id: String },
+    /// The management type of the circuit matched
+    ManagementType {
+        circuit_id: String,
+        circuit_management_type: String,
+    },
+    /// The ID of a member node of the circuit matched
+    Member { circuit_id: String, node_id: String },
+    /// The ID of a service in the circuit matched
+    ServiceId {
+        circuit_id: String,
+        service_id: String,
+    },
+    /// The type of a service in the circuit matched
+    ServiceType {
+        circuit_id: String,
+        service_id: String,
+        service_type: String,
+    },
+}
+
+/// Returns a hit for each field of the circuits that contains the query, ignoring case
+///
+/// The hits are in circuit ID order. The hits for each circuit are in the order the fields are
+/// listed in `SearchHit`.
+pub(super) fn search_circuits(circuit_state: &CircuitState, query: &str) -> Vec<SearchHit> {
+    let query = query.to_lowercase();
+    let matches = |value: &str| value.to_lowercase().contains(&query);
+
+    let mut hits = vec![];
+    for circuit in circuit_state.circuits.values() {
+        if matches(&circuit.id) {
+            hits.push(SearchHit::CircuitId {
+                circuit_id: circuit.id.to_string(),
+            });
+        }
+
+        if matches(&circuit.circuit_management_type) {
+            hits.push(SearchHit::ManagementType {
+                circuit_id: circuit.id.to_string(),
+                circuit_management_type: circuit.circuit_management_type.to_string(),
+            });
+        }
+
+        for node_id in circuit.members.iter().filter(|node_id| matches(node_id)) {
+            hits.push(SearchHit::Member {
+                circuit_id: circuit.id.to_string(),
+                node_id: node_id.to_string(),
+            });
+        }
+
+        for service in circuit.roster.iter() {
+            if matches(&service.service_id) {
+                hits.push(SearchHit::ServiceId {
+                    circuit_id: circuit.id.to_string(),
+                    service_id: service.service_id.to_string(),
+                });
+            }
+
+            if matches(&service.service_type) {
+                hits.push(SearchHit::ServiceType {
+                    circuit_id: circuit.id.to_string(),
+                    service_id: service.service_id.to_string(),
+                    service_type: service.service_type.to_string(),
+                });
+            }
+        }
+    }
+
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::tests::setup_state_files;
+    use super::super::YamlAdminServiceStore;
+
+    // Test that searching matches circuit and service fields, ignoring case
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Search for "ACME" and verify the circuit's member node is the only hit
+    // 3. Search for "a00" and verify the circuit's services are hits
+    // 4. Search for text that is not in any field and verify there are no hits
+    #[test]
+    fn test_search() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_search");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store.search("ACME").expect("Unable to search"),
+            vec![SearchHit::Member {
+                circuit_id: "WBKLF-AAAAA".to_string(),
+                node_id: "acme-node-000".to_string(),
+            }]
+        );
+
+        assert_eq!(
+            store.search("a00").expect("Unable to search"),
+            vec![
+                SearchHit::ServiceId {
+                    circuit_id: "WBKLF-AAAAA".to_string(),
+                    service_id: "a000".to_string(),
+                },
+                SearchHit::ServiceId {
+                    circuit_id: "WBKLF-AAAAA".to_string(),
+                    service_id: "a001".to_string(),
+                },
+            ]
+        );
+
+        assert!(store
+            .search("unknown")
+            .expect("Unable to search")
+            .is_empty());
+    }
+}
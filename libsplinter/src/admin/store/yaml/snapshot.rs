@@ -0,0 +1,280 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A point-in-time copy of the state cached by the `YamlAdminServiceStore`
+
+use std::sync::Arc;
+
+use super::YamlState;
+use crate::admin::store::{
+    AdminServiceStoreError, Circuit, CircuitNode, CircuitPredicate, CircuitProposal, Service,
+    ServiceId,
+};
+
+/// A frozen copy of the circuit and proposal state of a `YamlAdminServiceStore`
+///
+/// A snapshot is taken from a single load of the store's state, so every query made against it
+/// observes the same state, regardless of changes made to the store afterwards.
+#[derive(Debug, Clone)]
+pub struct StoreSnapshot {
+    state: Arc<YamlState>,
+}
+
+impl StoreSnapshot {
+    pub(super) fn new(state: Arc<YamlState>) -> Self {
+        StoreSnapshot { state }
+    }
+
+    /// Fetches a circuit proposal from the snapshot
+    ///
+    /// # Arguments
+    ///
+    ///  * `proposal_id` - The unique ID of the circuit proposal to be returned
+    pub fn fetch_proposal(&self, proposal_id: &str) -> Option<CircuitProposal> {
+        self.state
+            .proposal_state
+            .proposals
+            .get(proposal_id)
+            .cloned()
+    }
+
+    /// List circuit proposals from the snapshot
+    ///
+    /// The proposals returned can be filtered by provided `CircuitPredicate`. This enables
+    /// filtering by management type and members.
+    pub fn list_proposals(
+        &self,
+        predicates: &[CircuitPredicate],
+    ) -> Box<dyn ExactSizeIterator<Item = CircuitProposal>> {
+        let proposals: Vec<CircuitProposal> = self
+            .state
+            .proposal_state
+            .proposals
+            .values()
+            .filter(|proposal| {
+                predicates
+                    .iter()
+                    .all(|predicate| predicate.apply_to_proposals(proposal))
+            })
+            .cloned()
+            .collect();
+
+        Box::new(proposals.into_iter())
+    }
+
+    /// Fetches a circuit from the snapshot
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_id` - The unique ID of the circuit to be returned
+    pub fn fetch_circuit(&self, circuit_id: &str) -> Option<Circuit> {
+        self.state.circuit_state.circuits.get(circuit_id).cloned()
+    }
+
+    /// List all circuits from the snapshot
+    ///
+    /// The circuits returned can be filtered by provided `CircuitPredicate`. This enables
+    /// filtering by management type and members.
+    pub fn list_circuits(
+        &self,
+        predicates: &[CircuitPredicate],
+    ) -> Box<dyn ExactSizeIterator<Item = Circuit>> {
+        let circuits: Vec<Circuit> = self
+            .state
+            .circuit_state
+            .circuits
+            .values()
+            .filter(|circuit| {
+                predicates
+                    .iter()
+                    .all(|predicate| predicate.apply_to_circuit(circuit))
+            })
+            .cloned()
+            .collect();
+
+        Box::new(circuits.into_iter())
+    }
+
+    /// Fetches a node from the snapshot
+    ///
+    /// # Arguments
+    ///
+    ///  * `node_id` - The unique ID of the node to be returned
+    pub fn fetch_node(&self, node_id: &str) -> Option<CircuitNode> {
+        self.state.circuit_state.nodes.get(node_id).cloned()
+    }
+
+    /// List all nodes from the snapshot
+    pub fn list_nodes(&self) -> Box<dyn ExactSizeIterator<Item = CircuitNode>> {
+        let nodes: Vec<CircuitNode> = self.state.circuit_state.nodes.values().cloned().collect();
+
+        Box::new(nodes.into_iter())
+    }
+
+    /// Fetches a service from the snapshot
+    ///
+    /// # Arguments
+    ///
+    ///  * `service_id` - The `ServiceId` of a service made up of the circuit ID and service ID
+    pub fn fetch_service(&self, service_id: &ServiceId) -> Option<Service> {
+        self.state.service_directory.get(service_id).cloned()
+    }
+
+    /// List all services in a specific circuit from the snapshot
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_id` - The unique ID of the circuit the services belong to
+    ///
+    /// Returns an error if the circuit does not exist in the snapshot
+    pub fn list_services(
+        &self,
+        circuit_id: &str,
+    ) -> Result<Box<dyn ExactSizeIterator<Item = Service>>, AdminServiceStoreError> {
+        let services = self
+            .state
+            .circuit_state
+            .circuits
+            .get(circuit_id)
+            .ok_or_else(|| AdminServiceStoreError::OperationError {
+                context: format!("Circuit {} does not exist", circuit_id),
+                source: None,
+            })?
+            .roster
+            .clone();
+
+        Ok(Box::new(services.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::tests::{
+        create_expected_circuit, create_expected_proposal, new_circuit, new_proposal,
+        setup_state_files,
+    };
+    use super::super::YamlAdminServiceStore;
+    use crate::admin::store::AdminServiceStore;
+
+    // Test that a snapshot of the store is not affected by changes made to the store after the
+    // snapshot was taken.
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Take a snapshot of the store
+    // 3. Remove the circuit and proposal, and add a new proposal to the store
+    // 4. Validate the snapshot still returns the original circuit, proposal, nodes and services
+    #[test]
+    fn test_snapshot() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_snapshot");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let snapshot = store.snapshot().expect("Unable to take snapshot");
+
+        store
+            .remove_circuit("WBKLF-AAAAA")
+            .expect("Unable to remove circuit");
+        store
+            .remove_proposal("WBKLF-BBBBB")
+            .expect("Unable to remove proposal");
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+
+        assert_eq!(store.fetch_circuit("WBKLF-AAAAA").unwrap(), None);
+        assert_eq!(
+            snapshot.fetch_circuit("WBKLF-AAAAA"),
+            Some(create_expected_circuit())
+        );
+        assert_eq!(
+            snapshot.list_circuits(&[]).collect::<Vec<Circuit>>(),
+            vec![create_expected_circuit()]
+        );
+        assert_eq!(
+            snapshot
+                .list_proposals(&[])
+                .collect::<Vec<CircuitProposal>>(),
+            vec![create_expected_proposal()]
+        );
+        assert_eq!(snapshot.fetch_proposal("WBKLF-CCCCC"), None);
+        assert_eq!(snapshot.list_nodes().len(), 2);
+        assert!(snapshot
+            .fetch_service(&ServiceId::new(
+                "a000".to_string(),
+                "WBKLF-AAAAA".to_string()
+            ))
+            .is_some());
+        assert_eq!(
+            snapshot
+                .list_services("WBKLF-AAAAA")
+                .expect("Unable to list services")
+                .len(),
+            2
+        );
+    }
+
+    // Test that exporting circuits by management type includes only the matching circuits and
+    // their member nodes
+    //
+    // 1. Setup the temp directory with existing state and add a circuit with a new node
+    // 2. Export the circuits with the management type of the new circuit
+    // 3. Check that only the new circuit, its members and its services are in the snapshot
+    #[test]
+    fn test_export_circuits() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_export_circuits");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let (circuit, node) = new_circuit();
+        store
+            .add_circuit(circuit.clone(), vec![node])
+            .expect("Unable to add circuit");
+
+        let snapshot = store
+            .export_circuits(&[CircuitPredicate::ManagmentTypeEq(
+                circuit.circuit_management_type.to_string(),
+            )])
+            .expect("Unable to export circuits");
+
+        assert_eq!(
+            snapshot
+                .list_circuits(&[])
+                .map(|circuit| circuit.id)
+                .collect::<Vec<_>>(),
+            vec![circuit.id.to_string()]
+        );
+
+        let mut node_ids: Vec<String> = snapshot.list_nodes().map(|node| node.id).collect();
+        node_ids.sort();
+        let mut expected_node_ids = circuit.members.clone();
+        expected_node_ids.sort();
+        assert_eq!(node_ids, expected_node_ids);
+
+        assert!(snapshot
+            .fetch_service(&ServiceId::new("a000".to_string(), circuit.id.to_string()))
+            .is_some());
+        assert!(snapshot
+            .fetch_service(&ServiceId::new(
+                "a000".to_string(),
+                "WBKLF-AAAAA".to_string()
+            ))
+            .is_none());
+        assert!(snapshot.fetch_circuit("WBKLF-AAAAA").is_none());
+        assert_eq!(snapshot.list_proposals(&[]).count(), 0);
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming export and import of the state of a `YamlAdminServiceStore`
+//!
+//! The state is written as one JSON record per line for each node, circuit and proposal, so it
+//! can be written and read a record at a time.
+
+use std::io::{Read, Write};
+
+use super::{CircuitState, ProposalState, YamlState};
+use crate::admin::store::{AdminServiceStoreError, Circuit, CircuitNode, CircuitProposal};
+
+/// A record written to an export stream
+#[derive(Serialize)]
+enum ExportRecord<'a> {
+    Node(&'a CircuitNode),
+    Circuit(&'a Circuit),
+    Proposal(&'a CircuitProposal),
+}
+
+/// A record read from an export stream, which has the same representation as an `ExportRecord`
+#[derive(Deserialize)]
+enum ImportRecord {
+    Node(CircuitNode),
+    Circuit(Circuit),
+    Proposal(CircuitProposal),
+}
+
+/// Writes a record for each node, circuit and proposal in the given state to a writer
+///
+/// # Arguments
+///
+///  * `state` - The state to export
+///  * `writer` - The writer the records are written to
+pub(super) fn export_records(
+    state: &YamlState,
+    mut writer: impl Write,
+) -> Result<(), AdminServiceStoreError> {
+    let records = state
+        .circuit_state
+        .nodes
+        .values()
+        .map(ExportRecord::Node)
+        .chain(
+            state
+                .circuit_state
+                .circuits
+                .values()
+                .map(ExportRecord::Circuit),
+        )
+        .chain(
+            state
+                .proposal_state
+                .proposals_by_submission_order()
+                .into_iter()
+                .map(ExportRecord::Proposal),
+        );
+
+    for record in records {
+        serde_json::to_writer(&mut writer, &record).map_err(|err| {
+            AdminServiceStoreError::StorageError {
+                context: "Failed to write export record".to_string(),
+                source: Some(Box::new(err)),
+            }
+        })?;
+
+        writer
+            .write_all(b"\n")
+            .map_err(|err| AdminServiceStoreError::StorageError {
+                context: "Failed to write export record".to_string(),
+                source: Some(Box::new(err)),
+            })?;
+    }
+
+    writer
+        .flush()
+        .map_err(|err| AdminServiceStoreError::StorageError {
+            context: "Failed to flush export".to_string(),
+            source: Some(Box::new(err)),
+        })
+}
+
+/// Reads the records written by `export_records` from a reader, one record at a time
+///
+/// # Arguments
+///
+///  * `reader` - The reader the records are read from
+///
+/// Returns the circuit and proposal state made up of the records
+pub(super) fn import_records(
+    reader: impl Read,
+) -> Result<(CircuitState, ProposalState), AdminServiceStoreError> {
+    let mut circuit_state = CircuitState::default();
+    let mut proposal_state = ProposalState::default();
+
+    for record in serde_json::Deserializer::from_reader(reader).into_iter::<ImportRecord>() {
+        let record = record.map_err(|err| AdminServiceStoreError::StorageError {
+            context: "Failed to read export record".to_string(),
+            source: Some(Box::new(err)),
+        })?;
+
+        match record {
+            ImportRecord::Node(node) => {
+                circuit_state.nodes.insert(node.id.to_string(), node);
+            }
+            ImportRecord::Circuit(circuit) => {
+                circuit_state
+                    .circuits
+                    .insert(circuit.id.to_string(), circuit);
+            }
+            ImportRecord::Proposal(proposal) => {
+                proposal_state.insert_proposal(proposal);
+            }
+        }
+    }
+
+    Ok((circuit_state, proposal_state))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use tempdir::TempDir;
+
+    use super::super::tests::{new_circuit, setup_state_files, store_builder};
+    use super::super::{FixedClock, YamlAdminServiceStore};
+    use crate::admin::store::AdminServiceStore;
+
+    // Test that a store with many circuits can be exported to a writer and imported into a new
+    // store from a reader
+    //
+    // 1. Setup the temp directory with existing state and create a store with a fixed clock
+    // 2. Add many circuits to the store
+    // 3. Export the state to an in-memory buffer
+    // 4. Import the buffer into a store created in a separate empty directory
+    // 5. Validate the circuits, proposals and nodes match the original store
+    #[test]
+    fn test_export_import_stream() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_export_import_stream");
+
+        // circuit times are exported to the second, so use a clock without fractional seconds
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_clock(Arc::new(FixedClock::new(
+                UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+            )))
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        for i in 0..100 {
+            let (mut circuit, node) = new_circuit();
+            circuit.id = format!("WBKLF-{:05}", i);
+            store
+                .add_circuit(circuit, vec![node])
+                .expect("Unable to add circuit");
+        }
+
+        let mut buffer = vec![];
+        store
+            .export_to_writer(&mut buffer)
+            .expect("Unable to export state");
+
+        let import_dir =
+            TempDir::new("test_export_import_stream_import").expect("Failed to create temp dir");
+        let (import_store, _) = YamlAdminServiceStore::new(
+            import_dir
+                .path()
+                .join("circuits.yaml")
+                .to_str()
+                .expect("Failed to get path")
+                .to_string(),
+            import_dir
+                .path()
+                .join("circuit_proposals.yaml")
+                .to_str()
+                .expect("Failed to get path")
+                .to_string(),
+        )
+        .expect("Unable to create yaml admin store");
+
+        import_store
+            .import_from_reader(buffer.as_slice(), false)
+            .expect("Unable to import state");
+
+        assert_eq!(import_store.list_circuits(&[]).unwrap().len(), 101);
+        assert_eq!(
+            import_store.list_circuits(&[]).unwrap().collect::<Vec<_>>(),
+            store.list_circuits(&[]).unwrap().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            import_store
+                .list_proposals(&[])
+                .unwrap()
+                .collect::<Vec<_>>(),
+            store.list_proposals(&[]).unwrap().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            import_store.list_nodes().unwrap().collect::<Vec<_>>(),
+            store.list_nodes().unwrap().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Counts and progress of the votes cast on the proposals of a `YamlAdminServiceStore`
+
+use std::collections::BTreeMap;
+
+use crate::admin::store::{CircuitProposal, Vote};
+
+/// The number of votes of each kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VoteCounts {
+    /// The number of votes to accept
+    pub accept: usize,
+    /// The number of votes to reject
+    pub reject: usize,
+}
+
+impl VoteCounts {
+    /// Returns the total number of votes
+    pub fn total(&self) -> usize {
+        self.accept + self.reject
+    }
+
+    fn add(&mut self, vote: &Vote) {
+        match vote {
+            Vote::Accept => self.accept += 1,
+            Vote::Reject => self.reject += 1,
+        }
+    }
+}
+
+/// The votes cast on a set of proposals, in total and for each circuit management type
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VoteTally {
+    total: VoteCounts,
+    by_management_type: BTreeMap<String, VoteCounts>,
+}
+
+impl VoteTally {
+    /// Returns the counts of the votes cast on all of the proposals
+    pub fn total(&self) -> VoteCounts {
+        self.total
+    }
+
+    /// Returns the counts of the votes cast on proposals for circuits with the given management
+    /// type. Management types without any votes have zero counts.
+    ///
+    /// # Arguments
+    ///
+    ///  * `circuit_management_type` - The management type of the proposed circuits
+    pub fn by_management_type(&self, circuit_management_type: &str) -> VoteCounts {
+        self.by_management_type
+            .get(circuit_management_type)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns an iterator over the management types that have votes, in order, and their counts
+    pub fn management_types(&self) -> impl Iterator<Item = (&str, VoteCounts)> {
+        self.by_management_type
+            .iter()
+            .map(|(management_type, counts)| (management_type.as_str(), *counts))
+    }
+
+    pub(super) fn add(&mut self, circuit_management_type: &str, vote: &Vote) {
+        self.total.add(vote);
+        self.by_management_type
+            .entry(circuit_management_type.to_string())
+            .or_default()
+            .add(vote);
+    }
+}
+
+/// The progress of the vote on a proposal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoteProgress {
+    members: usize,
+    accepted: usize,
+    rejected: usize,
+    remaining_voters: Vec<String>,
+}
+
+impl VoteProgress {
+    /// Computes the progress of the vote on a proposal. Every member node votes, including the
+    /// requester's node.
+    pub(super) fn new(proposal: &CircuitProposal) -> Self {
+        let counts =
+            proposal
+                .votes
+                .iter()
+                .fold(VoteCounts::default(), |mut counts, vote_record| {
+                    counts.add(&vote_record.vote);
+                    counts
+                });
+
+        let remaining_voters = proposal
+            .circuit
+            .members
+            .iter()
+            .filter(|member| {
+                !proposal
+                    .votes
+                    .iter()
+                    .any(|vote_record| vote_record.voter_node_id == member.node_id)
+            })
+            .map(|member| member.node_id.to_string())
+            .collect();
+
+        VoteProgress {
+            members: proposal.circuit.members.len(),
+            accepted: counts.accept,
+            rejected: counts.reject,
+            remaining_voters,
+        }
+    }
+
+    /// Returns the number of members of the proposed circuit, including the requester's node
+    pub fn members(&self) -> usize {
+        self.members
+    }
+
+    /// Returns the number of votes to accept the proposal
+    pub fn accepted(&self) -> usize {
+        self.accepted
+    }
+
+    /// Returns the number of votes to reject the proposal
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// Returns the IDs of the member nodes that have not yet voted, in member order
+    pub fn remaining_voters(&self) -> &[String] {
+        &self.remaining_voters
+    }
+
+    /// Returns whether every member node, including the requester's node, has voted to accept
+    /// the proposal and no node has voted to reject it, so it can be upgraded to a circuit
+    pub fn is_upgradeable(&self) -> bool {
+        self.rejected == 0 && self.remaining_voters.is_empty()
+    }
+}
+
+/// A proposal together with the progress of the vote on it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposalWithProgress {
+    proposal: CircuitProposal,
+    progress: VoteProgress,
+}
+
+impl ProposalWithProgress {
+    pub(super) fn new(proposal: CircuitProposal) -> Self {
+        let progress = VoteProgress::new(&proposal);
+        ProposalWithProgress { proposal, progress }
+    }
+
+    /// Returns the proposal
+    pub fn proposal(&self) -> &CircuitProposal {
+        &self.proposal
+    }
+
+    /// Returns the progress of the vote on the proposal
+    pub fn progress(&self) -> &VoteProgress {
+        &self.progress
+    }
+
+    /// Returns the proposal, consuming the `ProposalWithProgress`
+    pub fn into_proposal(self) -> CircuitProposal {
+        self.proposal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::tests::{
+        create_expected_proposal, new_proposal, setup_state_files, CIRCUIT_STATE, PROPOSAL_STATE,
+    };
+    use super::super::YamlAdminServiceStore;
+    use crate::admin::store::{AdminServiceStore, VoteRecord};
+
+    // Test that the votes on all proposals are counted in total and by management type
+    //
+    // 1. Setup the temp directory with existing state, which has a proposal without votes
+    // 2. Add two proposals with the "test" management type, one with an accepting vote and one
+    //    with an accepting and a rejecting vote, and a "gameroom" proposal with an accepting vote
+    // 3. Verify the total and per management type counts
+    #[test]
+    fn test_vote_tally() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_vote_tally");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        assert_eq!(
+            store.vote_tally().expect("Unable to tally votes"),
+            VoteTally::default()
+        );
+
+        let vote = |vote, voter_node_id: &str| VoteRecord {
+            public_key: vec![1, 2, 3],
+            vote,
+            voter_node_id: voter_node_id.to_string(),
+        };
+
+        let mut accepted = new_proposal();
+        accepted.add_vote(vote(Vote::Accept, "bubba-node-000"));
+        store
+            .add_proposal(accepted)
+            .expect("Unable to add proposal");
+
+        let mut rejected = new_proposal();
+        rejected.circuit_id = "WBKLF-EEEEE".to_string();
+        rejected.add_vote(vote(Vote::Accept, "bubba-node-000"));
+        rejected.add_vote(vote(Vote::Reject, "acme-node-000"));
+        store
+            .add_proposal(rejected)
+            .expect("Unable to add proposal");
+
+        let mut gameroom = create_expected_proposal();
+        gameroom.circuit_id = "WBKLF-FFFFF".to_string();
+        gameroom.add_vote(vote(Vote::Accept, "bubba-node-000"));
+        store
+            .add_proposal(gameroom)
+            .expect("Unable to add proposal");
+
+        let tally = store.vote_tally().expect("Unable to tally votes");
+        assert_eq!(
+            tally.total(),
+            VoteCounts {
+                accept: 3,
+                reject: 1
+            }
+        );
+        assert_eq!(
+            tally.by_management_type("test"),
+            VoteCounts {
+                accept: 2,
+                reject: 1
+            }
+        );
+        assert_eq!(
+            tally.by_management_type("gameroom"),
+            VoteCounts {
+                accept: 1,
+                reject: 0
+            }
+        );
+        assert_eq!(tally.by_management_type("unknown").total(), 0);
+        assert_eq!(
+            tally
+                .management_types()
+                .map(|(management_type, _)| management_type)
+                .collect::<Vec<_>>(),
+            vec!["gameroom", "test"]
+        );
+    }
+
+    // Test that proposals are counted by the management type of their proposed circuit
+    //
+    // 1. Create a store from the existing state, which has one gameroom proposal
+    // 2. Add two proposals with the management type test
+    // 3. Verify the counts for each management type
+    #[test]
+    fn test_proposal_counts_by_management_type() {
+        let store = YamlAdminServiceStore::from_bytes(CIRCUIT_STATE, PROPOSAL_STATE)
+            .expect("Unable to create yaml admin store");
+
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+        let mut proposal = new_proposal();
+        proposal.circuit_id = "WBKLF-EEEEE".to_string();
+        store
+            .add_proposal(proposal)
+            .expect("Unable to add proposal");
+
+        let mut expected = BTreeMap::new();
+        expected.insert("gameroom".to_string(), 1);
+        expected.insert("test".to_string(), 2);
+        assert_eq!(
+            store
+                .proposal_counts_by_management_type()
+                .expect("Unable to count proposals"),
+            expected
+        );
+    }
+
+    // Test that a proposal is fetched with the progress of the vote on it
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Fetch the existing proposal, which has no votes, and verify all of its members remain
+    //    to vote
+    // 3. Add a vote to the proposal and verify it is counted, the voter no longer remains and
+    //    the proposal is not upgradeable until the requester has voted
+    // 4. Add the requester's vote and verify the proposal is upgradeable
+    // 5. Verify fetching an unknown proposal returns None
+    #[test]
+    fn test_fetch_proposal_with_progress() {
+        let (_temp_dir, circuit_path, proposals_path) =
+            setup_state_files("test_fetch_proposal_with_progress");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+
+        let with_progress = store
+            .fetch_proposal_with_progress("WBKLF-BBBBB")
+            .expect("Unable to fetch proposal")
+            .expect("Proposal not found");
+
+        assert_eq!(with_progress.proposal(), &create_expected_proposal());
+        assert_eq!(with_progress.progress().members(), 2);
+        assert_eq!(with_progress.progress().accepted(), 0);
+        assert_eq!(with_progress.progress().rejected(), 0);
+        assert_eq!(
+            with_progress.progress().remaining_voters(),
+            &["bubba-node-000".to_string(), "acme-node-000".to_string()]
+        );
+        assert!(!with_progress.progress().is_upgradeable());
+
+        let mut proposal = with_progress.into_proposal();
+        proposal.add_vote(VoteRecord {
+            public_key: vec![1, 2, 3],
+            vote: Vote::Accept,
+            voter_node_id: "bubba-node-000".to_string(),
+        });
+        store
+            .update_proposal(proposal)
+            .expect("Unable to update proposal");
+
+        let progress = store
+            .fetch_proposal_with_progress("WBKLF-BBBBB")
+            .expect("Unable to fetch proposal")
+            .expect("Proposal not found")
+            .progress()
+            .clone();
+
+        assert_eq!(progress.accepted(), 1);
+        assert_eq!(progress.remaining_voters(), &["acme-node-000".to_string()]);
+        assert!(!progress.is_upgradeable());
+
+        let mut proposal = store
+            .get_proposal("WBKLF-BBBBB")
+            .expect("Unable to fetch proposal");
+        proposal.add_vote(VoteRecord {
+            public_key: vec![1, 2, 3],
+            vote: Vote::Accept,
+            voter_node_id: "acme-node-000".to_string(),
+        });
+        store
+            .update_proposal(proposal)
+            .expect("Unable to update proposal");
+
+        let progress = store
+            .fetch_proposal_with_progress("WBKLF-BBBBB")
+            .expect("Unable to fetch proposal")
+            .expect("Proposal not found")
+            .progress()
+            .clone();
+
+        assert_eq!(progress.accepted(), 2);
+        assert!(progress.remaining_voters().is_empty());
+        assert!(progress.is_upgradeable());
+
+        assert!(store
+            .fetch_proposal_with_progress("WBKLF-ZZZZZ")
+            .expect("Unable to fetch proposal")
+            .is_none());
+    }
+}
@@ -0,0 +1,455 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of circuit state files outside of a `YamlAdminServiceStore`, and of the proposals
+//! held by a store
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+
+use super::error::YamlAdminStoreError;
+use super::{FileFormat, YamlCircuitState};
+use crate::admin::store::{Circuit, CircuitProposal};
+
+/// The length of a compressed secp256k1 public key, the form of a proposal's requester key
+const PUBLIC_KEY_LENGTH: usize = 33;
+
+/// A referential-consistency issue found in a circuit state file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IntegrityWarning {
+    /// A circuit is stored under a key that differs from its ID
+    MismatchedCircuitId { key: String, circuit_id: String },
+    /// A circuit member is not in the file's nodes
+    UnknownMember { circuit_id: String, node_id: String },
+    /// A service is allowed to run on a node that is not a member of its circuit
+    AllowedNodeNotMember {
+        circuit_id: String,
+        service_id: String,
+        node_id: String,
+    },
+    /// More than one service in a circuit's roster has the same ID
+    DuplicateService {
+        circuit_id: String,
+        service_id: String,
+    },
+}
+
+impl fmt::Display for IntegrityWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrityWarning::MismatchedCircuitId { key, circuit_id } => {
+                write!(f, "Circuit {} is stored under the key {}", circuit_id, key)
+            }
+            IntegrityWarning::UnknownMember {
+                circuit_id,
+                node_id,
+            } => write!(
+                f,
+                "Circuit {} has member {} which is not a known node",
+                circuit_id, node_id
+            ),
+            IntegrityWarning::AllowedNodeNotMember {
+                circuit_id,
+                service_id,
+                node_id,
+            } => write!(
+                f,
+                "Service {} in circuit {} is allowed on node {} which is not a circuit member",
+                service_id, circuit_id, node_id
+            ),
+            IntegrityWarning::DuplicateService {
+                circuit_id,
+                service_id,
+            } => write!(
+                f,
+                "Circuit {} has more than one service with ID {}",
+                circuit_id, service_id
+            ),
+        }
+    }
+}
+
+/// An issue found in a proposal held by a `YamlAdminServiceStore`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProposalWarning {
+    /// A proposal is stored under a key that differs from its circuit ID
+    MismatchedProposalId { key: String, circuit_id: String },
+    /// The ID of a proposal differs from the ID of the circuit it proposes
+    MismatchedProposedCircuitId {
+        proposal_id: String,
+        proposed_circuit_id: String,
+    },
+    /// A node has voted on a proposal more than once
+    DuplicateVoter {
+        proposal_id: String,
+        node_id: String,
+    },
+    /// A node that is not a member of the proposed circuit has voted on a proposal
+    NonMemberVoter {
+        proposal_id: String,
+        node_id: String,
+    },
+    /// The requester of a proposal is not a compressed public key
+    MalformedRequesterKey { proposal_id: String },
+}
+
+impl fmt::Display for ProposalWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProposalWarning::MismatchedProposalId { key, circuit_id } => {
+                write!(f, "Proposal {} is stored under the key {}", circuit_id, key)
+            }
+            ProposalWarning::MismatchedProposedCircuitId {
+                proposal_id,
+                proposed_circuit_id,
+            } => write!(
+                f,
+                "Proposal {} proposes a circuit with ID {}",
+                proposal_id, proposed_circuit_id
+            ),
+            ProposalWarning::DuplicateVoter {
+                proposal_id,
+                node_id,
+            } => write!(
+                f,
+                "Node {} has voted on proposal {} more than once",
+                node_id, proposal_id
+            ),
+            ProposalWarning::NonMemberVoter {
+                proposal_id,
+                node_id,
+            } => write!(
+                f,
+                "Node {} has voted on proposal {} but is not a member of the proposed circuit",
+                node_id, proposal_id
+            ),
+            ProposalWarning::MalformedRequesterKey { proposal_id } => write!(
+                f,
+                "Proposal {} has a requester that is not a compressed public key",
+                proposal_id
+            ),
+        }
+    }
+}
+
+/// Validates a YAML circuit state file without creating a `YamlAdminServiceStore`
+///
+/// The file is parsed and its circuits are checked against its nodes and their own members. The
+/// proposal state file is not read.
+///
+/// # Arguments
+///
+///  * `path` - The path to the circuit state file
+///
+/// Returns the issues found in the file, or an error if the file cannot be read or parsed
+pub fn validate_circuit_file(path: &Path) -> Result<Vec<IntegrityWarning>, YamlAdminStoreError> {
+    let circuit_file = File::open(path).map_err(|err| {
+        YamlAdminStoreError::general_error_with_source(
+            "Failed to open YAML circuit state file",
+            Box::new(err),
+        )
+    })?;
+
+    let circuit_state: YamlCircuitState =
+        FileFormat::Yaml.read(circuit_file, "circuit state file")?;
+
+    let mut warnings = vec![];
+    for (key, circuit) in circuit_state.circuits.iter() {
+        if key != &circuit.id {
+            warnings.push(IntegrityWarning::MismatchedCircuitId {
+                key: key.to_string(),
+                circuit_id: circuit.id.to_string(),
+            });
+        }
+
+        for node_id in circuit.members.iter() {
+            if !circuit_state.nodes.contains_key(node_id) {
+                warnings.push(IntegrityWarning::UnknownMember {
+                    circuit_id: circuit.id.to_string(),
+                    node_id: node_id.to_string(),
+                });
+            }
+        }
+
+        let mut service_ids = BTreeSet::new();
+        for service in circuit.roster.iter() {
+            if !service_ids.insert(&service.service_id) {
+                warnings.push(IntegrityWarning::DuplicateService {
+                    circuit_id: circuit.id.to_string(),
+                    service_id: service.service_id.to_string(),
+                });
+            }
+
+            for node_id in service.allowed_nodes.iter() {
+                if !circuit.members.contains(node_id) {
+                    warnings.push(IntegrityWarning::AllowedNodeNotMember {
+                        circuit_id: circuit.id.to_string(),
+                        service_id: service.service_id.to_string(),
+                        node_id: node_id.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Returns an `IntegrityWarning::AllowedNodeNotMember` for each node a service in the circuit's
+/// roster is allowed on that is not a member of the circuit, in roster order. This is the check
+/// `validate_circuit_file` makes, applied to a circuit being written by the store.
+pub(super) fn allowed_nodes_not_members(circuit: &Circuit) -> Vec<IntegrityWarning> {
+    circuit
+        .roster
+        .iter()
+        .flat_map(|service| {
+            service
+                .allowed_nodes
+                .iter()
+                .filter(move |node_id| !circuit.members.contains(node_id))
+                .map(move |node_id| IntegrityWarning::AllowedNodeNotMember {
+                    circuit_id: circuit.id.to_string(),
+                    service_id: service.service_id.to_string(),
+                    node_id: node_id.to_string(),
+                })
+        })
+        .collect()
+}
+
+/// Returns the issues found in a proposal, in the order the variants of `ProposalWarning` are
+/// listed. The issues for each vote are in vote order.
+///
+/// # Arguments
+///
+///  * `key` - The key the proposal is stored under
+///  * `proposal` - The proposal to check
+pub(super) fn audit_proposal(key: &str, proposal: &CircuitProposal) -> Vec<ProposalWarning> {
+    let mut warnings = vec![];
+    if key != proposal.circuit_id {
+        warnings.push(ProposalWarning::MismatchedProposalId {
+            key: key.to_string(),
+            circuit_id: proposal.circuit_id.to_string(),
+        });
+    }
+
+    if proposal.circuit.circuit_id != proposal.circuit_id {
+        warnings.push(ProposalWarning::MismatchedProposedCircuitId {
+            proposal_id: proposal.circuit_id.to_string(),
+            proposed_circuit_id: proposal.circuit.circuit_id.to_string(),
+        });
+    }
+
+    let mut voters = BTreeSet::new();
+    for vote_record in proposal.votes.iter() {
+        if !voters.insert(&vote_record.voter_node_id) {
+            warnings.push(ProposalWarning::DuplicateVoter {
+                proposal_id: proposal.circuit_id.to_string(),
+                node_id: vote_record.voter_node_id.to_string(),
+            });
+        }
+    }
+
+    for vote_record in proposal.votes.iter() {
+        let is_member = proposal
+            .circuit
+            .members
+            .iter()
+            .any(|member| member.node_id == vote_record.voter_node_id);
+        if !is_member {
+            warnings.push(ProposalWarning::NonMemberVoter {
+                proposal_id: proposal.circuit_id.to_string(),
+                node_id: vote_record.voter_node_id.to_string(),
+            });
+        }
+    }
+
+    let is_compressed_key = proposal.requester.len() == PUBLIC_KEY_LENGTH
+        && (proposal.requester[0] == 0x02 || proposal.requester[0] == 0x03);
+    if !is_compressed_key {
+        warnings.push(ProposalWarning::MalformedRequesterKey {
+            proposal_id: proposal.circuit_id.to_string(),
+        });
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempdir::TempDir;
+
+    use super::super::tests::{
+        create_expected_proposal, new_proposal, write_file, CIRCUIT_STATE, PROPOSAL_STATE,
+    };
+    use super::super::YamlAdminServiceStore;
+    use crate::admin::store::{AdminServiceStore, Vote, VoteRecord};
+
+    // Test that a circuit state file can be validated without creating a store
+    //
+    // 1. Setup the temp directory with the sample circuit state and verify it has no warnings
+    // 2. Write a circuit state file with an unknown member, a service allowed on a non-member
+    //    node, a duplicate service and a circuit stored under the wrong key
+    // 3. Verify each issue is reported as a warning
+    // 4. Verify a file that cannot be parsed returns an error
+    #[test]
+    fn test_validate_circuit_file() {
+        // create temp dir
+        let temp_dir =
+            TempDir::new("test_validate_circuit_file").expect("Failed to create temp dir");
+        let circuit_path = temp_dir.path().join("circuits.yaml");
+
+        write_file(
+            CIRCUIT_STATE,
+            circuit_path.to_str().expect("Failed to get path"),
+        );
+        assert_eq!(
+            validate_circuit_file(&circuit_path).expect("Unable to validate circuit file"),
+            vec![]
+        );
+
+        write_file(
+            b"---
+nodes:
+    acme-node-000:
+        id: acme-node-000
+        endpoints:
+          - \"tcps://splinterd-node-acme:8044\"
+circuits:
+    WBKLF-ZZZZZ:
+        id: WBKLF-AAAAA
+        auth: Trust
+        members:
+          - bubba-node-000
+          - acme-node-000
+        roster:
+          - service_id: a000
+            service_type: scabbard
+            allowed_nodes:
+              - acme-node-000
+            arguments: {}
+          - service_id: a000
+            service_type: scabbard
+            allowed_nodes:
+              - other-node-000
+            arguments: {}
+        persistence: Any
+        durability: NoDurability
+        routes: Any
+        circuit_management_type: gameroom",
+            circuit_path.to_str().expect("Failed to get path"),
+        );
+
+        assert_eq!(
+            validate_circuit_file(&circuit_path).expect("Unable to validate circuit file"),
+            vec![
+                IntegrityWarning::MismatchedCircuitId {
+                    key: "WBKLF-ZZZZZ".to_string(),
+                    circuit_id: "WBKLF-AAAAA".to_string(),
+                },
+                IntegrityWarning::UnknownMember {
+                    circuit_id: "WBKLF-AAAAA".to_string(),
+                    node_id: "bubba-node-000".to_string(),
+                },
+                IntegrityWarning::DuplicateService {
+                    circuit_id: "WBKLF-AAAAA".to_string(),
+                    service_id: "a000".to_string(),
+                },
+                IntegrityWarning::AllowedNodeNotMember {
+                    circuit_id: "WBKLF-AAAAA".to_string(),
+                    service_id: "a000".to_string(),
+                    node_id: "other-node-000".to_string(),
+                },
+            ]
+        );
+
+        write_file(
+            b"circuits: [",
+            circuit_path.to_str().expect("Failed to get path"),
+        );
+        assert!(validate_circuit_file(&circuit_path).is_err());
+    }
+
+    // Test that auditing the proposals reports each kind of problem, without rejecting the
+    // proposals
+    //
+    // 1. Create a store from proposal state with a proposal stored under the wrong key
+    // 2. Add a proposal without problems, a proposal for a circuit with a different ID, and a
+    //    proposal with a duplicate vote, a vote from a non-member and a malformed requester key
+    // 3. Verify each problem is reported, in proposal ID order
+    #[test]
+    fn test_audit_proposals() {
+        let proposal_state = String::from_utf8_lossy(PROPOSAL_STATE).replacen(
+            "    WBKLF-BBBBB:",
+            "    WBKLF-ZZZZZ:",
+            1,
+        );
+        let store = YamlAdminServiceStore::from_bytes(
+            b"nodes: {}\ncircuits: {}",
+            proposal_state.as_bytes(),
+        )
+        .expect("Unable to create yaml admin store");
+
+        store
+            .add_proposal(create_expected_proposal())
+            .expect("Unable to add proposal");
+
+        // the proposed circuit of this proposal has the ID WBKLF-PqfoE
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+
+        let vote = |voter_node_id: &str| VoteRecord {
+            public_key: vec![1, 2, 3],
+            vote: Vote::Accept,
+            voter_node_id: voter_node_id.to_string(),
+        };
+        let mut voted = create_expected_proposal();
+        voted.circuit_id = "WBKLF-FFFFF".to_string();
+        voted.circuit.circuit_id = "WBKLF-FFFFF".to_string();
+        voted.add_vote(vote("bubba-node-000"));
+        voted.add_vote(vote("bubba-node-000"));
+        voted.add_vote(vote("unknown-node-000"));
+        voted.requester = vec![1, 2, 3];
+        store.add_proposal(voted).expect("Unable to add proposal");
+
+        assert_eq!(
+            store.audit_proposals().expect("Unable to audit proposals"),
+            vec![
+                ProposalWarning::MismatchedProposedCircuitId {
+                    proposal_id: "WBKLF-CCCCC".to_string(),
+                    proposed_circuit_id: "WBKLF-PqfoE".to_string(),
+                },
+                ProposalWarning::DuplicateVoter {
+                    proposal_id: "WBKLF-FFFFF".to_string(),
+                    node_id: "bubba-node-000".to_string(),
+                },
+                ProposalWarning::NonMemberVoter {
+                    proposal_id: "WBKLF-FFFFF".to_string(),
+                    node_id: "unknown-node-000".to_string(),
+                },
+                ProposalWarning::MalformedRequesterKey {
+                    proposal_id: "WBKLF-FFFFF".to_string(),
+                },
+                ProposalWarning::MismatchedProposalId {
+                    key: "WBKLF-ZZZZZ".to_string(),
+                    circuit_id: "WBKLF-BBBBB".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,549 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An append-only log of the changes made to a `YamlAdminServiceStore`
+//!
+//! Each change is appended to the log as a single JSON record per line and synced to disk before
+//! the operation that made it returns. The state files are only rewritten at checkpoints, after
+//! which the log is truncated. When the store is created, any records in the log are replayed
+//! over the state read from the files.
+//!
+//! Changes are logged for the entries a mutation reports changing, as the entry's value after the
+//! change, so appending does not depend on the size of the state. Archived proposals are logged
+//! along with the circuits, nodes and proposals.
+
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::sync::{Mutex, MutexGuard};
+
+use super::error::YamlAdminStoreError;
+use super::{ProposalState, YamlState};
+use crate::admin::store::{Circuit, CircuitNode, CircuitProposal};
+
+/// An entry of the store's state changed by a mutation, identified by its ID
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum StateChange {
+    /// The whole state was replaced, so the change is written to the state files rather than
+    /// logged
+    State,
+    Node(String),
+    Circuit(String),
+    Proposal(String),
+    ArchivedProposal(String),
+}
+
+/// A single change to the store's state
+#[derive(Debug, Serialize, Deserialize)]
+enum WalRecord {
+    PutNode(CircuitNode),
+    RemoveNode(String),
+    PutCircuit(Circuit),
+    RemoveCircuit(String),
+    PutProposal {
+        proposal: CircuitProposal,
+        sequence: Option<u64>,
+    },
+    RemoveProposal(String),
+    PutArchivedProposal {
+        proposal: CircuitProposal,
+        sequence: Option<u64>,
+    },
+}
+
+/// The write-ahead log of a `YamlAdminServiceStore`
+pub(super) struct WriteAheadLog {
+    path: String,
+    checkpoint_interval: usize,
+    /// The number of records in the log since the last checkpoint
+    records: Mutex<usize>,
+}
+
+impl WriteAheadLog {
+    /// Creates a write-ahead log at the given path
+    ///
+    /// # Arguments
+    ///
+    ///  * `path` - The path to the log file
+    ///  * `checkpoint_interval` - The number of records after which the state files are rewritten
+    ///    and the log is truncated
+    pub(super) fn new(path: String, checkpoint_interval: usize) -> Self {
+        WriteAheadLog {
+            path,
+            checkpoint_interval,
+            records: Mutex::new(0),
+        }
+    }
+
+    /// Appends a record of each changed entry's value in the given state, syncing the log to disk
+    /// before returning
+    ///
+    /// # Arguments
+    ///
+    ///  * `changes` - The entries changed since the state was last logged
+    ///  * `state` - The state after the changes
+    ///
+    /// Returns whether a checkpoint is due
+    pub(super) fn append(
+        &self,
+        changes: &[StateChange],
+        state: &YamlState,
+    ) -> Result<bool, YamlAdminStoreError> {
+        let mut logged_records = self.lock_records()?;
+
+        let records = changes
+            .iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|change| record_change(change, state))
+            .collect::<Vec<_>>();
+        if !records.is_empty() {
+            let mut output = Vec::new();
+            for record in records.iter() {
+                serde_json::to_writer(&mut output, record).map_err(|err| {
+                    YamlAdminStoreError::general_error_with_source(
+                        "Failed to write write-ahead log record",
+                        Box::new(err),
+                    )
+                })?;
+                output.push(b'\n');
+            }
+
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|err| {
+                    YamlAdminStoreError::general_error_with_source(
+                        "Failed to open write-ahead log",
+                        Box::new(err),
+                    )
+                })?;
+
+            file.write_all(&output)
+                .and_then(|_| file.sync_data())
+                .map_err(|err| {
+                    YamlAdminStoreError::general_error_with_source(
+                        "Failed to append to write-ahead log",
+                        Box::new(err),
+                    )
+                })?;
+        }
+
+        *logged_records += records.len();
+
+        Ok(*logged_records >= self.checkpoint_interval)
+    }
+
+    /// Applies the records in the log to the given state, returning the number of records applied
+    ///
+    /// A final record that was only partly written, because of a crash while it was appended, is
+    /// ignored. Returns an error if any other record cannot be parsed.
+    pub(super) fn replay(&self, state: &mut YamlState) -> Result<usize, YamlAdminStoreError> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(err) => {
+                return Err(YamlAdminStoreError::general_error_with_source(
+                    "Failed to read write-ahead log",
+                    Box::new(err),
+                ))
+            }
+        };
+
+        let mut circuit_state = state.circuit_state.clone();
+        let mut applied = 0;
+        let mut lines = contents
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .peekable();
+
+        while let Some(line) = lines.next() {
+            let record: WalRecord = match serde_json::from_slice(line) {
+                Ok(record) => record,
+                Err(_) if lines.peek().is_none() && !contents.ends_with(b"\n") => {
+                    warn!("Ignoring partly written record at the end of the write-ahead log");
+                    break;
+                }
+                Err(err) => {
+                    return Err(YamlAdminStoreError::general_error_with_source(
+                        "Failed to parse write-ahead log record",
+                        Box::new(err),
+                    ))
+                }
+            };
+
+            match record {
+                WalRecord::PutNode(node) => {
+                    circuit_state.nodes.insert(node.id.to_string(), node);
+                }
+                WalRecord::RemoveNode(node_id) => {
+                    circuit_state.nodes.remove(&node_id);
+                }
+                WalRecord::PutCircuit(circuit) => {
+                    circuit_state
+                        .circuits
+                        .insert(circuit.id.to_string(), circuit);
+                }
+                WalRecord::RemoveCircuit(circuit_id) => {
+                    circuit_state.circuits.remove(&circuit_id);
+                }
+                WalRecord::PutProposal { proposal, sequence } => {
+                    state
+                        .proposal_state
+                        .insert_proposal_with_sequence(proposal, sequence);
+                }
+                WalRecord::RemoveProposal(proposal_id) => {
+                    state.proposal_state.remove_proposal(&proposal_id);
+                }
+                WalRecord::PutArchivedProposal { proposal, sequence } => {
+                    state
+                        .archived_proposal_state
+                        .insert_proposal_with_sequence(proposal, sequence);
+                }
+            }
+            applied += 1;
+        }
+
+        state.replace_circuit_state(circuit_state);
+
+        Ok(applied)
+    }
+
+    /// Records that the state has been written to the state files, truncating the log
+    pub(super) fn checkpoint(&self) -> Result<(), YamlAdminStoreError> {
+        let mut logged_records = self.lock_records()?;
+
+        File::create(&self.path)
+            .and_then(|file| file.sync_all())
+            .map_err(|err| {
+                YamlAdminStoreError::general_error_with_source(
+                    "Failed to truncate write-ahead log",
+                    Box::new(err),
+                )
+            })?;
+
+        *logged_records = 0;
+
+        Ok(())
+    }
+
+    fn lock_records(&self) -> Result<MutexGuard<'_, usize>, YamlAdminStoreError> {
+        self.records.lock().map_err(|_| {
+            YamlAdminStoreError::general_error("Write-ahead log's internal lock poisoned")
+        })
+    }
+}
+
+/// Returns the record of a changed entry's value in the given state: a put record if the entry
+/// exists, or a remove record if it does not. Returns `None` for an archived proposal that does not
+/// exist, as archived proposals are never removed, and for a replaced state, which is not logged.
+fn record_change(change: &StateChange, state: &YamlState) -> Option<WalRecord> {
+    let circuit_state = &state.circuit_state;
+    let record = match change {
+        StateChange::State => return None,
+        StateChange::Node(node_id) => match circuit_state.nodes.get(node_id) {
+            Some(node) => WalRecord::PutNode(node.clone()),
+            None => WalRecord::RemoveNode(node_id.to_string()),
+        },
+        StateChange::Circuit(circuit_id) => match circuit_state.circuits.get(circuit_id) {
+            Some(circuit) => WalRecord::PutCircuit(circuit.clone()),
+            None => WalRecord::RemoveCircuit(circuit_id.to_string()),
+        },
+        StateChange::Proposal(proposal_id) => {
+            match proposal_with_sequence(&state.proposal_state, proposal_id) {
+                Some((proposal, sequence)) => WalRecord::PutProposal { proposal, sequence },
+                None => WalRecord::RemoveProposal(proposal_id.to_string()),
+            }
+        }
+        StateChange::ArchivedProposal(proposal_id) => {
+            let (proposal, sequence) =
+                proposal_with_sequence(&state.archived_proposal_state, proposal_id)?;
+            WalRecord::PutArchivedProposal { proposal, sequence }
+        }
+    };
+
+    Some(record)
+}
+
+/// Returns a proposal and its sequence number, if the proposal exists
+fn proposal_with_sequence(
+    proposal_state: &ProposalState,
+    proposal_id: &str,
+) -> Option<(CircuitProposal, Option<u64>)> {
+    let proposal = proposal_state.proposals.get(proposal_id)?.clone();
+    let sequence = proposal_state.submission_order.get(proposal_id).copied();
+    Some((proposal, sequence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempdir::TempDir;
+
+    use super::super::tests::{
+        create_expected_proposal, new_circuit, new_proposal, state_file_paths, store_builder,
+        write_file, CIRCUIT_STATE, PROPOSAL_STATE,
+    };
+    use super::super::{YamlAdminServiceStore, YamlAdminServiceStoreBuilder};
+    use crate::admin::store::{AdminServiceStore, ServiceId};
+
+    // Test that changes recorded in the write-ahead log are applied when the store is created
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Create a store with a write-ahead log that does not checkpoint, then add a circuit and a
+    //    proposal and remove the existing proposal
+    // 3. Drop the store without a checkpoint, simulating a crash, and verify the state files do
+    //    not contain the changes
+    // 4. Append a partly written record to the log, as left by a crash during an append
+    // 5. Create the store again with the log and verify the changes were applied
+    // 6. Verify the state files were rewritten and the log was truncated
+    #[test]
+    fn test_write_ahead_log_replay() {
+        let (temp_dir, circuit_path, proposals_path) =
+            state_file_paths("test_write_ahead_log_replay");
+
+        let wal_path = temp_dir
+            .path()
+            .join("admin.wal")
+            .to_str()
+            .expect("Failed to get path")
+            .to_string();
+
+        // write yaml files to temp_dir
+        write_file(CIRCUIT_STATE, &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let build_store = || {
+            store_builder(&circuit_path, &proposals_path)
+                .with_write_ahead_log(&wal_path)
+                .with_checkpoint_interval(usize::MAX)
+                .build()
+                .expect("Unable to create yaml admin store")
+        };
+
+        let (new_circuit, new_node) = new_circuit();
+        {
+            let store = build_store();
+            store
+                .add_circuit(new_circuit, vec![new_node.clone()])
+                .expect("Unable to add circuit");
+            store
+                .add_proposal(new_proposal())
+                .expect("Unable to add proposal");
+            store
+                .remove_proposal("WBKLF-BBBBB")
+                .expect("Unable to remove proposal");
+        }
+
+        assert_eq!(
+            fs::read(&circuit_path).expect("Unable to read circuit file"),
+            CIRCUIT_STATE
+        );
+        assert_eq!(
+            fs::read(&proposals_path).expect("Unable to read proposal file"),
+            PROPOSAL_STATE
+        );
+
+        let mut wal_file = fs::OpenOptions::new()
+            .append(true)
+            .open(&wal_path)
+            .expect("Unable to open write-ahead log");
+        wal_file
+            .write_all(b"{\"RemoveCircuit\":\"WBKL")
+            .expect("Unable to write to write-ahead log");
+
+        let store = build_store();
+
+        assert!(store
+            .fetch_circuit("WBKLF-AAAAA")
+            .expect("Unable to fetch circuit")
+            .is_some());
+        assert!(store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .is_some());
+        assert_eq!(
+            store
+                .fetch_node("new-node-000")
+                .expect("Unable to fetch node"),
+            Some(new_node)
+        );
+        assert!(store
+            .fetch_service(&ServiceId::new(
+                "a000".to_string(),
+                "WBKLF-DDDDD".to_string()
+            ))
+            .expect("Unable to fetch service")
+            .is_some());
+        assert_eq!(
+            store
+                .fetch_proposal("WBKLF-CCCCC")
+                .expect("Unable to fetch proposal"),
+            Some(new_proposal())
+        );
+        assert_eq!(
+            store
+                .fetch_proposal("WBKLF-BBBBB")
+                .expect("Unable to fetch proposal"),
+            None
+        );
+
+        assert_eq!(
+            fs::metadata(&wal_path)
+                .expect("Unable to read write-ahead log metadata")
+                .len(),
+            0
+        );
+        let (file_store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        assert!(file_store
+            .fetch_circuit("WBKLF-DDDDD")
+            .expect("Unable to fetch circuit")
+            .is_some());
+        assert_eq!(
+            file_store
+                .fetch_proposal("WBKLF-BBBBB")
+                .expect("Unable to fetch proposal"),
+            None
+        );
+    }
+
+    // Test that replaying the write-ahead log restores proposals with their logged sequence numbers
+    // and restores archived proposals
+    //
+    // 1. Setup the temp directory with existing proposal state
+    // 2. Create a store with proposal archiving and a write-ahead log that does not checkpoint,
+    //    then add a proposal and upgrade the existing proposal, archiving it
+    // 3. Drop the store without a checkpoint and create it again with the log
+    // 4. Verify the proposals and archived proposals have the same sequence numbers and the
+    //    upgraded proposal is archived
+    #[test]
+    fn test_write_ahead_log_replay_sequence_and_archive() {
+        let temp_dir = TempDir::new("test_write_ahead_log_replay_sequence_and_archive")
+            .expect("Failed to create temp dir");
+        let path = |file_name: &str| {
+            temp_dir
+                .path()
+                .join(file_name)
+                .to_str()
+                .expect("Failed to get path")
+                .to_string()
+        };
+        let proposals_path = path("circuit_proposals.yaml");
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let builder = YamlAdminServiceStoreBuilder::new()
+            .with_circuit_file_path(&path("circuits.yaml"))
+            .with_proposal_file_path(&proposals_path)
+            .with_archived_proposals(true)
+            .with_write_ahead_log(&path("admin.wal"))
+            .with_checkpoint_interval(usize::MAX);
+        let submission_orders = |store: &YamlAdminServiceStore| {
+            let state = store.shared_state().expect("Unable to get state");
+            (
+                state.proposal_state.submission_order.clone(),
+                state.archived_proposal_state.submission_order.clone(),
+            )
+        };
+
+        let logged_orders = {
+            let store = builder
+                .clone()
+                .build()
+                .expect("Unable to create yaml admin store");
+            store
+                .add_proposal(new_proposal())
+                .expect("Unable to add proposal");
+            store
+                .upgrade_proposal_to_circuit("WBKLF-BBBBB")
+                .expect("Unable to upgrade proposal to circuit");
+            submission_orders(&store)
+        };
+        assert!(!logged_orders.0.is_empty());
+        assert!(!logged_orders.1.is_empty());
+
+        let store = builder.build().expect("Unable to create yaml admin store");
+
+        assert_eq!(submission_orders(&store), logged_orders);
+        assert_eq!(
+            store.fetch_archived_proposal("WBKLF-BBBBB").unwrap(),
+            Some(create_expected_proposal())
+        );
+        assert!(store.fetch_proposal("WBKLF-CCCCC").unwrap().is_some());
+    }
+
+    // Test that the state files are rewritten once the checkpoint interval is reached
+    //
+    // 1. Setup the temp directory with existing state
+    // 2. Create a store with a write-ahead log that checkpoints every two records
+    // 3. Add a proposal and verify the proposal file was not rewritten
+    // 4. Remove a proposal and verify the proposal file was rewritten and the log truncated
+    #[test]
+    fn test_write_ahead_log_checkpoint() {
+        let (temp_dir, circuit_path, proposals_path) =
+            state_file_paths("test_write_ahead_log_checkpoint");
+
+        let wal_path = temp_dir
+            .path()
+            .join("admin.wal")
+            .to_str()
+            .expect("Failed to get path")
+            .to_string();
+
+        // write yaml files to temp_dir
+        write_file(CIRCUIT_STATE, &circuit_path);
+        write_file(PROPOSAL_STATE, &proposals_path);
+
+        let store = store_builder(&circuit_path, &proposals_path)
+            .with_write_ahead_log(&wal_path)
+            .with_checkpoint_interval(2)
+            .build()
+            .expect("Unable to create yaml admin store");
+
+        store
+            .add_proposal(new_proposal())
+            .expect("Unable to add proposal");
+        assert_eq!(
+            fs::read(&proposals_path).expect("Unable to read proposal file"),
+            PROPOSAL_STATE
+        );
+        assert!(
+            fs::metadata(&wal_path)
+                .expect("Unable to read write-ahead log metadata")
+                .len()
+                > 0
+        );
+
+        store
+            .remove_proposal("WBKLF-BBBBB")
+            .expect("Unable to remove proposal");
+        assert_eq!(
+            fs::metadata(&wal_path)
+                .expect("Unable to read write-ahead log metadata")
+                .len(),
+            0
+        );
+
+        let (file_store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        let proposal_ids: Vec<String> = file_store
+            .list_proposals(&[])
+            .expect("Unable to list proposals")
+            .map(|proposal| proposal.circuit_id)
+            .collect();
+        assert_eq!(proposal_ids, vec!["WBKLF-CCCCC".to_string()]);
+    }
+}
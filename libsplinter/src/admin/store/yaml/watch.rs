@@ -0,0 +1,236 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Watching the state files of a `YamlAdminServiceStore` for changes made outside of the store
+
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::error::YamlAdminStoreError;
+use super::YamlAdminServiceStore;
+
+/// How long file events are collected before they are delivered, so that a file replaced by
+/// several operations only causes one reload
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(100);
+
+/// How often the watcher thread checks whether it has been asked to shut down
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Watches the circuit and proposal state files of a `YamlAdminServiceStore` and reloads the
+/// store when either is changed by something other than the store
+///
+/// The watcher is created by `YamlAdminServiceStore::watch_state_files`. It stops watching and
+/// its thread is shut down when it is dropped, or once the store has been dropped.
+pub struct StateFileWatcher {
+    shutdown: Arc<AtomicBool>,
+    watcher: Option<RecommendedWatcher>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StateFileWatcher {
+    /// Starts watching the given state files of a store
+    ///
+    /// The directories containing the files are watched, rather than the files themselves,
+    /// because the files are replaced, not modified, when they are written.
+    ///
+    /// # Arguments
+    ///
+    ///  * `store` - The store to reload when a state file changes
+    ///  * `paths` - The paths of the state files
+    pub(super) fn new(
+        store: Weak<YamlAdminServiceStore>,
+        paths: &[&str],
+    ) -> Result<Self, YamlAdminStoreError> {
+        let (sender, receiver) = channel();
+        let mut file_watcher = watcher(sender, DEBOUNCE_DELAY).map_err(|err| {
+            YamlAdminStoreError::general_error_with_source(
+                "Failed to create state file watcher",
+                Box::new(err),
+            )
+        })?;
+
+        let mut directories = BTreeSet::new();
+        let mut file_names = BTreeSet::new();
+        for path in paths.iter().map(Path::new) {
+            let directory = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            directories.insert(directory);
+
+            if let Some(file_name) = path.file_name() {
+                file_names.insert(file_name.to_os_string());
+            }
+        }
+
+        for directory in directories.iter() {
+            file_watcher
+                .watch(directory, RecursiveMode::NonRecursive)
+                .map_err(|err| {
+                    YamlAdminStoreError::general_error_with_source(
+                        &format!("Failed to watch directory '{}'", directory.display()),
+                        Box::new(err),
+                    )
+                })?;
+        }
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread_shutdown = Arc::clone(&shutdown);
+        let thread = thread::Builder::new()
+            .name("YamlAdminServiceStore-Watcher".to_string())
+            .spawn(move || watch_loop(store, receiver, file_names, thread_shutdown))
+            .map_err(|err| {
+                YamlAdminStoreError::general_error_with_source(
+                    "Failed to start state file watcher thread",
+                    Box::new(err),
+                )
+            })?;
+
+        Ok(StateFileWatcher {
+            shutdown,
+            watcher: Some(file_watcher),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for StateFileWatcher {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Dropping the file watcher stops the delivery of events
+        self.watcher.take();
+
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("State file watcher thread panicked");
+            }
+        }
+    }
+}
+
+/// Reloads the store whenever an event for one of the state files is received, until the
+/// watcher is shut down or the store is dropped
+fn watch_loop(
+    store: Weak<YamlAdminServiceStore>,
+    receiver: Receiver<DebouncedEvent>,
+    file_names: BTreeSet<OsString>,
+    shutdown: Arc<AtomicBool>,
+) {
+    while !shutdown.load(Ordering::SeqCst) {
+        let event = match receiver.recv_timeout(SHUTDOWN_POLL_INTERVAL) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let changed_path = match &event {
+            DebouncedEvent::Create(path)
+            | DebouncedEvent::Write(path)
+            | DebouncedEvent::Rename(_, path) => Some(path),
+            DebouncedEvent::Rescan => None,
+            DebouncedEvent::Error(err, _) => {
+                warn!("Error watching state files: {}", err);
+                continue;
+            }
+            _ => continue,
+        };
+
+        let is_state_file = changed_path
+            .map(|path| {
+                path.file_name()
+                    .map(|file_name| file_names.contains(file_name))
+                    .unwrap_or(false)
+            })
+            // a rescan means events may have been missed, so the files are checked
+            .unwrap_or(true);
+        if !is_state_file {
+            continue;
+        }
+
+        let store = match store.upgrade() {
+            Some(store) => store,
+            None => break,
+        };
+
+        match store.reload_if_changed() {
+            Ok(true) => debug!("Reloaded state files changed outside of the store"),
+            Ok(false) => (),
+            Err(err) => warn!("Unable to reload changed state files: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    use super::super::tests::{new_circuit, setup_state_files};
+    use crate::admin::store::AdminServiceStore;
+
+    // Test that a watched store is reloaded when its state files are changed outside of the store,
+    // and that it stops being reloaded once the watcher is dropped
+    //
+    // 1. Setup the temp directory with existing state and watch the state files of a store
+    // 2. Remove the existing circuit through a second store using the same files
+    // 3. Check that the watched store no longer has the circuit, without calling reload
+    // 4. Drop the watcher and add a circuit through the second store
+    // 5. Check that the watched store does not have the new circuit
+    #[cfg(all(feature = "admin-store-watch", target_os = "linux"))]
+    #[test]
+    fn test_watch_state_files() {
+        let (_temp_dir, circuit_path, proposals_path) = setup_state_files("test_watch_state_files");
+
+        let (store, _) = YamlAdminServiceStore::new(circuit_path.clone(), proposals_path.clone())
+            .expect("Unable to create yaml admin store");
+        let store = Arc::new(store);
+        let watcher = store
+            .watch_state_files()
+            .expect("Unable to watch state files");
+
+        let (other_store, _) = YamlAdminServiceStore::new(circuit_path, proposals_path)
+            .expect("Unable to create yaml admin store");
+        other_store
+            .remove_circuit("WBKLF-AAAAA")
+            .expect("Unable to remove circuit");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while store.fetch_circuit("WBKLF-AAAAA").unwrap().is_some() {
+            assert!(
+                Instant::now() < deadline,
+                "Store was not reloaded after the circuit state file changed"
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        drop(watcher);
+
+        let (circuit, node) = new_circuit();
+        other_store
+            .add_circuit(circuit, vec![node])
+            .expect("Unable to add circuit");
+
+        thread::sleep(Duration::from_millis(500));
+        assert!(store.fetch_circuit("WBKLF-DDDDD").unwrap().is_none());
+    }
+}